@@ -13,9 +13,42 @@ extern crate merlin;
 use merlin::Transcript;
 
 extern crate bulletproofs;
+use bulletproofs::range_proof_mpc::dealer::{Dealer, DealerAwaitingProofShares};
+use bulletproofs::range_proof_mpc::messages::ProofShare;
+use bulletproofs::range_proof_mpc::party::Party;
 use bulletproofs::RangeProof;
 use bulletproofs::{BulletproofGens, PedersenGens};
 
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts the bytes allocated, so that the benchmarks can report the
+/// heap traffic of the code they time.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Returns the result of `f`, and the number of bytes it allocated.
+fn allocated_by<T, F: FnOnce() -> T>(f: F) -> (T, usize) {
+    let before = ALLOCATED.load(Ordering::SeqCst);
+    let result = f();
+    (result, ALLOCATED.load(Ordering::SeqCst) - before)
+}
+
 static AGGREGATION_SIZES: [usize; 6] = [1, 2, 4, 8, 16, 32];
 
 fn create_aggregated_rangeproof_helper(n: usize, c: &mut Criterion) {
@@ -66,6 +99,38 @@ fn create_aggregated_rangeproof_n_64(c: &mut Criterion) {
     create_aggregated_rangeproof_helper(64, c);
 }
 
+/// Proof creation for a large aggregation, where running the parties
+/// in parallel should pay off.  Compare the results with and without
+/// `--features rayon`.
+fn create_aggregated_rangeproof_n_64_m_64(c: &mut Criterion) {
+    let (n, m) = (64, 64);
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(n, m);
+    let mut rng = rand::thread_rng();
+
+    let values: Vec<u64> = (0..m).map(|_| rng.gen()).collect();
+    let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
+
+    c.bench_function(
+        "Aggregated 64-bit rangeproof creation, 64 parties",
+        move |b| {
+            b.iter(|| {
+                // Each proof creation requires a clean transcript.
+                let mut transcript = Transcript::new(b"AggregateRangeProofBenchmark");
+
+                RangeProof::prove_multiple(
+                    &bp_gens,
+                    &pc_gens,
+                    &mut transcript,
+                    &values,
+                    &blindings,
+                    n,
+                )
+            })
+        },
+    );
+}
+
 fn verify_aggregated_rangeproof_helper(n: usize, c: &mut Criterion) {
     let label = format!("Aggregated {}-bit rangeproof verification", n);
 
@@ -118,6 +183,124 @@ fn verify_aggregated_rangeproof_n_64(c: &mut Criterion) {
     verify_aggregated_rangeproof_helper(64, c);
 }
 
+/// Verification of a large aggregation, where splitting the
+/// multiscalar multiplication across threads should pay off.  Compare
+/// the results with and without `--features rayon`.
+fn verify_aggregated_rangeproof_n_64_m_32(c: &mut Criterion) {
+    let (n, m) = (64, 32);
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(n, m);
+    let mut rng = rand::thread_rng();
+
+    let values: Vec<u64> = (0..m).map(|_| rng.gen()).collect();
+    let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
+
+    let mut transcript = Transcript::new(b"AggregateRangeProofBenchmark");
+    let (proof, value_commitments) = RangeProof::prove_multiple(
+        &bp_gens,
+        &pc_gens,
+        &mut transcript,
+        &values,
+        &blindings,
+        n,
+    )
+    .unwrap();
+
+    c.bench_function(
+        "Aggregated 64-bit rangeproof verification, 32 parties",
+        move |b| {
+            b.iter(|| {
+                // Each proof verification requires a clean transcript.
+                let mut transcript = Transcript::new(b"AggregateRangeProofBenchmark");
+
+                proof.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, n)
+            })
+        },
+    );
+}
+
+/// Runs the MPC protocol for `m` parties up to the proof shares.
+fn proof_shares<'a, 'b>(
+    bp_gens: &'b BulletproofGens,
+    pc_gens: &'b PedersenGens,
+    transcript: &'a mut Transcript,
+    n: usize,
+    m: usize,
+) -> (DealerAwaitingProofShares<'a, 'b>, Vec<ProofShare>) {
+    let mut rng = rand::thread_rng();
+
+    let dealer = Dealer::new(bp_gens, pc_gens, transcript, n, m).unwrap();
+    let (parties, bit_commitments): (Vec<_>, Vec<_>) = (0..m)
+        .map(|j| {
+            Party::new(bp_gens, pc_gens, rng.gen(), Scalar::random(&mut rng), n)
+                .unwrap()
+                .assign_position(j)
+                .unwrap()
+        })
+        .unzip();
+    let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+    let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+        .into_iter()
+        .map(|p| p.apply_challenge(&bit_challenge))
+        .unzip();
+    let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+    let proof_shares = parties
+        .into_iter()
+        .map(|p| p.apply_challenge(&poly_challenge).unwrap())
+        .collect();
+
+    (dealer, proof_shares)
+}
+
+/// Assembly of trusted proof shares, by reference and by value.  The
+/// bytes allocated by each are printed before the timings, which
+/// include running the rest of the protocol.
+fn assemble_trusted_shares_n_64(c: &mut Criterion) {
+    let n = 64;
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(n, 32);
+
+    for &m in AGGREGATION_SIZES.iter() {
+        let mut transcript = Transcript::new(b"AggregateRangeProofBenchmark");
+        let (dealer, shares) = proof_shares(&bp_gens, &pc_gens, &mut transcript, n, m);
+        let (_, borrowed) = allocated_by(|| dealer.receive_trusted_shares(&shares));
+
+        let mut transcript = Transcript::new(b"AggregateRangeProofBenchmark");
+        let (dealer, shares) = proof_shares(&bp_gens, &pc_gens, &mut transcript, n, m);
+        let (_, owned) = allocated_by(|| dealer.receive_trusted_shares_owned(shares));
+
+        println!(
+            "Trusted share assembly, {} parties: {} bytes allocated by reference, {} by value",
+            m, borrowed, owned
+        );
+    }
+
+    c.bench_function_over_inputs(
+        "Aggregated 64-bit trusted share assembly, by reference",
+        move |b, &&m| {
+            b.iter(|| {
+                let mut transcript = Transcript::new(b"AggregateRangeProofBenchmark");
+                let (dealer, shares) = proof_shares(&bp_gens, &pc_gens, &mut transcript, n, m);
+                dealer.receive_trusted_shares(&shares)
+            })
+        },
+        &AGGREGATION_SIZES,
+    );
+
+    let bp_gens = BulletproofGens::new(n, 32);
+    c.bench_function_over_inputs(
+        "Aggregated 64-bit trusted share assembly, by value",
+        move |b, &&m| {
+            b.iter(|| {
+                let mut transcript = Transcript::new(b"AggregateRangeProofBenchmark");
+                let (dealer, shares) = proof_shares(&bp_gens, &pc_gens, &mut transcript, n, m);
+                dealer.receive_trusted_shares_owned(shares)
+            })
+        },
+        &AGGREGATION_SIZES,
+    );
+}
+
 criterion_group! {
     name = create_rp;
     config = Criterion::default().sample_size(10);
@@ -126,6 +309,7 @@ criterion_group! {
     create_aggregated_rangeproof_n_16,
     create_aggregated_rangeproof_n_32,
     create_aggregated_rangeproof_n_64,
+    create_aggregated_rangeproof_n_64_m_64,
 }
 
 criterion_group! {
@@ -136,6 +320,14 @@ criterion_group! {
     verify_aggregated_rangeproof_n_16,
     verify_aggregated_rangeproof_n_32,
     verify_aggregated_rangeproof_n_64,
+    verify_aggregated_rangeproof_n_64_m_32,
+}
+
+criterion_group! {
+    name = assemble_rp;
+    config = Criterion::default().sample_size(10);
+    targets =
+    assemble_trusted_shares_n_64,
 }
 
-criterion_main!(create_rp, verify_rp);
+criterion_main!(create_rp, verify_rp, assemble_rp);
@@ -0,0 +1,6 @@
+//! Re-exports the `bulletproofs` JavaScript bindings, so that
+//! `wasm-pack` can build them into a package for the browser.
+
+extern crate bulletproofs;
+
+pub use bulletproofs::wasm::*;
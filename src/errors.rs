@@ -1,5 +1,7 @@
 //! Errors related to proving and verifying proofs.
 
+use alloc::vec::Vec;
+
 /// Represents an error in proof creation, verification, or parsing.
 #[derive(Fail, Clone, Debug, Eq, PartialEq)]
 pub enum ProofError {
@@ -9,21 +11,47 @@ pub enum ProofError {
     /// This error occurs when the proof encoding is malformed.
     #[fail(display = "Proof data could not be parsed.")]
     FormatError,
+    /// This error occurs when the proof encoding contains a point
+    /// which is not a valid compressed Ristretto point.
+    #[fail(display = "Proof contains an invalid point.")]
+    InvalidPoint,
     /// This error occurs during proving if the number of blinding
     /// factors does not match the number of values.
     #[fail(display = "Wrong number of blinding factors supplied.")]
     WrongNumBlindingFactors,
+    /// This error occurs when the number of per-party bitsizes does
+    /// not match the number of values or value commitments.
+    #[fail(display = "Wrong number of bitsizes supplied.")]
+    WrongNumBitsizes,
+    /// This error occurs when attempting to create a bounded
+    /// rangeproof for a value outside of its bounds.
+    #[fail(display = "Value is outside of the bounds.")]
+    ValueOutOfBounds,
+    /// This error occurs when the sum of the values to be proved in
+    /// range does not fit in the range.
+    #[fail(display = "Sum of values overflows the range.")]
+    ValueOverflow,
     /// This error occurs when attempting to create a proof with
-    /// bitsize other than \\(8\\), \\(16\\), \\(32\\), or \\(64\\).
-    #[fail(display = "Invalid bitsize, must have n = 8,16,32,64.")]
+    /// bitsize other than \\(8\\), \\(16\\), \\(32\\), \\(64\\), or \\(128\\).
+    #[fail(display = "Invalid bitsize, must have n = 8,16,32,64,128.")]
     InvalidBitsize,
-    /// This error occurs when attempting to create an aggregated
-    /// proof with non-power-of-two aggregation size.
-    #[fail(display = "Invalid aggregation size, m must be a power of 2.")]
+    /// This error occurs when attempting to create or verify an
+    /// aggregated proof with no values.
+    #[fail(display = "Invalid aggregation size, m must be nonzero.")]
     InvalidAggregation,
     /// This error occurs when there are insufficient generators for the proof.
     #[fail(display = "Invalid generators size, too few generators for proof")]
     InvalidGeneratorsLength,
+    /// This error occurs during batch verification if the number of
+    /// transcripts does not match the number of proofs.
+    #[fail(display = "Wrong number of transcripts supplied.")]
+    WrongNumTranscripts,
+    /// This error occurs when one or more proofs in a batch fail to verify.
+    #[fail(display = "Batch verification failed for proofs {:?}.", bad_proofs)]
+    BatchVerificationError {
+        /// A vector with the indexes of the proofs which failed to verify.
+        bad_proofs: Vec<usize>,
+    },
     /// This error results from an internal error during proving.
     ///
     /// The single-party prover is implemented by performing
@@ -37,14 +65,39 @@ pub enum ProofError {
 impl From<MPCError> for ProofError {
     fn from(e: MPCError) -> ProofError {
         match e {
-            MPCError::InvalidBitsize => ProofError::InvalidBitsize,
-            MPCError::InvalidAggregation => ProofError::InvalidAggregation,
+            MPCError::InvalidBitsize | MPCError::ZeroBitsize => ProofError::InvalidBitsize,
+            MPCError::InvalidAggregation | MPCError::EmptyAggregation => {
+                ProofError::InvalidAggregation
+            }
             MPCError::InvalidGeneratorsLength => ProofError::InvalidGeneratorsLength,
             _ => ProofError::ProvingError(e),
         }
     }
 }
 
+/// The part of a rangeproof's verification which failed, as reported
+/// by `RangeProof::diagnose_multiple_variable` with the `diagnostics`
+/// feature.
+///
+/// Determining the reason requires checking the polynomial identity
+/// for \\(t(x)\\) on its own, so verification itself only reports
+/// [`ProofError::VerificationError`].
+#[derive(Fail, Copy, Clone, Debug, Eq, PartialEq)]
+pub enum VerifyFailureReason {
+    /// The proof's evaluation \\(t(x)\\) is inconsistent with the
+    /// value commitments and the commitments \\(T_1, T_2\\).
+    #[fail(display = "t(x) polynomial identity mismatch.")]
+    PolynomialIdentity,
+    /// The polynomial identity holds, but the vectors committed to in
+    /// the proof do not satisfy the inner-product relation.
+    #[fail(display = "Inner product relation mismatch.")]
+    InnerProductRelation,
+    /// One of the proof's points or the value commitments failed to
+    /// decompress.
+    #[fail(display = "Point decompression failed.")]
+    PointDecompression,
+}
+
 /// Represents an error during the multiparty computation protocol for
 /// proof aggregation.
 ///
@@ -59,28 +112,53 @@ pub enum MPCError {
     #[fail(display = "Dealer gave a malicious challenge value.")]
     MaliciousDealer,
     /// This error occurs when attempting to create a proof with
-    /// bitsize other than \\(8\\), \\(16\\), \\(32\\), or \\(64\\).
-    #[fail(display = "Invalid bitsize, must have n = 8,16,32,64")]
+    /// bitsize other than \\(8\\), \\(16\\), \\(32\\), \\(64\\), or \\(128\\).
+    #[fail(display = "Invalid bitsize, must have n = 8,16,32,64,128")]
     InvalidBitsize,
+    /// This error occurs when attempting to create a proof with a
+    /// bitsize of zero.
+    #[fail(display = "Invalid bitsize, n must be nonzero")]
+    ZeroBitsize,
     /// This error occurs when attempting to create an aggregated
-    /// proof with non-power-of-two aggregation size.
+    /// proof with too many parties to pad the aggregation up to a
+    /// power of two.
     #[fail(display = "Invalid aggregation size, m must be a power of 2")]
     InvalidAggregation,
+    /// This error occurs when attempting to create an aggregated
+    /// proof with no parties.
+    #[fail(display = "Invalid aggregation size, m must be nonzero")]
+    EmptyAggregation,
     /// This error occurs when there are insufficient generators for the proof.
     #[fail(display = "Invalid generators size, too few generators for proof")]
     InvalidGeneratorsLength,
+    /// This error occurs when a [`DealerBuilder`](::range_proof_mpc::dealer::DealerBuilder)
+    /// is built without a transcript.
+    #[fail(display = "No transcript was given to the dealer")]
+    MissingTranscript,
+    /// This error occurs when a party is given a value commitment
+    /// which is not opened by its value and blinding factor.
+    #[fail(display = "Value commitment does not match the value and blinding")]
+    MismatchedCommitment,
     /// This error occurs when the dealer is given the wrong number of
-    /// value commitments.
-    #[fail(display = "Wrong number of value commitments")]
+    /// bit commitments.
+    #[fail(display = "Wrong number of bit commitments")]
     WrongNumBitCommitments,
     /// This error occurs when the dealer is given the wrong number of
     /// polynomial commitments.
-    #[fail(display = "Wrong number of value commitments")]
+    #[fail(display = "Wrong number of polynomial commitments")]
     WrongNumPolyCommitments,
     /// This error occurs when the dealer is given the wrong number of
     /// proof shares.
     #[fail(display = "Wrong number of proof shares")]
     WrongNumProofShares,
+    /// This error occurs when a strict dealer receives bit commitments
+    /// with identity or duplicate points.
+    #[fail(display = "Malformed bit commitments from parties {:?}", bad_parties)]
+    MalformedBitCommitments {
+        /// A vector with the indexes of the parties whose bit
+        /// commitments were malformed.
+        bad_parties: Vec<usize>,
+    },
     /// This error occurs when one or more parties submit malformed
     /// proof shares.
     #[fail(display = "Malformed proof shares from parties {:?}", bad_shares)]
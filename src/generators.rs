@@ -4,14 +4,23 @@
 #![allow(non_snake_case)]
 #![deny(missing_docs)]
 
+use alloc::vec::Vec;
+
 use curve25519_dalek::constants::RISTRETTO_BASEPOINT_COMPRESSED;
 use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
-use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::ristretto::VartimeRistrettoPrecomputation;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
 use curve25519_dalek::scalar::Scalar;
-use curve25519_dalek::traits::MultiscalarMul;
+use curve25519_dalek::traits::{MultiscalarMul, VartimePrecomputedMultiscalarMul};
 
 use digest::{ExtendableOutput, Input, XofReader};
 use sha3::{Sha3XofReader, Sha3_512, Shake256};
+use subtle::ConstantTimeEq;
+
+use serde::de::Error as DeserializeError;
+use serde::{Deserialize, Deserializer};
+
+use errors::ProofError;
 
 /// Represents a pair of base points for Pedersen commitments.
 ///
@@ -24,7 +33,19 @@ use sha3::{Sha3XofReader, Sha3_512, Shake256};
 /// * `B`: the `ristretto255` basepoint;
 /// * `B_blinding`: the result of `ristretto255` SHA3-512
 /// hash-to-group on input `B_bytes`.
-#[derive(Copy, Clone)]
+///
+/// Application-specific bases, such as a blinding base distinct per
+/// context, can be used by constructing a `PedersenGens` with those
+/// points directly.  The value base `B` commits to the values and,
+/// scaled by a challenge \\(w\\), to the inner product
+/// \\(Q = w \cdot B\\), while `B_blinding` only ever carries blinding
+/// factors.  The same bases are used for the value commitments, for
+/// the inner-product argument, and for verification, so a proof only
+/// verifies against the bases it was created with.
+///
+/// The bases are serialized as compressed points, and
+/// deserialization rejects points which fail to decompress.
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct PedersenGens {
     /// Base for the committed value
     pub B: RistrettoPoint,
@@ -34,9 +55,55 @@ pub struct PedersenGens {
 
 impl PedersenGens {
     /// Creates a Pedersen commitment using the value scalar and a blinding factor.
+    ///
+    /// Commitments are additively homomorphic: the sum of the
+    /// commitments to \\(v_1\\) and \\(v_2\\) is a commitment to
+    /// \\(v_1 + v_2\\), with the sum of their blinding factors.
     pub fn commit(&self, value: Scalar, blinding: Scalar) -> RistrettoPoint {
         RistrettoPoint::multiscalar_mul(&[value, blinding], &[self.B, self.B_blinding])
     }
+
+    /// Creates the compressed commitment to `value` with the given
+    /// `blinding` factor.
+    ///
+    /// This is the same value commitment \\(V\\) that a party proving
+    /// `value` with these generators sends to the dealer, so it can be
+    /// created ahead of the rangeproof.
+    pub fn commit_value(&self, value: u64, blinding: &Scalar) -> CompressedRistretto {
+        self.commit(Scalar::from(value), *blinding).compress()
+    }
+
+    /// Re-randomizes a Pedersen `commitment` by adding `delta` to its
+    /// blinding factor, without knowledge of the committed value.
+    ///
+    /// The result is a fresh commitment to the same value, opened by
+    /// the original blinding factor plus `delta`.  It is only
+    /// meaningful as a standalone commitment: re-randomizing a
+    /// commitment used in a rangeproof invalidates that proof.
+    pub fn rerandomize(&self, commitment: &RistrettoPoint, delta: &Scalar) -> RistrettoPoint {
+        commitment + delta * self.B_blinding
+    }
+
+    /// Checks whether `value` and `blinding` open the Pedersen
+    /// `commitment`.
+    ///
+    /// The recomputed commitment is compared in constant time, so
+    /// this is safe to call with secret `value` and `blinding`.  Only
+    /// the decompression of `commitment` itself, which is public,
+    /// is variable-time.
+    pub fn verify_opening(
+        &self,
+        commitment: &CompressedRistretto,
+        value: u64,
+        blinding: &Scalar,
+    ) -> bool {
+        let V = match commitment.decompress() {
+            Some(V) => V,
+            None => return false,
+        };
+
+        self.commit(Scalar::from(value), *blinding).ct_eq(&V).into()
+    }
 }
 
 impl Default for PedersenGens {
@@ -53,6 +120,10 @@ impl Default for PedersenGens {
 /// The `GeneratorsChain` creates an arbitrary-long sequence of
 /// orthogonal generators.  The sequence can be deterministically
 /// produced starting with an arbitrary point.
+///
+/// The chain for `label` is the sequence of `ristretto255`
+/// hash-to-group outputs on consecutive 64-byte blocks of the
+/// SHAKE256 output on input `b"GeneratorsChain" || label`.
 struct GeneratorsChain {
     reader: Sha3XofReader,
 }
@@ -116,7 +187,16 @@ impl Iterator for GeneratorsChain {
 /// chain, and even forward-compatible to multiparty aggregation of
 /// constraint system proofs, since the generators are namespaced by
 /// their party index.
-#[derive(Clone)]
+///
+/// # Serialization
+///
+/// Deriving the generators takes a hash-to-group operation per
+/// point, so a `BulletproofGens` can be serialized to avoid
+/// recomputing a large set on startup.  The capacities are stored
+/// with the compressed points of each party, and deserialization
+/// rejects points which fail to decompress, or a number of points
+/// which does not match the capacities.
+#[derive(Clone, Serialize)]
 pub struct BulletproofGens {
     /// The maximum number of usable generators for each party.
     pub gens_capacity: usize,
@@ -128,6 +208,41 @@ pub struct BulletproofGens {
     H_vec: Vec<Vec<RistrettoPoint>>,
 }
 
+impl<'de> Deserialize<'de> for BulletproofGens {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Mirrors the fields of `BulletproofGens`, so that the derived
+        // deserializer can be reused before validating the lengths.
+        #[derive(Deserialize)]
+        #[serde(rename = "BulletproofGens")]
+        struct BulletproofGensData {
+            gens_capacity: usize,
+            party_capacity: usize,
+            G_vec: Vec<Vec<RistrettoPoint>>,
+            H_vec: Vec<Vec<RistrettoPoint>>,
+        }
+
+        let data = BulletproofGensData::deserialize(deserializer)?;
+        let has_capacity = |gens: &Vec<Vec<RistrettoPoint>>| {
+            gens.len() == data.party_capacity && gens.iter().all(|g| g.len() == data.gens_capacity)
+        };
+        if !has_capacity(&data.G_vec) || !has_capacity(&data.H_vec) {
+            return Err(D::Error::custom(
+                "BulletproofGens lengths do not match the capacities",
+            ));
+        }
+
+        Ok(BulletproofGens {
+            gens_capacity: data.gens_capacity,
+            party_capacity: data.party_capacity,
+            G_vec: data.G_vec,
+            H_vec: data.H_vec,
+        })
+    }
+}
+
 impl BulletproofGens {
     /// Create a new `BulletproofGens` object.
     ///
@@ -141,39 +256,76 @@ impl BulletproofGens {
     ///
     /// * `party_capacity` is the maximum number of parties that can
     ///    produce an aggregated proof.
+    ///
+    /// This is equivalent to [`BulletproofGens::from_label`] with an
+    /// empty label.
     pub fn new(gens_capacity: usize, party_capacity: usize) -> Self {
+        BulletproofGens::from_label(&[], gens_capacity, party_capacity)
+    }
+
+    /// Create a new `BulletproofGens` object, with generators
+    /// derived deterministically from a domain-separation `label`.
+    ///
+    /// Two calls with the same `label`, `gens_capacity` and
+    /// `party_capacity` produce identical generators, and the
+    /// generators for a smaller capacity are a prefix of those for a
+    /// larger one.
+    ///
+    /// # Construction
+    ///
+    /// For the party with index `j`, the \\(\mathbf G\\) generators
+    /// are the first `gens_capacity` points of the generator chain
+    /// with input
+    /// ```text
+    /// label || b"G" || LE32(j)
+    /// ```
+    /// and the \\(\mathbf H\\) generators are those of the chain with
+    /// input `label || b"H" || LE32(j)`, where `LE32(j)` is the
+    /// 4-byte little-endian encoding of `j`.
+    ///
+    /// A generator chain with input `x` is produced by absorbing
+    /// `b"GeneratorsChain" || x` into SHAKE256, then repeatedly
+    /// squeezing 64 bytes of output and mapping them to a point with
+    /// the `ristretto255` hash-to-group function
+    /// (`RistrettoPoint::from_uniform_bytes`).
+    ///
+    /// The Pedersen generators are not derived from the label; see
+    /// [`PedersenGens`] for their construction.
+    pub fn from_label(label: &[u8], gens_capacity: usize, party_capacity: usize) -> Self {
         use byteorder::{ByteOrder, LittleEndian};
 
-        BulletproofGens {
-            gens_capacity,
-            party_capacity,
-            G_vec: (0..party_capacity)
+        let party_gens = |prefix: u8| -> Vec<Vec<RistrettoPoint>> {
+            (0..party_capacity)
                 .map(|i| {
                     let party_index = i as u32;
-                    let mut label = [b'G', 0, 0, 0, 0];
-                    LittleEndian::write_u32(&mut label[1..5], party_index);
+                    let mut chain_label = label.to_vec();
+                    chain_label.extend_from_slice(&[prefix, 0, 0, 0, 0]);
+                    let len = chain_label.len();
+                    LittleEndian::write_u32(&mut chain_label[len - 4..], party_index);
 
-                    GeneratorsChain::new(&label)
+                    GeneratorsChain::new(&chain_label)
                         .take(gens_capacity)
                         .collect::<Vec<_>>()
                 })
-                .collect(),
-            H_vec: (0..party_capacity)
-                .map(|i| {
-                    let party_index = i as u32;
-                    let mut label = [b'H', 0, 0, 0, 0];
-                    LittleEndian::write_u32(&mut label[1..5], party_index);
+                .collect()
+        };
 
-                    GeneratorsChain::new(&label)
-                        .take(gens_capacity)
-                        .collect::<Vec<_>>()
-                })
-                .collect(),
+        BulletproofGens {
+            gens_capacity,
+            party_capacity,
+            G_vec: party_gens(b'G'),
+            H_vec: party_gens(b'H'),
         }
     }
 
     /// Returns j-th share of generators, with an appropriate
     /// slice of vectors G and H for the j-th range proof.
+    ///
+    /// The share is a view into this `BulletproofGens`, so no
+    /// generators are copied, and a party proving with it only reads
+    /// the first `n` of its own \\(\mathbf G\\) and \\(\mathbf H\\)
+    /// generators.  The generators of the other parties are only used
+    /// by the dealer and the verifier.
     pub fn share(&self, j: usize) -> BulletproofGensShare {
         BulletproofGensShare {
             gens: &self,
@@ -181,6 +333,40 @@ impl BulletproofGens {
         }
     }
 
+    /// Precompute tables of the generators used to verify rangeproofs
+    /// of bitsize `n` aggregated over up to `m` parties, with the
+    /// Pedersen generators `pc_gens`.
+    ///
+    /// The resulting [`PrecomputedGens`] can be passed to
+    /// [`RangeProof::verify_multiple_precomputed`](::RangeProof::verify_multiple_precomputed),
+    /// and can be shared between threads (e.g., in an `Arc`) by a
+    /// server verifying many proofs.
+    ///
+    /// Returns an error if there are too few generators for `n` and `m`.
+    pub fn precompute(
+        &self,
+        pc_gens: &PedersenGens,
+        n: usize,
+        m: usize,
+    ) -> Result<PrecomputedGens, ProofError> {
+        if self.gens_capacity < n || self.party_capacity < m {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+
+        let precomputation = VartimeRistrettoPrecomputation::new(
+            [pc_gens.B, pc_gens.B_blinding]
+                .iter()
+                .chain(self.G(n, m))
+                .chain(self.H(n, m)),
+        );
+
+        Ok(PrecomputedGens {
+            n,
+            m,
+            precomputation,
+        })
+    }
+
     /// Return an iterator over the aggregation of the parties' G generators with given size `n`.
     pub(crate) fn G(&self, n: usize, m: usize) -> impl Iterator<Item = &RistrettoPoint> {
         AggregatedGensIter {
@@ -202,6 +388,51 @@ impl BulletproofGens {
             gen_idx: 0,
         }
     }
+
+    /// Return an iterator over the aggregation of the parties' G
+    /// generators, where the `j`-th party uses `bitsizes[j]` of them.
+    pub(crate) fn G_variable<'a>(
+        &'a self,
+        bitsizes: &'a [usize],
+    ) -> impl Iterator<Item = &'a RistrettoPoint> + 'a {
+        VariableGensIter {
+            array: &self.G_vec,
+            bitsizes,
+            party_idx: 0,
+            gen_idx: 0,
+        }
+    }
+
+    /// Return an iterator over the aggregation of the parties' H
+    /// generators, where the `j`-th party uses `bitsizes[j]` of them.
+    pub(crate) fn H_variable<'a>(
+        &'a self,
+        bitsizes: &'a [usize],
+    ) -> impl Iterator<Item = &'a RistrettoPoint> + 'a {
+        VariableGensIter {
+            array: &self.H_vec,
+            bitsizes,
+            party_idx: 0,
+            gen_idx: 0,
+        }
+    }
+}
+
+/// Precomputed tables of the static generators for verifying
+/// rangeproofs of a fixed bitsize `n` and aggregation size of up to
+/// `m`.
+///
+/// The tables cover the Pedersen generators \\(B\\) and
+/// \\(\tilde{B}\\), followed by the first `n` \\(\mathbf G\\) and
+/// \\(\mathbf H\\) generators of each of the `m` parties.
+///
+/// The `PrecomputedGens` is produced by [`BulletproofGens::precompute()`].
+pub struct PrecomputedGens {
+    /// The bitsize the tables were computed for
+    pub(crate) n: usize,
+    /// The maximum aggregation size the tables were computed for
+    pub(crate) m: usize,
+    pub(crate) precomputation: VartimeRistrettoPrecomputation,
 }
 
 struct AggregatedGensIter<'a> {
@@ -236,6 +467,38 @@ impl<'a> Iterator for AggregatedGensIter<'a> {
     }
 }
 
+struct VariableGensIter<'a> {
+    array: &'a Vec<Vec<RistrettoPoint>>,
+    bitsizes: &'a [usize],
+    party_idx: usize,
+    gen_idx: usize,
+}
+
+impl<'a> Iterator for VariableGensIter<'a> {
+    type Item = &'a RistrettoPoint;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.party_idx < self.bitsizes.len() && self.gen_idx >= self.bitsizes[self.party_idx]
+        {
+            self.gen_idx = 0;
+            self.party_idx += 1;
+        }
+
+        if self.party_idx >= self.bitsizes.len() {
+            None
+        } else {
+            let cur_gen = self.gen_idx;
+            self.gen_idx += 1;
+            Some(&self.array[self.party_idx][cur_gen])
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let size = self.bitsizes.iter().sum();
+        (size, Some(size))
+    }
+}
+
 /// Represents a view of the generators used by a specific party in an
 /// aggregated proof.
 ///
@@ -268,6 +531,85 @@ impl<'a> BulletproofGensShare<'a> {
 mod tests {
     use super::*;
 
+    use bincode;
+
+    #[test]
+    fn verify_commitment_opening() {
+        let pc_gens = PedersenGens::default();
+        let blinding = Scalar::from(42u64);
+        let V = pc_gens.commit(Scalar::from(7u64), blinding).compress();
+
+        assert!(pc_gens.verify_opening(&V, 7, &blinding));
+        assert!(!pc_gens.verify_opening(&V, 8, &blinding));
+        assert!(!pc_gens.verify_opening(&V, 7, &Scalar::from(43u64)));
+
+        assert_eq!(pc_gens.commit_value(7, &blinding), V);
+
+        // A commitment which doesn't decompress has no opening.
+        let invalid = CompressedRistretto([0xff; 32]);
+        assert!(!pc_gens.verify_opening(&invalid, 7, &blinding));
+    }
+
+    #[test]
+    fn serde_roundtrip() {
+        let bp_gens = BulletproofGens::from_label(b"serde test", 16, 3);
+        let bytes = bincode::serialize(&bp_gens).unwrap();
+        let bp_gens2: BulletproofGens = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(bp_gens2.gens_capacity, 16);
+        assert_eq!(bp_gens2.party_capacity, 3);
+        assert!(bp_gens.G(16, 3).eq(bp_gens2.G(16, 3)));
+        assert!(bp_gens.H(16, 3).eq(bp_gens2.H(16, 3)));
+
+        let pc_gens = PedersenGens::default();
+        let bytes = bincode::serialize(&pc_gens).unwrap();
+        let pc_gens2: PedersenGens = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(pc_gens.B, pc_gens2.B);
+        assert_eq!(pc_gens.B_blinding, pc_gens2.B_blinding);
+    }
+
+    #[test]
+    fn deserialization_checks_generators() {
+        // The capacities must match the number of points.
+        let mut bp_gens = BulletproofGens::new(8, 2);
+        bp_gens.gens_capacity = 16;
+        let bytes = bincode::serialize(&bp_gens).unwrap();
+        assert!(bincode::deserialize::<BulletproofGens>(&bytes).is_err());
+
+        let mut bp_gens = BulletproofGens::new(8, 2);
+        bp_gens.party_capacity = 1;
+        let bytes = bincode::serialize(&bp_gens).unwrap();
+        assert!(bincode::deserialize::<BulletproofGens>(&bytes).is_err());
+
+        // Corrupting the last byte of the last point makes it fail to
+        // decompress.
+        let bp_gens = BulletproofGens::new(8, 2);
+        let mut bytes = bincode::serialize(&bp_gens).unwrap();
+        *bytes.last_mut().unwrap() = 0xff;
+        assert!(bincode::deserialize::<BulletproofGens>(&bytes).is_err());
+    }
+
+    #[test]
+    fn rerandomized_commitment_opens_with_shifted_blinding() {
+        let pc_gens = PedersenGens::default();
+        let (r, delta) = (Scalar::from(5u64), Scalar::from(8u64));
+        let V = pc_gens.commit(Scalar::from(3u64), r);
+        let V2 = pc_gens.rerandomize(&V, &delta);
+
+        assert!(V2 != V);
+        assert!(pc_gens.verify_opening(&V2.compress(), 3, &(r + delta)));
+        assert!(!pc_gens.verify_opening(&V2.compress(), 3, &r));
+    }
+
+    #[test]
+    fn commitments_are_additively_homomorphic() {
+        let pc_gens = PedersenGens::default();
+        let (r1, r2) = (Scalar::from(5u64), Scalar::from(11u64));
+        let V1 = pc_gens.commit(Scalar::from(3u64), r1);
+        let V2 = pc_gens.commit(Scalar::from(4u64), r2);
+
+        assert!(pc_gens.verify_opening(&(V1 + V2).compress(), 7, &(r1 + r2)));
+    }
+
     #[test]
     fn aggregated_gens_iter_matches_flat_map() {
         let gens = BulletproofGens::new(64, 8);
@@ -308,4 +650,73 @@ mod tests {
         helper(16, 2);
         helper(16, 1);
     }
+
+    #[test]
+    fn variable_gens_iter_matches_parties_gens() {
+        let gens = BulletproofGens::new(64, 4);
+
+        // Equal bitsizes give the same generators as the aggregated iterator.
+        let uniform: Vec<_> = gens.G_variable(&[32, 32, 32, 32]).collect();
+        assert_eq!(uniform, gens.G(32, 4).collect::<Vec<_>>());
+
+        let bitsizes = [8, 64, 16];
+        let expected_G: Vec<_> = (0..3)
+            .flat_map(|j| gens.share(j).G(bitsizes[j]).collect::<Vec<_>>())
+            .collect();
+        let expected_H: Vec<_> = (0..3)
+            .flat_map(|j| gens.share(j).H(bitsizes[j]).collect::<Vec<_>>())
+            .collect();
+        assert_eq!(gens.G_variable(&bitsizes).collect::<Vec<_>>(), expected_G);
+        assert_eq!(gens.H_variable(&bitsizes).collect::<Vec<_>>(), expected_H);
+    }
+
+    #[test]
+    fn gens_from_label_are_deterministic() {
+        let a = BulletproofGens::from_label(b"test label", 32, 4);
+        let b = BulletproofGens::from_label(b"test label", 32, 4);
+        assert_eq!(a.G_vec, b.G_vec);
+        assert_eq!(a.H_vec, b.H_vec);
+
+        // Smaller capacities give a prefix of the same generators.
+        let small = BulletproofGens::from_label(b"test label", 16, 2);
+        assert_eq!(
+            small.G(16, 2).collect::<Vec<_>>(),
+            a.G(16, 2).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            small.H(16, 2).collect::<Vec<_>>(),
+            a.H(16, 2).collect::<Vec<_>>()
+        );
+
+        let other = BulletproofGens::from_label(b"other label", 32, 4);
+        assert!(a.G_vec[0][0] != other.G_vec[0][0]);
+        assert!(a.H_vec[0][0] != other.H_vec[0][0]);
+        assert!(a.G_vec[0][0] != a.H_vec[0][0]);
+
+        // The default generators use the empty label.
+        let default = BulletproofGens::new(32, 4);
+        let empty = BulletproofGens::from_label(&[], 32, 4);
+        assert_eq!(default.G_vec, empty.G_vec);
+        assert_eq!(default.H_vec, empty.H_vec);
+    }
+
+    #[test]
+    fn gens_chain_matches_documented_construction() {
+        let mut shake = Shake256::default();
+        shake.input(b"GeneratorsChain");
+        shake.input(b"label");
+        shake.input(&[b'G', 1, 0, 0, 0]);
+        let mut reader = shake.xof_result();
+
+        let expected: Vec<RistrettoPoint> = (0..4)
+            .map(|_| {
+                let mut uniform_bytes = [0u8; 64];
+                reader.read(&mut uniform_bytes);
+                RistrettoPoint::from_uniform_bytes(&uniform_bytes)
+            })
+            .collect();
+
+        let gens = BulletproofGens::from_label(b"label", 4, 2);
+        assert_eq!(gens.G_vec[1], expected);
+    }
 }
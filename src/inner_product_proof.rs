@@ -1,8 +1,9 @@
 #![allow(non_snake_case)]
 #![doc(include = "../docs/inner-product-protocol.md")]
 
-use std::borrow::Borrow;
-use std::iter;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::iter;
 
 use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
 use curve25519_dalek::scalar::Scalar;
@@ -10,9 +11,15 @@ use curve25519_dalek::traits::VartimeMultiscalarMul;
 use merlin::Transcript;
 
 use errors::ProofError;
-use transcript::TranscriptProtocol;
-
-#[derive(Clone, Debug)]
+use transcript::{labels, TranscriptProtocol};
+
+/// A proof of the inner product of two vectors, with respect to
+/// bases \\(\mathbf{G}\\), \\(\mathbf{H}'\\) and \\(Q\\).
+///
+/// The range proofs use this argument internally, but it can also be
+/// used as a standalone primitive, with [`InnerProductProof::create`]
+/// and [`InnerProductProof::verify`].
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct InnerProductProof {
     pub(crate) L_vec: Vec<CompressedRistretto>,
     pub(crate) R_vec: Vec<CompressedRistretto>,
@@ -31,7 +38,13 @@ impl InnerProductProof {
     /// protocols).
     ///
     /// The lengths of the vectors must all be the same, and must all be
-    /// either 0 or a power of 2.
+    /// a power of 2.  In particular, they can't be empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `G_vec`, `H_vec`, `a_vec` and `b_vec` don't all have
+    /// the same length, if that length is not a power of 2, or if
+    /// `Hprime_factors` doesn't have that length.
     pub fn create(
         transcript: &mut Transcript,
         Q: &RistrettoPoint,
@@ -41,14 +54,44 @@ impl InnerProductProof {
         mut a_vec: Vec<Scalar>,
         mut b_vec: Vec<Scalar>,
     ) -> InnerProductProof {
-        // Create slices G, H, a, b backed by their respective
-        // vectors.  This lets us reslice as we compress the lengths
-        // of the vectors in the main loop below.
-        let mut G = &mut G_vec[..];
-        let mut H = &mut H_vec[..];
-        let mut a = &mut a_vec[..];
-        let mut b = &mut b_vec[..];
+        InnerProductProof::create_in_place(
+            transcript,
+            Q,
+            Hprime_factors,
+            &mut G_vec,
+            &mut H_vec,
+            &mut a_vec,
+            &mut b_vec,
+        )
+    }
 
+    /// Create an inner-product proof, folding the vectors in the
+    /// provided buffers rather than allocating new ones.
+    ///
+    /// This creates the same proof as [`InnerProductProof::create`].
+    /// Each round folds `G`, `H`, `a` and `b` into the front half of
+    /// their buffers, so their contents are overwritten.  A caller
+    /// creating many proofs can keep the buffers as scratch space,
+    /// copying the generators and vectors for each proof into them.
+    ///
+    /// The lengths of the buffers and of `Hprime_factors` must all be
+    /// the same, and must all be a power of 2.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `G`, `H`, `a`, `b` and `Hprime_factors` don't all
+    /// have the same length, or if that length is not a power of 2.
+    pub fn create_in_place(
+        transcript: &mut Transcript,
+        Q: &RistrettoPoint,
+        Hprime_factors: &[Scalar],
+        mut G: &mut [RistrettoPoint],
+        mut H: &mut [RistrettoPoint],
+        mut a: &mut [Scalar],
+        mut b: &mut [Scalar],
+    ) -> InnerProductProof {
+        // The slices G, H, a, b are resliced as we compress the
+        // lengths of the vectors in the main loop below.
         let mut n = G.len();
 
         // All of the input vectors must have the same length.
@@ -56,6 +99,7 @@ impl InnerProductProof {
         assert_eq!(H.len(), n);
         assert_eq!(a.len(), n);
         assert_eq!(b.len(), n);
+        assert_eq!(Hprime_factors.len(), n);
 
         // All of the input vectors must have a length that is a power of two.
         assert!(n.is_power_of_two());
@@ -107,10 +151,10 @@ impl InnerProductProof {
             L_vec.push(L);
             R_vec.push(R);
 
-            transcript.append_point(b"L", &L);
-            transcript.append_point(b"R", &R);
+            transcript.append_point(labels::L, &L);
+            transcript.append_point(labels::R, &R);
 
-            let u = transcript.challenge_scalar(b"u");
+            let u = transcript.challenge_scalar(labels::U);
             let u_inv = u.invert();
 
             for i in 0..n {
@@ -154,10 +198,10 @@ impl InnerProductProof {
             L_vec.push(L);
             R_vec.push(R);
 
-            transcript.append_point(b"L", &L);
-            transcript.append_point(b"R", &R);
+            transcript.append_point(labels::L, &L);
+            transcript.append_point(labels::R, &R);
 
-            let u = transcript.challenge_scalar(b"u");
+            let u = transcript.challenge_scalar(labels::U);
             let u_inv = u.invert();
 
             for i in 0..n {
@@ -184,7 +228,15 @@ impl InnerProductProof {
     /// Computes three vectors of verification scalars \\([u\_{i}^{2}]\\), \\([u\_{i}^{-2}]\\) and \\([s\_{i}]\\) for combined multiscalar multiplication
     /// in a parent protocol. See [inner product protocol notes](index.html#verification-equation) for details.
     /// The verifier must provide the input length \\(n\\) explicitly to avoid unbounded allocation within the inner product proof.
-    pub(crate) fn verification_scalars(
+    ///
+    /// The `transcript` must be in the same state as for
+    /// [`InnerProductProof::verify`].  The first two vectors have one
+    /// entry per round, and the exponents of \\(\mathbf{G}\\) and
+    /// \\(\mathbf{H}\\) are \\(a \cdot s\_i\\) and
+    /// \\(b \cdot s\_{n-1-i}\\) times the factors of \\(\mathbf{H}'\\), so
+    /// several proofs can be folded into a single multiscalar
+    /// multiplication by a batch verifier.
+    pub fn verification_scalars(
         &self,
         n: usize,
         transcript: &mut Transcript,
@@ -205,9 +257,9 @@ impl InnerProductProof {
 
         let mut challenges = Vec::with_capacity(lg_n);
         for (L, R) in self.L_vec.iter().zip(self.R_vec.iter()) {
-            transcript.validate_and_append_point(b"L", L)?;
-            transcript.validate_and_append_point(b"R", R)?;
-            challenges.push(transcript.challenge_scalar(b"u"));
+            transcript.validate_and_append_point(labels::L, L)?;
+            transcript.validate_and_append_point(labels::R, R)?;
+            challenges.push(transcript.challenge_scalar(labels::U));
         }
 
         // 2. Compute 1/(u_k...u_1) and 1/u_k, ..., 1/u_1
@@ -241,11 +293,18 @@ impl InnerProductProof {
         Ok((challenges_sq, challenges_inv_sq, s))
     }
 
-    /// This method is for testing that proof generation work,
-    /// but for efficiency the actual protocols would use `verification_scalars`
-    /// method to combine inner product verification with other checks
-    /// in a single multiscalar multiplication.
-    #[allow(dead_code)]
+    /// Verifies an inner-product proof for vectors of length `n`,
+    /// committed to by
+    /// \\(P = \langle \mathbf{a}, \mathbf{G} \rangle + \langle \mathbf{b}, \mathbf{H}' \rangle + \langle \mathbf{a}, \mathbf{b} \rangle Q\\),
+    /// where \\(H'\_i = H\_i \cdot \texttt{Hprime\\_factors}\_i\\).
+    ///
+    /// The `transcript` must be in the same state as the one passed
+    /// to [`InnerProductProof::create`], and `G`, `H` and
+    /// `Hprime_factors` must all have length `n`.
+    ///
+    /// The range proofs don't use this method: for efficiency, they
+    /// combine the inner product verification with their other checks
+    /// into a single multiscalar multiplication.
     pub fn verify<I>(
         &self,
         n: usize,
@@ -260,17 +319,25 @@ impl InnerProductProof {
         I: IntoIterator,
         I::Item: Borrow<Scalar>,
     {
+        if G.len() != n || H.len() != n {
+            return Err(ProofError::VerificationError);
+        }
+
         let (u_sq, u_inv_sq, s) = self.verification_scalars(n, transcript)?;
 
-        let a_times_s = s.iter().map(|s_i| self.a * s_i).take(G.len());
+        let a_times_s = s.iter().map(|s_i| self.a * s_i);
 
         // 1/s[i] is s[!i], and !i runs from n-1 to 0 as i runs from 0 to n-1
         let inv_s = s.iter().rev();
 
-        let h_times_b_div_s = Hprime_factors
+        let h_times_b_div_s: Vec<Scalar> = Hprime_factors
             .into_iter()
             .zip(inv_s)
-            .map(|(h_i, s_i_inv)| (self.b * s_i_inv) * h_i.borrow());
+            .map(|(h_i, s_i_inv)| (self.b * s_i_inv) * h_i.borrow())
+            .collect();
+        if h_times_b_div_s.len() != n {
+            return Err(ProofError::VerificationError);
+        }
 
         let neg_u_sq = u_sq.iter().map(|ui| -ui);
         let neg_u_inv_sq = u_inv_sq.iter().map(|ui| -ui);
@@ -307,6 +374,24 @@ impl InnerProductProof {
         }
     }
 
+    /// Returns the number of rounds of the proof, which is
+    /// \\(\lg n\\) for vectors of length `n`.
+    pub fn rounds(&self) -> usize {
+        self.L_vec.len()
+    }
+
+    /// Returns the final scalar \\(a\\), the folded left vector
+    /// after the last round.
+    pub fn final_a(&self) -> Scalar {
+        self.a
+    }
+
+    /// Returns the final scalar \\(b\\), the folded right vector
+    /// after the last round.
+    pub fn final_b(&self) -> Scalar {
+        self.b
+    }
+
     /// Returns the size in bytes required to serialize the inner
     /// product proof.
     ///
@@ -335,7 +420,8 @@ impl InnerProductProof {
     /// Returns an error in the following cases:
     /// * the slice does not have \\(2n+2\\) 32-byte elements,
     /// * \\(n\\) is larger or equal to 32 (proof is too big),
-    /// * any of \\(2n\\) points are not valid compressed Ristretto points,
+    /// * any of \\(2n\\) points are not valid compressed Ristretto points
+    ///   (returning `ProofError::InvalidPoint`),
     /// * any of 2 scalars are not canonical scalars modulo Ristretto group order.
     pub fn from_bytes(slice: &[u8]) -> Result<InnerProductProof, ProofError> {
         let b = slice.len();
@@ -354,14 +440,14 @@ impl InnerProductProof {
             return Err(ProofError::FormatError);
         }
 
-        use util::read32;
+        use util::{read32, read_point};
 
         let mut L_vec: Vec<CompressedRistretto> = Vec::with_capacity(lg_n);
         let mut R_vec: Vec<CompressedRistretto> = Vec::with_capacity(lg_n);
         for i in 0..lg_n {
             let pos = 2 * i * 32;
-            L_vec.push(CompressedRistretto(read32(&slice[pos..])));
-            R_vec.push(CompressedRistretto(read32(&slice[pos + 32..])));
+            L_vec.push(read_point(&slice[pos..])?);
+            R_vec.push(read_point(&slice[pos + 32..])?);
         }
 
         let pos = 2 * lg_n * 32;
@@ -455,6 +541,53 @@ mod tests {
             )
             .is_ok());
 
+        let bytes = proof.to_bytes();
+        assert_eq!(&bytes[bytes.len() - 64..][..32], proof.final_a().as_bytes());
+        assert_eq!(&bytes[bytes.len() - 32..], proof.final_b().as_bytes());
+        if n == 1 {
+            // With no rounds, the final scalars are the vectors themselves.
+            assert_eq!((proof.final_a(), proof.final_b()), (a[0], b[0]));
+        }
+
+        // Reused scratch buffers give the same proof each time.
+        let mut G_buf = vec![RistrettoPoint::default(); n];
+        let mut H_buf = vec![RistrettoPoint::default(); n];
+        let mut a_buf = vec![Scalar::zero(); n];
+        let mut b_buf = vec![Scalar::zero(); n];
+        for _ in 0..2 {
+            G_buf.copy_from_slice(&G);
+            H_buf.copy_from_slice(&H);
+            a_buf.copy_from_slice(&a);
+            b_buf.copy_from_slice(&b);
+            let mut verifier = Transcript::new(b"innerproducttest");
+            let in_place = InnerProductProof::create_in_place(
+                &mut verifier,
+                &Q,
+                &Hprime_factors,
+                &mut G_buf,
+                &mut H_buf,
+                &mut a_buf,
+                &mut b_buf,
+            );
+            assert_eq!(in_place.to_bytes(), proof.to_bytes());
+        }
+
+        // The s values are the products of the challenges or their
+        // inverses, so s_i * s_{n-1-i} = 1.
+        assert_eq!(1 << proof.rounds(), n);
+        let mut verifier = Transcript::new(b"innerproducttest");
+        let (u_sq, u_inv_sq, s) = proof.verification_scalars(n, &mut verifier).unwrap();
+        assert_eq!(u_sq.len(), proof.rounds());
+        for (u_sq, u_inv_sq) in u_sq.iter().zip(u_inv_sq.iter()) {
+            assert_eq!(u_sq * u_inv_sq, Scalar::one());
+        }
+        assert_eq!(s.len(), n);
+        for i in 0..n {
+            assert_eq!(s[i] * s[n - 1 - i], Scalar::one());
+        }
+        let mut verifier = Transcript::new(b"innerproducttest");
+        assert!(proof.verification_scalars(2 * n, &mut verifier).is_err());
+
         let proof = InnerProductProof::from_bytes(proof.to_bytes().as_slice()).unwrap();
         let mut verifier = Transcript::new(b"innerproducttest");
         assert!(proof
@@ -468,6 +601,44 @@ mod tests {
                 &H
             )
             .is_ok());
+
+        // A different commitment, or mismatched lengths, are rejected.
+        let mut verifier = Transcript::new(b"innerproducttest");
+        assert!(proof
+            .verify(
+                n,
+                &mut verifier,
+                util::exp_iter(y_inv).take(n),
+                &(P + Q),
+                &Q,
+                &G,
+                &H
+            )
+            .is_err());
+        let mut verifier = Transcript::new(b"innerproducttest");
+        assert!(proof
+            .verify(
+                n,
+                &mut verifier,
+                util::exp_iter(y_inv).take(n / 2),
+                &P,
+                &Q,
+                &G,
+                &H
+            )
+            .is_err());
+        let mut verifier = Transcript::new(b"innerproducttest");
+        assert!(proof
+            .verify(
+                n,
+                &mut verifier,
+                util::exp_iter(y_inv).take(n),
+                &P,
+                &Q,
+                &G[..n / 2],
+                &H
+            )
+            .is_err());
     }
 
     #[test]
@@ -495,6 +666,42 @@ mod tests {
         test_helper_create(64);
     }
 
+    #[test]
+    fn from_bytes_rejects_bad_lengths() {
+        // k = 0, 1, 2 rounds: 2*k points plus the two scalars.
+        for k in 0..3 {
+            let bytes = vec![0u8; (2 * k + 2) * 32];
+            let proof = InnerProductProof::from_bytes(&bytes).unwrap();
+            assert_eq!(proof.L_vec.len(), k);
+            assert_eq!(proof.R_vec.len(), k);
+            assert_eq!(proof.to_bytes(), bytes);
+        }
+
+        for len in &[0, 31, 32, 33, 3 * 32, 5 * 32, 4 * 32 + 1] {
+            let bytes = vec![0u8; *len];
+            assert_eq!(
+                InnerProductProof::from_bytes(&bytes).unwrap_err(),
+                ProofError::FormatError
+            );
+        }
+
+        // Points that fail to decompress are rejected eagerly.
+        let mut bytes = vec![0u8; 4 * 32];
+        bytes[32] = 1;
+        assert_eq!(
+            InnerProductProof::from_bytes(&bytes).unwrap_err(),
+            ProofError::InvalidPoint
+        );
+
+        // Non-canonical scalars are rejected as well.
+        let mut bytes = vec![0u8; 2 * 32];
+        bytes[63] = 0xff;
+        assert_eq!(
+            InnerProductProof::from_bytes(&bytes).unwrap_err(),
+            ProofError::FormatError
+        );
+    }
+
     #[test]
     fn test_inner_product() {
         let a = vec![
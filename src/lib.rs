@@ -1,3 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![feature(nll)]
 #![feature(external_doc)]
 #![feature(try_trait)]
@@ -5,10 +6,14 @@
 #![doc(include = "../README.md")]
 #![doc(html_logo_url = "https://doc.dalek.rs/assets/dalek-logo-clear.png")]
 
+#[macro_use]
+extern crate alloc;
 extern crate byteorder;
+#[cfg(feature = "std")]
 extern crate core;
 extern crate digest;
 extern crate rand;
+extern crate rand_chacha;
 extern crate sha3;
 
 extern crate clear_on_drop;
@@ -22,6 +27,18 @@ extern crate serde;
 #[macro_use]
 extern crate failure;
 
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
+#[cfg(feature = "zeroize")]
+extern crate zeroize;
+
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+
+#[cfg(feature = "hex")]
+extern crate hex;
+
 #[cfg(test)]
 extern crate bincode;
 
@@ -43,9 +60,15 @@ mod inner_product_proof;
 mod range_proof;
 mod transcript;
 
-pub use errors::ProofError;
-pub use generators::{BulletproofGens, BulletproofGensShare, PedersenGens};
-pub use range_proof::RangeProof;
+pub use errors::{ProofError, VerifyFailureReason};
+pub use generators::{BulletproofGens, BulletproofGensShare, PedersenGens, PrecomputedGens};
+pub use inner_product_proof::InnerProductProof;
+pub use range_proof::{
+    CommittedValue, RangeProof, RangeProof64, RangeProofChallenges, VerifiableProof,
+    VerifierContext,
+};
+pub use transcript::append_optional_message;
+pub use transcript::labels as transcript_labels;
 
 #[doc(include = "../docs/aggregation-api.md")]
 pub mod range_proof_mpc {
@@ -57,3 +80,6 @@ pub mod range_proof_mpc {
 
 #[cfg(feature = "yoloproofs")]
 pub mod r1cs;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -5,6 +5,7 @@ use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
 use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::traits::MultiscalarMul;
 use merlin::Transcript;
+use rand::{CryptoRng, RngCore};
 
 use super::{ConstraintSystem, LinearCombination, R1CSProof, Variable};
 
@@ -289,7 +290,23 @@ impl<'a, 'b> ProverCS<'a, 'b> {
     }
 
     /// Consume this `ConstraintSystem` to produce a proof.
-    pub fn prove(mut self) -> Result<R1CSProof, R1CSError> {
+    ///
+    /// This is a convenience wrapper around `prove_with_rng`, passing
+    /// in a threadsafe RNG.
+    pub fn prove(self) -> Result<R1CSProof, R1CSError> {
+        self.prove_with_rng(&mut rand::thread_rng())
+    }
+
+    /// Consume this `ConstraintSystem` to produce a proof, using `rng`
+    /// as the external randomness for the proof's blinding factors.
+    ///
+    /// The randomness is mixed into the transcript together with the
+    /// witness's blinding factors, so a seeded `rng` gives
+    /// deterministic proofs.
+    pub fn prove_with_rng<T: RngCore + CryptoRng>(
+        mut self,
+        rng: &mut T,
+    ) -> Result<R1CSProof, R1CSError> {
         use std::iter;
         use util;
 
@@ -328,8 +345,7 @@ impl<'a, 'b> ProverCS<'a, 'b> {
                 builder = builder.rekey_with_witness_bytes(b"v_blinding", v_b.as_bytes());
             }
 
-            use rand::thread_rng;
-            builder.finalize(&mut thread_rng())
+            builder.finalize(rng)
         };
 
         // 3. Choose blinding factors and form commitments to low-level witness data
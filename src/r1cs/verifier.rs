@@ -4,6 +4,7 @@ use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
 use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::traits::VartimeMultiscalarMul;
 use merlin::Transcript;
+use rand::{CryptoRng, RngCore};
 
 use super::{ConstraintSystem, LinearCombination, R1CSProof, Variable};
 
@@ -245,7 +246,20 @@ impl<'a, 'b> VerifierCS<'a, 'b> {
     }
 
     /// Consume this `VerifierCS` and attempt to verify the supplied `proof`.
-    pub fn verify(mut self, proof: &R1CSProof) -> Result<(), R1CSError> {
+    ///
+    /// This is a convenience wrapper around `verify_with_rng`, passing
+    /// in a threadsafe RNG.
+    pub fn verify(self, proof: &R1CSProof) -> Result<(), R1CSError> {
+        self.verify_with_rng(proof, &mut rand::thread_rng())
+    }
+
+    /// Consume this `VerifierCS` and attempt to verify the supplied
+    /// `proof`, using `rng` for the verifier's random challenge.
+    pub fn verify_with_rng<T: RngCore + CryptoRng>(
+        mut self,
+        proof: &R1CSProof,
+        rng: &mut T,
+    ) -> Result<(), R1CSError> {
         // If the number of multiplications is not 0 or a power of 2, then pad the circuit.
         let n = self.num_vars;
         let padded_n = self.num_vars.next_power_of_two();
@@ -326,8 +340,7 @@ impl<'a, 'b> VerifierCS<'a, 'b> {
         // Create a `TranscriptRng` from the transcript. The verifier
         // has no witness data to commit, so this just mixes external
         // randomness into the existing transcript.
-        use rand::thread_rng;
-        let mut rng = self.transcript.build_rng().finalize(&mut thread_rng());
+        let mut rng = self.transcript.build_rng().finalize(rng);
         let r = Scalar::random(&mut rng);
 
         let xx = x * x;
@@ -3,26 +3,87 @@
 //!
 //! For more explanation of how the `dealer`, `party`, and `messages` modules orchestrate the protocol execution, see
 //! [the API for the aggregated multiparty computation protocol](../aggregation/index.html#api-for-the-aggregated-multiparty-computation-protocol).
+//!
+//! # Resuming a dealer
+//!
+//! The dealer's states borrow the `Transcript`, and Merlin does not
+//! expose a transcript's internal state, so a dealer cannot be
+//! serialized between rounds.  Instead, a dealer which must outlive
+//! a request can be resumed by keeping the (serializable) messages
+//! it has received: since the dealer is deterministic, creating it
+//! again from the same initial transcript and replaying the messages
+//! reproduces the same challenges.
+//!
+//! # Continuing the transcript
+//!
+//! The final state consumes itself, so the borrow of the `Transcript`
+//! ends when [`receive_shares`](DealerAwaitingProofShares::receive_shares)
+//! returns, and the caller can go on using it.  The transcript is then
+//! in the state after the last challenge of the proof, which is the
+//! same state that the verifier's transcript is left in by a
+//! successful verification.  Both sides can therefore continue it to
+//! bind further protocol steps to the proof, such as deriving the
+//! challenge of a signature.
+
+use alloc::vec::Vec;
+use core::mem;
 
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::{Identity, IsIdentity, VartimeMultiscalarMul};
 use merlin::Transcript;
+use rand::{CryptoRng, RngCore};
 
-use errors::MPCError;
+use errors::{MPCError, ProofError};
 use generators::{BulletproofGens, PedersenGens};
 use inner_product_proof;
-use range_proof::RangeProof;
-use transcript::TranscriptProtocol;
+use range_proof::{RangeProof, VerifiableProof};
+use transcript::{append_optional_message, labels, TranscriptProtocol};
 
 use util;
 
 use super::messages::*;
+use super::party::{Party, PartyAwaitingBitChallenge};
 
 /// Used to construct a dealer for the aggregated rangeproof MPC protocol.
 pub struct Dealer {}
 
 impl Dealer {
+    /// Returns a [`DealerBuilder`] for configuring a new dealer using
+    /// the given generators.
+    pub fn builder<'a, 'b>(
+        bp_gens: &'b BulletproofGens,
+        pc_gens: &'b PedersenGens,
+    ) -> DealerBuilder<'a, 'b> {
+        DealerBuilder {
+            bp_gens,
+            pc_gens,
+            transcript: None,
+            n: 0,
+            m: 0,
+            strict: false,
+        }
+    }
+
     /// Creates a new dealer coordinating `m` parties proving `n`-bit ranges.
+    ///
+    /// If `m` is not a power of two, the dealer pads the aggregation
+    /// up to the next power of two with parties committing to zero
+    /// with a zero blinding factor, as [`RangeProof::prove_multiple`]
+    /// does.  The dealer computes the padding parties' messages
+    /// itself, so the real parties are assigned the positions `0` to
+    /// `m - 1` as usual, and the padding parties never appear in the
+    /// messages exchanged with them.  The verifier only needs the
+    /// value commitments of the real parties.
+    ///
+    /// To reject malformed bit commitments early, create a strict
+    /// dealer with [`DealerBuilder::strict`].
+    ///
+    /// The dealer continues from the current state of the
+    /// `transcript`, so any data the proof should be bound to, like a
+    /// transaction ID, can be appended to it before calling this
+    /// function.  The verifier must append the same data to its
+    /// transcript.  See [`Dealer::new_with_context`].
     pub fn new<'a, 'b>(
         bp_gens: &'b BulletproofGens,
         pc_gens: &'b PedersenGens,
@@ -30,16 +91,120 @@ impl Dealer {
         n: usize,
         m: usize,
     ) -> Result<DealerAwaitingBitCommitments<'a, 'b>, MPCError> {
-        if !(n == 8 || n == 16 || n == 32 || n == 64) {
+        Dealer::validate_params(n, m)?;
+        Dealer::new_variable(bp_gens, pc_gens, transcript, &vec![n; m])
+    }
+
+    /// Checks that a dealer can be created for `m` parties proving
+    /// `n`-bit ranges, without creating one.
+    ///
+    /// This performs the checks of [`Dealer::new`] which don't depend
+    /// on the generators, so that untrusted parameters, for instance
+    /// from a network message, can be rejected before allocating
+    /// generators for them.  Returns [`MPCError::ZeroBitsize`] if `n`
+    /// is zero, [`MPCError::InvalidBitsize`] unless it is one of
+    /// \\(8, 16, 32, 64, 128\\), [`MPCError::EmptyAggregation`] if `m`
+    /// is zero, and [`MPCError::InvalidAggregation`] if `m` can't be
+    /// padded to a power of two.
+    ///
+    /// The errors are [`MPCError`]s, as returned by [`Dealer::new`],
+    /// so that a dealer can tell which check failed.  They convert to
+    /// the matching [`ProofError`] with `?` or `ProofError::from`.
+    pub fn validate_params(n: usize, m: usize) -> Result<(), MPCError> {
+        // Either a zero-bit range or an empty aggregation would give
+        // an empty inner-product proof, so they are rejected
+        // explicitly rather than left to the other checks.
+        if n == 0 {
+            return Err(MPCError::ZeroBitsize);
+        }
+        if !util::is_valid_bitsize(n) {
             return Err(MPCError::InvalidBitsize);
         }
-        if !m.is_power_of_two() {
+        if m == 0 {
+            return Err(MPCError::EmptyAggregation);
+        }
+        if m.checked_next_power_of_two().is_none() {
             return Err(MPCError::InvalidAggregation);
         }
-        if bp_gens.gens_capacity < n {
+        Ok(())
+    }
+
+    /// Creates a new dealer coordinating `m` parties proving `n`-bit
+    /// ranges, binding the proof to the application's `context`.
+    ///
+    /// The `context` is appended to the transcript under the label
+    /// [`CONTEXT`](::transcript_labels::CONTEXT), so the verifier must
+    /// call
+    /// `transcript.append_message(transcript_labels::CONTEXT, context)`
+    /// before verifying, and a proof created for another context
+    /// fails to verify.
+    pub fn new_with_context<'a, 'b>(
+        bp_gens: &'b BulletproofGens,
+        pc_gens: &'b PedersenGens,
+        transcript: &'a mut Transcript,
+        n: usize,
+        m: usize,
+        context: &[u8],
+    ) -> Result<DealerAwaitingBitCommitments<'a, 'b>, MPCError> {
+        transcript.append_message(labels::CONTEXT, context);
+        Dealer::new(bp_gens, pc_gens, transcript, n, m)
+    }
+
+    /// Creates a new dealer coordinating `m` parties proving `n`-bit
+    /// ranges, binding the proof to the application's `context` if
+    /// there is one.
+    ///
+    /// Unlike [`Dealer::new_with_context`], the transcript has the
+    /// same shape whether or not a context is given, and a missing
+    /// context is distinct from an empty one.  Under the label
+    /// [`CONTEXT`](::transcript_labels::CONTEXT), the dealer appends
+    /// a presence byte, `[1]` or `[0]`, and then the context, or an
+    /// empty message if there is none.  The verifier must call
+    /// [`append_optional_message`](::append_optional_message) with
+    /// the `CONTEXT` label and the same context before verifying.
+    pub fn new_with_optional_context<'a, 'b>(
+        bp_gens: &'b BulletproofGens,
+        pc_gens: &'b PedersenGens,
+        transcript: &'a mut Transcript,
+        n: usize,
+        m: usize,
+        context: Option<&[u8]>,
+    ) -> Result<DealerAwaitingBitCommitments<'a, 'b>, MPCError> {
+        append_optional_message(transcript, labels::CONTEXT, context);
+        Dealer::new(bp_gens, pc_gens, transcript, n, m)
+    }
+
+    /// Creates a new dealer coordinating parties proving ranges of
+    /// different bitsizes, where the `j`-th party proves a
+    /// `bitsizes[j]`-bit range.
+    ///
+    /// The parties' generators are laid out one after another, so
+    /// the `j`-th party must be given the sum of the bitsizes before
+    /// it, with
+    /// [`assign_variable_position`](::range_proof_mpc::party::PartyAwaitingPosition::assign_variable_position).
+    /// If the sum of the bitsizes is not a power of two, the dealer
+    /// pads the aggregation as [`RangeProof::prove_multiple_variable`]
+    /// does, with parties of the smallest bitsize placed after the
+    /// real ones, and computes their messages itself as described for
+    /// [`Dealer::new`].
+    pub fn new_variable<'a, 'b>(
+        bp_gens: &'b BulletproofGens,
+        pc_gens: &'b PedersenGens,
+        transcript: &'a mut Transcript,
+        bitsizes: &[usize],
+    ) -> Result<DealerAwaitingBitCommitments<'a, 'b>, MPCError> {
+        let m = bitsizes.len();
+        if m == 0 {
+            return Err(MPCError::EmptyAggregation);
+        }
+        for &n in bitsizes {
+            Dealer::validate_params(n, m)?;
+        }
+        let bitsizes = super::pad_bitsizes(bitsizes).map_err(|_| MPCError::InvalidAggregation)?;
+        if bitsizes.iter().any(|&n| bp_gens.gens_capacity < n) {
             return Err(MPCError::InvalidGeneratorsLength);
         }
-        if bp_gens.party_capacity < m {
+        if bp_gens.party_capacity < bitsizes.len() {
             return Err(MPCError::InvalidGeneratorsLength);
         }
 
@@ -57,19 +222,104 @@ impl Dealer {
         // state.
         let initial_transcript = transcript.clone();
 
-        transcript.rangeproof_domain_sep(n as u64, m as u64);
+        transcript.variable_rangeproof_domain_sep(&bitsizes);
 
         Ok(DealerAwaitingBitCommitments {
             bp_gens,
             pc_gens,
             transcript,
             initial_transcript,
-            n,
+            bitsizes,
             m,
+            strict: false,
         })
     }
 }
 
+/// Configures a dealer, as an alternative to the positional arguments
+/// of [`Dealer::new`].
+///
+/// The transcript, bitsize and number of parties are required, and
+/// the dealer is not strict unless requested.  Missing parameters are
+/// reported by [`DealerBuilder::build`]:
+///
+/// ```
+/// extern crate merlin;
+/// use merlin::Transcript;
+///
+/// extern crate bulletproofs;
+/// use bulletproofs::range_proof_mpc::dealer::Dealer;
+/// use bulletproofs::{BulletproofGens, PedersenGens};
+///
+/// # fn main() {
+/// let pc_gens = PedersenGens::default();
+/// let bp_gens = BulletproofGens::new(32, 4);
+/// let mut transcript = Transcript::new(b"doctest example");
+///
+/// let dealer = Dealer::builder(&bp_gens, &pc_gens)
+///     .transcript(&mut transcript)
+///     .bits(32)
+///     .parties(4)
+///     .strict(true)
+///     .build();
+/// assert!(dealer.is_ok());
+/// # }
+/// ```
+pub struct DealerBuilder<'a, 'b> {
+    bp_gens: &'b BulletproofGens,
+    pc_gens: &'b PedersenGens,
+    transcript: Option<&'a mut Transcript>,
+    n: usize,
+    m: usize,
+    strict: bool,
+}
+
+impl<'a, 'b> DealerBuilder<'a, 'b> {
+    /// Sets the transcript for the proof.
+    pub fn transcript(mut self, transcript: &'a mut Transcript) -> Self {
+        self.transcript = Some(transcript);
+        self
+    }
+
+    /// Sets the bitsize `n` of each party's range.
+    pub fn bits(mut self, n: usize) -> Self {
+        self.n = n;
+        self
+    }
+
+    /// Sets the number of parties `m`.
+    pub fn parties(mut self, m: usize) -> Self {
+        self.m = m;
+        self
+    }
+
+    /// Sets whether the dealer is strict.
+    ///
+    /// A strict dealer rejects bit commitments containing the
+    /// identity, or a value commitment already sent by another party.
+    /// These indicate a party which did not commit to any randomness,
+    /// and would otherwise only be caught when the proof fails to
+    /// verify.  The padding parties added by the dealer itself are
+    /// not checked.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Creates the dealer, as with [`Dealer::new`].
+    ///
+    /// Returns [`MPCError::MissingTranscript`] if no transcript was
+    /// set, and [`MPCError::ZeroBitsize`] or
+    /// [`MPCError::EmptyAggregation`] if the bitsize or the number
+    /// of parties were not set.
+    pub fn build(self) -> Result<DealerAwaitingBitCommitments<'a, 'b>, MPCError> {
+        let transcript = self.transcript.ok_or(MPCError::MissingTranscript)?;
+        let mut dealer = Dealer::new(self.bp_gens, self.pc_gens, transcript, self.n, self.m)?;
+        dealer.strict = self.strict;
+        Ok(dealer)
+    }
+}
+
 /// A dealer waiting for the parties to send their [`BitCommitment`]s.
 pub struct DealerAwaitingBitCommitments<'a, 'b> {
     bp_gens: &'b BulletproofGens,
@@ -78,8 +328,13 @@ pub struct DealerAwaitingBitCommitments<'a, 'b> {
     /// The dealer keeps a copy of the initial transcript state, so
     /// that it can attempt to verify the aggregated proof at the end.
     initial_transcript: Transcript,
-    n: usize,
+    /// The bitsize of each party's range, including the padding
+    /// parties after the first `m`
+    bitsizes: Vec<usize>,
+    /// The number of real parties
     m: usize,
+    /// Whether to reject identity or duplicate bit commitments
+    strict: bool,
 }
 
 impl<'a, 'b> DealerAwaitingBitCommitments<'a, 'b> {
@@ -91,33 +346,97 @@ impl<'a, 'b> DealerAwaitingBitCommitments<'a, 'b> {
         if self.m != bit_commitments.len() {
             return Err(MPCError::WrongNumBitCommitments);
         }
+        self.receive_bit_commitments_iter(bit_commitments)
+    }
+
+    /// Receive the parties' [`BitCommitment`]s in order, as they
+    /// arrive, and compute the [`BitChallenge`].
+    ///
+    /// Unlike [`receive_bit_commitments`](DealerAwaitingBitCommitments::receive_bit_commitments),
+    /// the commitments don't need to be collected beforehand: each
+    /// one is added to the transcript and to the aggregated \\(A\\)
+    /// and \\(S\\) when it is received.  The dealer still keeps the
+    /// commitments themselves, since they are needed to audit the
+    /// parties' proof shares.
+    ///
+    /// Receiving the wrong number of commitments, or malformed ones
+    /// for a strict dealer, is only detected once the iterator is
+    /// exhausted, after the transcript has been modified.
+    pub fn receive_bit_commitments_iter<I>(
+        self,
+        bit_commitments: I,
+    ) -> Result<(DealerAwaitingPolyCommitments<'a, 'b>, BitChallenge), MPCError>
+    where
+        I: IntoIterator<Item = BitCommitment>,
+    {
+        let mut A = RistrettoPoint::identity();
+        let mut S = RistrettoPoint::identity();
+        let mut received: Vec<BitCommitment> = Vec::with_capacity(self.m);
+        let mut bad_parties = Vec::new();
+
+        for vc in bit_commitments {
+            let j = received.len();
+            if j == self.m {
+                return Err(MPCError::WrongNumBitCommitments);
+            }
+
+            if self.strict
+                && (vc.V_j.is_identity()
+                    || vc.A_j.is_identity()
+                    || vc.S_j.is_identity()
+                    || received.iter().any(|prev| prev.V_j == vc.V_j))
+            {
+                bad_parties.push(j);
+            }
+
+            // Commit each V_j individually, and aggregate A_j, S_j
+            self.transcript.append_point(labels::V, &vc.V_j);
+            A += vc.A_j;
+            S += vc.S_j;
+            received.push(vc);
+        }
+
+        if received.len() != self.m {
+            return Err(MPCError::WrongNumBitCommitments);
+        }
+        if !bad_parties.is_empty() {
+            return Err(MPCError::MalformedBitCommitments { bad_parties });
+        }
 
-        // Commit each V_j individually
-        for vc in bit_commitments.iter() {
-            self.transcript.append_point(b"V", &vc.V_j);
+        // Add the padding parties after the real ones.  Their
+        // commitments to zero are the identity, which the verifier
+        // appends in their place.
+        let mut padding_parties = Vec::with_capacity(self.bitsizes.len() - self.m);
+        let mut offset: usize = self.bitsizes[..self.m].iter().sum();
+        for (j, &n) in self.bitsizes.iter().enumerate().skip(self.m) {
+            let (party, vc) = Party::new_u128(self.bp_gens, self.pc_gens, 0, Scalar::zero(), n)?
+                .assign_position_unblinded(j, offset)?;
+            self.transcript.append_point(labels::V, &vc.V_j);
+            A += vc.A_j;
+            S += vc.S_j;
+            padding_parties.push(party);
+            offset += n;
         }
 
         // Commit aggregated A_j, S_j
-        let A: RistrettoPoint = bit_commitments.iter().map(|vc| vc.A_j).sum();
-        self.transcript.append_point(b"A", &A.compress());
+        self.transcript.append_point(labels::A, &A.compress());
+        self.transcript.append_point(labels::S, &S.compress());
 
-        let S: RistrettoPoint = bit_commitments.iter().map(|vc| vc.S_j).sum();
-        self.transcript.append_point(b"S", &S.compress());
-
-        let y = self.transcript.challenge_scalar(b"y");
-        let z = self.transcript.challenge_scalar(b"z");
+        let y = self.transcript.challenge_scalar(labels::Y);
+        let z = self.transcript.challenge_scalar(labels::Z);
         let bit_challenge = BitChallenge { y, z };
 
         Ok((
             DealerAwaitingPolyCommitments {
-                n: self.n,
+                bitsizes: self.bitsizes,
                 m: self.m,
                 transcript: self.transcript,
                 initial_transcript: self.initial_transcript,
                 bp_gens: self.bp_gens,
                 pc_gens: self.pc_gens,
                 bit_challenge,
-                bit_commitments,
+                bit_commitments: received,
+                padding_parties,
                 A,
                 S,
             },
@@ -129,14 +448,20 @@ impl<'a, 'b> DealerAwaitingBitCommitments<'a, 'b> {
 /// A dealer which has sent the [`BitChallenge`] to the parties and
 /// is waiting for their [`PolyCommitment`]s.
 pub struct DealerAwaitingPolyCommitments<'a, 'b> {
-    n: usize,
+    /// The bitsize of each party's range, including the padding
+    /// parties after the first `m`
+    bitsizes: Vec<usize>,
+    /// The number of real parties
     m: usize,
     transcript: &'a mut Transcript,
     initial_transcript: Transcript,
     bp_gens: &'b BulletproofGens,
     pc_gens: &'b PedersenGens,
     bit_challenge: BitChallenge,
+    /// The real parties' bit commitments
     bit_commitments: Vec<BitCommitment>,
+    /// The padding parties, run by the dealer
+    padding_parties: Vec<PartyAwaitingBitChallenge<'b>>,
     /// Aggregated commitment to the parties' bits
     A: RistrettoPoint,
     /// Aggregated commitment to the parties' bit blindings
@@ -154,19 +479,39 @@ impl<'a, 'b> DealerAwaitingPolyCommitments<'a, 'b> {
             return Err(MPCError::WrongNumPolyCommitments);
         }
 
+        let bit_challenge = self.bit_challenge;
+        let (padding_parties, padding_commitments): (Vec<_>, Vec<_>) = self
+            .padding_parties
+            .into_iter()
+            .map(|p| p.apply_challenge_unblinded(&bit_challenge))
+            .unzip();
+
         // Commit sums of T_1_j's and T_2_j's
-        let T_1: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_1_j).sum();
-        let T_2: RistrettoPoint = poly_commitments.iter().map(|pc| pc.T_2_j).sum();
+        let T_1: RistrettoPoint = poly_commitments
+            .iter()
+            .chain(padding_commitments.iter())
+            .map(|pc| pc.T_1_j)
+            .sum();
+        let T_2: RistrettoPoint = poly_commitments
+            .iter()
+            .chain(padding_commitments.iter())
+            .map(|pc| pc.T_2_j)
+            .sum();
 
-        self.transcript.append_point(b"T_1", &T_1.compress());
-        self.transcript.append_point(b"T_2", &T_2.compress());
+        self.transcript.append_point(labels::T_1, &T_1.compress());
+        self.transcript.append_point(labels::T_2, &T_2.compress());
 
-        let x = self.transcript.challenge_scalar(b"x");
+        let x = self.transcript.challenge_scalar(labels::X);
         let poly_challenge = PolyChallenge { x };
 
+        let padding_shares = padding_parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&poly_challenge))
+            .collect::<Result<Vec<_>, _>>()?;
+
         Ok((
             DealerAwaitingProofShares {
-                n: self.n,
+                bitsizes: self.bitsizes,
                 m: self.m,
                 transcript: self.transcript,
                 initial_transcript: self.initial_transcript,
@@ -178,6 +523,7 @@ impl<'a, 'b> DealerAwaitingPolyCommitments<'a, 'b> {
                 S: self.S,
                 poly_challenge,
                 poly_commitments,
+                padding_shares,
                 T_1,
                 T_2,
             },
@@ -190,7 +536,10 @@ impl<'a, 'b> DealerAwaitingPolyCommitments<'a, 'b> {
 /// is waiting to aggregate their [`ProofShare`]s into a
 /// [`RangeProof`].
 pub struct DealerAwaitingProofShares<'a, 'b> {
-    n: usize,
+    /// The bitsize of each party's range, including the padding
+    /// parties after the first `m`
+    bitsizes: Vec<usize>,
+    /// The number of real parties
     m: usize,
     transcript: &'a mut Transcript,
     initial_transcript: Transcript,
@@ -200,6 +549,8 @@ pub struct DealerAwaitingProofShares<'a, 'b> {
     bit_commitments: Vec<BitCommitment>,
     poly_challenge: PolyChallenge,
     poly_commitments: Vec<PolyCommitment>,
+    /// The proof shares of the padding parties, computed by the dealer
+    padding_shares: Vec<ProofShare>,
     A: RistrettoPoint,
     S: RistrettoPoint,
     T_1: RistrettoPoint,
@@ -207,53 +558,226 @@ pub struct DealerAwaitingProofShares<'a, 'b> {
 }
 
 impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
+    /// Verify the proof share of the `j`-th party on its own, against
+    /// the commitments and challenges held by the dealer.
+    ///
+    /// This lets the dealer reject a malformed share as soon as it is
+    /// received, rather than after aggregating all of them.
+    pub fn verify_share(&self, j: usize, proof_share: &ProofShare) -> Result<(), ProofError> {
+        self.share_equations(j, proof_share)?.verify()
+    }
+
+    /// Verify the proof shares of all of the parties at once, with a
+    /// single multiscalar multiplication.
+    ///
+    /// This is a convenience wrapper around
+    /// [`batch_verify_shares_with_rng`](DealerAwaitingProofShares::batch_verify_shares_with_rng),
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn batch_verify_shares(&self, proof_shares: &[ProofShare]) -> Result<(), MPCError> {
+        self.batch_verify_shares_with_rng(proof_shares, &mut rand::thread_rng())
+    }
+
+    /// Verify the proof shares of all of the parties at once, using
+    /// `rng` to combine their equations into a single multiscalar
+    /// multiplication.
+    ///
+    /// This is cheaper than calling
+    /// [`verify_share`](DealerAwaitingProofShares::verify_share) for
+    /// each share.  If the combined check fails, the shares are
+    /// checked one at a time, and the indices of all of the malformed
+    /// shares are returned in [`MPCError::MalformedProofShares`].
+    pub fn batch_verify_shares_with_rng<T: RngCore + CryptoRng>(
+        &self,
+        proof_shares: &[ProofShare],
+        rng: &mut T,
+    ) -> Result<(), MPCError> {
+        if self.m != proof_shares.len() {
+            return Err(MPCError::WrongNumProofShares);
+        }
+
+        let mut scalars = Vec::new();
+        let mut points = Vec::new();
+        let mut all_well_formed = true;
+        for (j, proof_share) in proof_shares.iter().enumerate() {
+            let eq = match self.share_equations(j, proof_share) {
+                Ok(eq) => eq,
+                Err(_) => {
+                    all_well_formed = false;
+                    break;
+                }
+            };
+
+            // Weight each equation by a random scalar, so that the
+            // errors of different equations can't cancel out.
+            let c_P = Scalar::random(rng);
+            let c_t = Scalar::random(rng);
+            scalars.extend(eq.P_scalars.iter().map(|s| c_P * s));
+            scalars.extend(eq.t_scalars.iter().map(|s| c_t * s));
+            points.extend(eq.P_points);
+            points.extend(eq.t_points);
+        }
+
+        if all_well_formed {
+            let check = RistrettoPoint::vartime_multiscalar_mul(&scalars, &points);
+            if check.is_identity() {
+                return Ok(());
+            }
+        }
+
+        // The batch failed, so check the shares one at a time to find
+        // all of the malformed ones.
+        let bad_shares = (0..self.m)
+            .filter(|&j| self.verify_share(j, &proof_shares[j]).is_err())
+            .collect();
+        Err(MPCError::MalformedProofShares { bad_shares })
+    }
+
+    /// Computes the verification equations of the proof share of the
+    /// `j`-th party, against the commitments and challenges held by
+    /// the dealer.
+    fn share_equations(
+        &self,
+        j: usize,
+        proof_share: &ProofShare,
+    ) -> Result<ShareEquations, ProofError> {
+        if j >= self.m || proof_share.l_vec.len() != self.bitsizes[j] {
+            return Err(ProofError::VerificationError);
+        }
+        let offset = self.bitsizes[..j].iter().sum();
+
+        proof_share.verification_equations(
+            &self.bp_gens,
+            &self.pc_gens,
+            j,
+            offset,
+            &self.bit_commitments[j],
+            &self.bit_challenge,
+            &self.poly_commitments[j],
+            &self.poly_challenge,
+        )
+    }
+
     /// Assembles proof shares into an `RangeProof`.
     ///
     /// Used as a helper function by `receive_trusted_shares` (which
     /// just hands back the result) and `receive_shares` (which
     /// validates the proof shares.
     fn assemble_shares(&mut self, proof_shares: &[ProofShare]) -> Result<RangeProof, MPCError> {
+        self.check_share_lengths(proof_shares)?;
+        let (l_vec, r_vec) = self.concatenate_share_vectors(proof_shares);
+        Ok(self.assemble_vectors(proof_shares, l_vec, r_vec))
+    }
+
+    /// Assembles proof shares into an `RangeProof` as
+    /// `assemble_shares` does, taking the shares by value.
+    ///
+    /// Used as a helper function by `receive_trusted_shares_owned`.
+    fn assemble_owned_shares(
+        &mut self,
+        mut proof_shares: Vec<ProofShare>,
+    ) -> Result<RangeProof, MPCError> {
+        self.check_share_lengths(&proof_shares)?;
+
+        // A lone share's vectors are the whole proof's, so they can be
+        // moved out of it instead of copied.  Otherwise they are
+        // copied into a single allocation, and the shares clear their
+        // own copies when they are dropped.
+        let (l_vec, r_vec) = if proof_shares.len() == 1 && self.padding_shares.is_empty() {
+            let share = &mut proof_shares[0];
+            (
+                mem::replace(&mut share.l_vec, Vec::new()),
+                mem::replace(&mut share.r_vec, Vec::new()),
+            )
+        } else {
+            self.concatenate_share_vectors(&proof_shares)
+        };
+        Ok(self.assemble_vectors(&proof_shares, l_vec, r_vec))
+    }
+
+    /// Checks that there is one share per party, and that each share's
+    /// vectors have its party's bitsize.
+    fn check_share_lengths(&self, proof_shares: &[ProofShare]) -> Result<(), MPCError> {
         if self.m != proof_shares.len() {
             return Err(MPCError::WrongNumProofShares);
         }
 
+        // Check the lengths before concatenating the shares' vectors,
+        // since a share of the wrong length would shift all of the
+        // following parties' entries.
+        let bad_shares: Vec<usize> = proof_shares
+            .iter()
+            .zip(self.bitsizes.iter())
+            .enumerate()
+            .filter(|(_, (ps, &n))| ps.l_vec.len() != n || ps.r_vec.len() != n)
+            .map(|(j, _)| j)
+            .collect();
+        if !bad_shares.is_empty() {
+            return Err(MPCError::MalformedProofShares { bad_shares });
+        }
+        Ok(())
+    }
+
+    /// Concatenates the \\(\mathbf{l}(x)\\) and \\(\mathbf{r}(x)\\)
+    /// vectors of the real and padding parties' shares.
+    fn concatenate_share_vectors(&self, proof_shares: &[ProofShare]) -> (Vec<Scalar>, Vec<Scalar>) {
+        // Allocate the concatenated vectors at their final size, and
+        // copy the shares' vectors straight into them.
+        let nm = self.bitsizes.iter().sum();
+        let mut l_vec = Vec::with_capacity(nm);
+        let mut r_vec = Vec::with_capacity(nm);
+        for ps in proof_shares.iter().chain(self.padding_shares.iter()) {
+            l_vec.extend_from_slice(&ps.l_vec);
+            r_vec.extend_from_slice(&ps.r_vec);
+        }
+        (l_vec, r_vec)
+    }
+
+    /// Assembles the `RangeProof` from the shares, whose lengths have
+    /// been checked, and their concatenated vectors.
+    fn assemble_vectors(
+        &mut self,
+        proof_shares: &[ProofShare],
+        l_vec: Vec<Scalar>,
+        r_vec: Vec<Scalar>,
+    ) -> RangeProof {
+        let proof_shares: Vec<&ProofShare> = proof_shares
+            .iter()
+            .chain(self.padding_shares.iter())
+            .collect();
         let t_x: Scalar = proof_shares.iter().map(|ps| ps.t_x).sum();
         let t_x_blinding: Scalar = proof_shares.iter().map(|ps| ps.t_x_blinding).sum();
         let e_blinding: Scalar = proof_shares.iter().map(|ps| ps.e_blinding).sum();
 
-        self.transcript.append_scalar(b"t_x", &t_x);
+        self.transcript.append_scalar(labels::T_X, &t_x);
+        self.transcript
+            .append_scalar(labels::T_X_BLINDING, &t_x_blinding);
         self.transcript
-            .append_scalar(b"t_x_blinding", &t_x_blinding);
-        self.transcript.append_scalar(b"e_blinding", &e_blinding);
+            .append_scalar(labels::E_BLINDING, &e_blinding);
 
-        // Get a challenge value to combine statements for the IPP
-        let w = self.transcript.challenge_scalar(b"w");
+        // Get a challenge value to combine statements for the IPP.
+        // The inner product is committed to with the value base B,
+        // like t(x), not with the blinding base.
+        let w = self.transcript.challenge_scalar(labels::W);
         let Q = w * self.pc_gens.B;
 
-        let Hprime_factors: Vec<Scalar> = util::exp_iter(self.bit_challenge.y.invert())
-            .take(self.n * self.m)
-            .collect();
-
-        let l_vec: Vec<Scalar> = proof_shares
-            .iter()
-            .flat_map(|ps| ps.l_vec.clone().into_iter())
-            .collect();
-        let r_vec: Vec<Scalar> = proof_shares
-            .iter()
-            .flat_map(|ps| ps.r_vec.clone().into_iter())
-            .collect();
+        let Hprime_factors =
+            util::exp_vec(self.bit_challenge.y.invert(), self.bitsizes.iter().sum());
 
+        // The inner-product proof starts by appending its own domain
+        // separator to the transcript, which separates its challenges
+        // from those of the rangeproof.
         let ipp_proof = inner_product_proof::InnerProductProof::create(
             self.transcript,
             &Q,
             &Hprime_factors,
-            self.bp_gens.G(self.n, self.m).cloned().collect(),
-            self.bp_gens.H(self.n, self.m).cloned().collect(),
+            self.bp_gens.G_variable(&self.bitsizes).cloned().collect(),
+            self.bp_gens.H_variable(&self.bitsizes).cloned().collect(),
             l_vec,
             r_vec,
         );
 
-        Ok(RangeProof {
+        RangeProof {
             A: self.A.compress(),
             S: self.S.compress(),
             T_1: self.T_1.compress(),
@@ -262,7 +786,7 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
             t_x_blinding,
             e_blinding,
             ipp_proof,
-        })
+        }
     }
 
     /// Assemble the final aggregated [`RangeProof`] from the given
@@ -278,7 +802,26 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
     /// performing local aggregation,
     /// [`receive_trusted_shares`](DealerAwaitingProofShares::receive_trusted_shares)
     /// saves time by skipping verification of the aggregated proof.
-    pub fn receive_shares(mut self, proof_shares: &[ProofShare]) -> Result<RangeProof, MPCError> {
+    ///
+    /// This is a convenience wrapper around
+    /// [`receive_shares_with_rng`](DealerAwaitingProofShares::receive_shares_with_rng),
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn receive_shares(self, proof_shares: &[ProofShare]) -> Result<RangeProof, MPCError> {
+        self.receive_shares_with_rng(proof_shares, &mut rand::thread_rng())
+    }
+
+    /// Assemble the final aggregated [`RangeProof`] from the given
+    /// `proof_shares`, then validate the proof with `rng` to ensure
+    /// that all `ProofShare`s were well-formed.
+    ///
+    /// See [`receive_shares`](DealerAwaitingProofShares::receive_shares)
+    /// for details.
+    pub fn receive_shares_with_rng<T: RngCore + CryptoRng>(
+        mut self,
+        proof_shares: &[ProofShare],
+        rng: &mut T,
+    ) -> Result<RangeProof, MPCError> {
         let proof = self.assemble_shares(proof_shares)?;
 
         let Vs: Vec<_> = self.bit_commitments.iter().map(|vc| vc.V_j).collect();
@@ -286,31 +829,64 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
         // See comment in `Dealer::new` for why we use `initial_transcript`
         let transcript = &mut self.initial_transcript;
         if proof
-            .verify_multiple(self.bp_gens, self.pc_gens, transcript, &Vs, self.n)
+            .verify_multiple_variable_with_rng(
+                self.bp_gens,
+                self.pc_gens,
+                transcript,
+                &Vs,
+                &self.bitsizes[..self.m],
+                rng,
+            )
             .is_ok()
         {
             Ok(proof)
         } else {
             // Proof verification failed. Now audit the parties:
-            let mut bad_shares = Vec::new();
-            for j in 0..self.m {
-                match proof_shares[j].audit_share(
-                    &self.bp_gens,
-                    &self.pc_gens,
-                    j,
-                    &self.bit_commitments[j],
-                    &self.bit_challenge,
-                    &self.poly_commitments[j],
-                    &self.poly_challenge,
-                ) {
-                    Ok(_) => {}
-                    Err(_) => bad_shares.push(j),
-                }
-            }
+            let bad_shares = (0..self.m)
+                .filter(|&j| self.verify_share(j, &proof_shares[j]).is_err())
+                .collect();
             Err(MPCError::MalformedProofShares { bad_shares })
         }
     }
 
+    /// Assemble and validate the final aggregated [`RangeProof`] as
+    /// [`receive_shares`](DealerAwaitingProofShares::receive_shares)
+    /// does, and bundle it with the parties' value commitments and
+    /// bitsizes into a [`VerifiableProof`].
+    ///
+    /// This is a convenience wrapper around
+    /// [`receive_verifiable_shares_with_rng`](DealerAwaitingProofShares::receive_verifiable_shares_with_rng),
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn receive_verifiable_shares(
+        self,
+        proof_shares: &[ProofShare],
+    ) -> Result<VerifiableProof, MPCError> {
+        self.receive_verifiable_shares_with_rng(proof_shares, &mut rand::thread_rng())
+    }
+
+    /// Assemble and validate the final aggregated [`RangeProof`] with
+    /// `rng`, and bundle it with the parties' value commitments and
+    /// bitsizes into a [`VerifiableProof`].
+    ///
+    /// See [`receive_shares`](DealerAwaitingProofShares::receive_shares)
+    /// for details.
+    pub fn receive_verifiable_shares_with_rng<T: RngCore + CryptoRng>(
+        self,
+        proof_shares: &[ProofShare],
+        rng: &mut T,
+    ) -> Result<VerifiableProof, MPCError> {
+        let value_commitments = self.bit_commitments.iter().map(|vc| vc.V_j).collect();
+        let bitsizes = self.bitsizes[..self.m].to_vec();
+        let proof = self.receive_shares_with_rng(proof_shares, rng)?;
+
+        Ok(VerifiableProof {
+            proof,
+            value_commitments,
+            bitsizes,
+        })
+    }
+
     /// Assemble the final aggregated [`RangeProof`] from the given
     /// `proof_shares`, but skip validation of the proof.
     ///
@@ -325,10 +901,112 @@ impl<'a, 'b> DealerAwaitingProofShares<'a, 'b> {
     /// [`receive_shares`](DealerAwaitingProofShares::receive_shares),
     /// which validates that all shares are well-formed, or else
     /// detects which party(ies) submitted malformed shares.
+    ///
+    /// With the `dealer-checks` feature enabled, the shares are
+    /// checked with
+    /// [`batch_verify_shares`](DealerAwaitingProofShares::batch_verify_shares)
+    /// before they are aggregated, so that a share whose blinding
+    /// factors are inconsistent with its commitments is reported here,
+    /// rather than as a proof which fails to verify.
     pub fn receive_trusted_shares(
         mut self,
         proof_shares: &[ProofShare],
     ) -> Result<RangeProof, MPCError> {
+        #[cfg(feature = "dealer-checks")]
+        self.batch_verify_shares(proof_shares)?;
+
         self.assemble_shares(proof_shares)
     }
+
+    /// Assemble the final aggregated [`RangeProof`] from the given
+    /// `proof_shares`, taking them by value, but skip validation of
+    /// the proof.
+    ///
+    /// This creates the same proof as
+    /// [`receive_trusted_shares`](DealerAwaitingProofShares::receive_trusted_shares),
+    /// with the same **WARNING**.  Since the shares are consumed, a
+    /// single share's vectors are moved into the proof rather than
+    /// copied, and several shares' vectors are copied into one
+    /// allocation of the final size.
+    pub fn receive_trusted_shares_owned(
+        mut self,
+        proof_shares: Vec<ProofShare>,
+    ) -> Result<RangeProof, MPCError> {
+        #[cfg(feature = "dealer-checks")]
+        self.batch_verify_shares(&proof_shares)?;
+
+        self.assemble_owned_shares(proof_shares)
+    }
+
+    /// Assemble the final aggregated [`RangeProof`] from the given
+    /// `proof_shares`, without validating it, and return its
+    /// serialization as by [`RangeProof::to_bytes`].
+    ///
+    /// ## WARNING
+    ///
+    /// Like
+    /// [`receive_trusted_shares`](DealerAwaitingProofShares::receive_trusted_shares),
+    /// this function does **NOT** validate the proof shares.
+    pub fn finalize_to_bytes(self, proof_shares: &[ProofShare]) -> Result<Vec<u8>, MPCError> {
+        Ok(self.receive_trusted_shares(proof_shares)?.to_bytes())
+    }
+
+    /// Switches the dealer to receiving the [`ProofShare`]s one at a
+    /// time, in any order, verifying each share as soon as it arrives.
+    ///
+    /// This lets the dealer abort on the first malformed share,
+    /// without waiting for the other parties.
+    pub fn collect_shares(self) -> DealerCollectingProofShares<'a, 'b> {
+        let proof_shares = (0..self.m).map(|_| None).collect();
+        DealerCollectingProofShares {
+            dealer: self,
+            proof_shares,
+        }
+    }
+}
+
+/// A dealer which is receiving the parties' [`ProofShare`]s one at a
+/// time, as returned by
+/// [`collect_shares`](DealerAwaitingProofShares::collect_shares).
+pub struct DealerCollectingProofShares<'a, 'b> {
+    dealer: DealerAwaitingProofShares<'a, 'b>,
+    proof_shares: Vec<Option<ProofShare>>,
+}
+
+impl<'a, 'b> DealerCollectingProofShares<'a, 'b> {
+    /// Verifies the proof share of the `j`-th party and keeps it for
+    /// aggregation.
+    ///
+    /// Returns [`MPCError::MalformedProofShares`] for the `j`-th party
+    /// if the share is malformed, in which case the protocol should
+    /// be aborted, or [`MPCError::WrongNumProofShares`] if a share
+    /// was already received from that party.
+    pub fn add_share(&mut self, j: usize, proof_share: ProofShare) -> Result<(), MPCError> {
+        if self.dealer.verify_share(j, &proof_share).is_err() {
+            return Err(MPCError::MalformedProofShares {
+                bad_shares: vec![j],
+            });
+        }
+        if self.proof_shares[j].is_some() {
+            return Err(MPCError::WrongNumProofShares);
+        }
+        self.proof_shares[j] = Some(proof_share);
+        Ok(())
+    }
+
+    /// Assembles the final aggregated [`RangeProof`] from the
+    /// received shares.
+    ///
+    /// Since every share was verified when it was added, the proof is
+    /// not verified again.  Returns
+    /// [`MPCError::WrongNumProofShares`] if a share is missing.
+    pub fn finalize(self) -> Result<RangeProof, MPCError> {
+        let proof_shares: Vec<ProofShare> = self
+            .proof_shares
+            .into_iter()
+            .collect::<Option<_>>()
+            .ok_or(MPCError::WrongNumProofShares)?;
+        let mut dealer = self.dealer;
+        dealer.assemble_shares(&proof_shares)
+    }
 }
@@ -0,0 +1,254 @@
+//! A fixed-size encoding of rangeproofs with 64 bits in total.
+
+#![allow(non_snake_case)]
+
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::scalar::Scalar;
+
+use errors::ProofError;
+use inner_product_proof::InnerProductProof;
+use util::{read32, read_point};
+
+use super::RangeProof;
+
+/// The number of rounds \\(\lg 64\\) of the inner-product proof.
+const LG_N: usize = 6;
+
+/// A rangeproof with 64 bits in total and \\(\lg 64 = 6\\)
+/// inner-product rounds, stored in fixed-size arrays rather than
+/// `Vec`s.
+///
+/// This is the shape produced by [`RangeProof::prove_single`] with
+/// \\(n = 64\\), but also by any aggregation with \\(n \cdot m = 64\\),
+/// e.g. [`RangeProof::prove_multiple`] for two 32-bit values.  The
+/// proof does not record \\(n\\) and \\(m\\), which the verifier must
+/// supply along with the value commitments.  It can be created,
+/// encoded and decoded without allocating, which makes it suitable
+/// for storing and transmitting proofs on constrained devices.  To
+/// verify it, convert it to a [`RangeProof`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RangeProof64 {
+    A: CompressedRistretto,
+    S: CompressedRistretto,
+    T_1: CompressedRistretto,
+    T_2: CompressedRistretto,
+    t_x: Scalar,
+    t_x_blinding: Scalar,
+    e_blinding: Scalar,
+    L_vec: [CompressedRistretto; LG_N],
+    R_vec: [CompressedRistretto; LG_N],
+    a: Scalar,
+    b: Scalar,
+}
+
+impl RangeProof64 {
+    /// The length in bytes of the encoding returned by
+    /// [`RangeProof64::to_bytes`], which is
+    /// [`RangeProof::proof_size`]`(64, 1)`.
+    pub const SIZE: usize = (2 * LG_N + 9) * 32;
+
+    /// Converts a rangeproof with 64 bits in total to the fixed-size
+    /// type.
+    ///
+    /// Returns [`ProofError::FormatError`] if the proof has a
+    /// different shape, i.e. if it doesn't have exactly 6
+    /// inner-product rounds.  This does not check which values or
+    /// bitsizes the proof is for.
+    pub fn from_range_proof(proof: &RangeProof) -> Result<RangeProof64, ProofError> {
+        let ipp = &proof.ipp_proof;
+        if ipp.L_vec.len() != LG_N || ipp.R_vec.len() != LG_N {
+            return Err(ProofError::FormatError);
+        }
+
+        let mut L_vec = [CompressedRistretto::default(); LG_N];
+        let mut R_vec = [CompressedRistretto::default(); LG_N];
+        L_vec.copy_from_slice(&ipp.L_vec);
+        R_vec.copy_from_slice(&ipp.R_vec);
+
+        Ok(RangeProof64 {
+            A: proof.A,
+            S: proof.S,
+            T_1: proof.T_1,
+            T_2: proof.T_2,
+            t_x: proof.t_x,
+            t_x_blinding: proof.t_x_blinding,
+            e_blinding: proof.e_blinding,
+            L_vec,
+            R_vec,
+            a: ipp.a,
+            b: ipp.b,
+        })
+    }
+
+    /// Serializes the proof into a byte array of
+    /// [`RangeProof64::SIZE`] bytes.
+    ///
+    /// The encoding is the same as that of [`RangeProof::to_bytes`].
+    pub fn to_bytes(&self) -> [u8; RangeProof64::SIZE] {
+        let mut buf = [0u8; RangeProof64::SIZE];
+        {
+            let mut chunks = buf.chunks_mut(32);
+            let mut put = |bytes: &[u8; 32]| {
+                chunks.next().unwrap().copy_from_slice(bytes);
+            };
+
+            put(self.A.as_bytes());
+            put(self.S.as_bytes());
+            put(self.T_1.as_bytes());
+            put(self.T_2.as_bytes());
+            put(self.t_x.as_bytes());
+            put(self.t_x_blinding.as_bytes());
+            put(self.e_blinding.as_bytes());
+            for (L, R) in self.L_vec.iter().zip(self.R_vec.iter()) {
+                put(L.as_bytes());
+                put(R.as_bytes());
+            }
+            put(self.a.as_bytes());
+            put(self.b.as_bytes());
+        }
+        buf
+    }
+
+    /// Deserializes the proof from a byte slice of
+    /// [`RangeProof64::SIZE`] bytes, without allocating.
+    ///
+    /// Returns an error if the byte slice has the wrong length, or
+    /// cannot be parsed into a [`RangeProof64`], in the same cases as
+    /// [`RangeProof::from_bytes`].
+    pub fn from_bytes(slice: &[u8]) -> Result<RangeProof64, ProofError> {
+        if slice.len() != RangeProof64::SIZE {
+            return Err(ProofError::FormatError);
+        }
+
+        let scalar = |i: usize| {
+            Scalar::from_canonical_bytes(read32(&slice[i * 32..])).ok_or(ProofError::FormatError)
+        };
+
+        let mut L_vec = [CompressedRistretto::default(); LG_N];
+        let mut R_vec = [CompressedRistretto::default(); LG_N];
+        for (i, (L, R)) in L_vec.iter_mut().zip(R_vec.iter_mut()).enumerate() {
+            *L = read_point(&slice[(7 + 2 * i) * 32..])?;
+            *R = read_point(&slice[(8 + 2 * i) * 32..])?;
+        }
+
+        Ok(RangeProof64 {
+            A: read_point(slice)?,
+            S: read_point(&slice[32..])?,
+            T_1: read_point(&slice[2 * 32..])?,
+            T_2: read_point(&slice[3 * 32..])?,
+            t_x: scalar(4)?,
+            t_x_blinding: scalar(5)?,
+            e_blinding: scalar(6)?,
+            L_vec,
+            R_vec,
+            a: scalar(7 + 2 * LG_N)?,
+            b: scalar(8 + 2 * LG_N)?,
+        })
+    }
+}
+
+impl From<RangeProof64> for RangeProof {
+    fn from(proof: RangeProof64) -> RangeProof {
+        RangeProof {
+            A: proof.A,
+            S: proof.S,
+            T_1: proof.T_1,
+            T_2: proof.T_2,
+            t_x: proof.t_x,
+            t_x_blinding: proof.t_x_blinding,
+            e_blinding: proof.e_blinding,
+            ipp_proof: InnerProductProof {
+                L_vec: proof.L_vec.to_vec(),
+                R_vec: proof.R_vec.to_vec(),
+                a: proof.a,
+                b: proof.b,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use merlin::Transcript;
+
+    use generators::{BulletproofGens, PedersenGens};
+
+    #[test]
+    fn fixed_size_proof_roundtrips() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(64, 1);
+
+        let mut transcript = Transcript::new(b"RangeProof64Test");
+        let (proof, V) = RangeProof::prove_single(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            u64::max_value(),
+            &Scalar::from(3u64),
+            64,
+        )
+        .unwrap();
+
+        let fixed = RangeProof64::from_range_proof(&proof).unwrap();
+        let bytes = fixed.to_bytes();
+        assert_eq!(bytes.len(), RangeProof::proof_size(64, 1).unwrap());
+        assert_eq!(&bytes[..], &proof.to_bytes()[..]);
+        assert_eq!(RangeProof64::from_bytes(&bytes).unwrap(), fixed);
+
+        let decoded: RangeProof = RangeProof64::from_bytes(&bytes).unwrap().into();
+        let mut transcript = Transcript::new(b"RangeProof64Test");
+        assert!(decoded
+            .verify_single(&bp_gens, &pc_gens, &mut transcript, &V, 64)
+            .is_ok());
+
+        assert_eq!(
+            RangeProof64::from_bytes(&bytes[..RangeProof64::SIZE - 32]),
+            Err(ProofError::FormatError)
+        );
+
+        // Proofs of other shapes don't fit.
+        let mut transcript = Transcript::new(b"RangeProof64Test");
+        let (proof, _) = RangeProof::prove_single(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            7,
+            &Scalar::from(3u64),
+            32,
+        )
+        .unwrap();
+        assert_eq!(
+            RangeProof64::from_range_proof(&proof),
+            Err(ProofError::FormatError)
+        );
+    }
+
+    #[test]
+    fn aggregated_proof_of_64_bits_fits() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 2);
+        let blindings = [Scalar::from(3u64), Scalar::from(4u64)];
+
+        let mut transcript = Transcript::new(b"RangeProof64Test");
+        let (proof, Vs) = RangeProof::prove_multiple(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &[7, u32::max_value() as u64],
+            &blindings,
+            32,
+        )
+        .unwrap();
+
+        let fixed = RangeProof64::from_range_proof(&proof).unwrap();
+        assert_eq!(&fixed.to_bytes()[..], &proof.to_bytes()[..]);
+
+        let decoded: RangeProof = RangeProof64::from_bytes(&fixed.to_bytes()).unwrap().into();
+        let mut transcript = Transcript::new(b"RangeProof64Test");
+        assert!(decoded
+            .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &Vs, 32)
+            .is_ok());
+    }
+}
@@ -4,13 +4,23 @@
 //! For more explanation of how the `dealer`, `party`, and `messages` modules orchestrate the protocol execution, see
 //! [the API for the aggregated multiparty computation protocol](../aggregation/index.html#api-for-the-aggregated-multiparty-computation-protocol).
 
+use alloc::vec::Vec;
+use core::fmt;
+
+use clear_on_drop::clear::Clear;
 use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
 use curve25519_dalek::scalar::Scalar;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
 
+use errors::ProofError;
 use generators::{BulletproofGens, PedersenGens};
 
+use serde::de::Error as DeserializeError;
+use serde::{Deserialize, Deserializer};
+
 /// A commitment to the bits of a party's value.
-#[derive(Serialize, Deserialize, Copy, Clone, Debug)]
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
 pub struct BitCommitment {
     pub(super) V_j: CompressedRistretto,
     pub(super) A_j: RistrettoPoint,
@@ -18,28 +28,57 @@ pub struct BitCommitment {
 }
 
 /// Challenge values derived from all parties' [`BitCommitment`]s.
-#[derive(Serialize, Deserialize, Copy, Clone, Debug)]
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
 pub struct BitChallenge {
     pub(super) y: Scalar,
     pub(super) z: Scalar,
 }
 
+#[cfg(feature = "test-internals")]
+impl BitChallenge {
+    /// Creates a challenge with arbitrary values \\(y, z\\), so that
+    /// the parties' arithmetic can be tested for random challenges.
+    pub fn new(y: Scalar, z: Scalar) -> BitChallenge {
+        BitChallenge { y, z }
+    }
+}
+
 /// A commitment to a party's polynomial coefficents.
-#[derive(Serialize, Deserialize, Copy, Clone, Debug)]
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
 pub struct PolyCommitment {
     pub(super) T_1_j: RistrettoPoint,
     pub(super) T_2_j: RistrettoPoint,
 }
 
 /// Challenge values derived from all parties' [`PolyCommitment`]s.
-#[derive(Serialize, Deserialize, Copy, Clone, Debug)]
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
 pub struct PolyChallenge {
     pub(super) x: Scalar,
 }
 
+#[cfg(feature = "test-internals")]
+impl PolyChallenge {
+    /// Creates a challenge with an arbitrary value \\(x\\), so that
+    /// the parties' arithmetic can be tested for random challenges.
+    pub fn new(x: Scalar) -> PolyChallenge {
+        PolyChallenge { x }
+    }
+}
+
 /// A party's proof share, ready for aggregation into the final
 /// [`RangeProof`](::RangeProof).
-#[derive(Serialize, Deserialize, Clone, Debug)]
+///
+/// Deserialization rejects shares whose `l_vec` and `r_vec` have
+/// different lengths, so that a malformed share is caught when it is
+/// received rather than causing a panic during aggregation.
+///
+/// The share's secrets are overwritten with null bytes when it goes
+/// out of scope.  With the `zeroize` feature, they can also be wiped
+/// on demand with [`Zeroize::zeroize`](::zeroize::Zeroize::zeroize).
+/// Its `Debug` output redacts `l_vec` and `r_vec`, which are derived
+/// from the bits of the party's value, so that they aren't leaked to
+/// logs.
+#[derive(Serialize, Clone, PartialEq, Eq)]
 pub struct ProofShare {
     pub(super) t_x: Scalar,
     pub(super) t_x_blinding: Scalar,
@@ -48,27 +87,141 @@ pub struct ProofShare {
     pub(super) r_vec: Vec<Scalar>,
 }
 
+impl fmt::Debug for ProofShare {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProofShare")
+            .field("t_x", &self.t_x)
+            .field("t_x_blinding", &self.t_x_blinding)
+            .field("e_blinding", &self.e_blinding)
+            .field("l_vec", &Redacted(self.l_vec.len()))
+            .field("r_vec", &Redacted(self.r_vec.len()))
+            .finish()
+    }
+}
+
+/// Formats a secret vector of the given length as `[REDACTED; n]`.
+struct Redacted(usize);
+
+impl fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[REDACTED; {}]", self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for ProofShare {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Mirrors the fields of `ProofShare`, so that the derived
+        // deserializer can be reused before validating the lengths.
+        #[derive(Deserialize)]
+        #[serde(rename = "ProofShare")]
+        struct ProofShareData {
+            t_x: Scalar,
+            t_x_blinding: Scalar,
+            e_blinding: Scalar,
+            l_vec: Vec<Scalar>,
+            r_vec: Vec<Scalar>,
+        }
+
+        let data = ProofShareData::deserialize(deserializer)?;
+        if data.l_vec.len() != data.r_vec.len() {
+            return Err(D::Error::custom(
+                "ProofShare l_vec and r_vec lengths do not match",
+            ));
+        }
+
+        Ok(ProofShare {
+            t_x: data.t_x,
+            t_x_blinding: data.t_x_blinding,
+            e_blinding: data.e_blinding,
+            l_vec: data.l_vec,
+            r_vec: data.r_vec,
+        })
+    }
+}
+
 impl ProofShare {
-    /// Audit an individual proof share to determine whether it is
-    /// malformed.
-    pub(super) fn audit_share(
+    /// Overwrites the share's secrets with null bytes.
+    fn clear_secrets(&mut self) {
+        self.t_x.clear();
+        self.t_x_blinding.clear();
+        self.e_blinding.clear();
+
+        // Important: calling .clear() on a Vec only clears its header,
+        // so the contents must be cleared item-by-item.
+        // See the note in `PartyAwaitingBitChallenge`'s Drop impl.
+        for e in self.l_vec.iter_mut() {
+            e.clear();
+        }
+        for e in self.r_vec.iter_mut() {
+            e.clear();
+        }
+    }
+
+    /// Verify that the proof share of the `j`-th party is consistent
+    /// with its commitments and the dealer's challenges, before it is
+    /// aggregated into a [`RangeProof`](::RangeProof).
+    ///
+    /// The `offset` is the sum of the bitsizes of the parties before
+    /// the `j`-th, or \\(j \cdot n\\) when all parties use bitsize
+    /// \\(n\\).
+    ///
+    /// The dealer uses this to find the malformed shares when the
+    /// aggregated proof fails to verify, and callers orchestrating
+    /// their own MPC can use it to check each share as it arrives.
+    pub fn verify_share(
         &self,
         bp_gens: &BulletproofGens,
         pc_gens: &PedersenGens,
         j: usize,
+        offset: usize,
         bit_commitment: &BitCommitment,
         bit_challenge: &BitChallenge,
         poly_commitment: &PolyCommitment,
         poly_challenge: &PolyChallenge,
-    ) -> Result<(), ()> {
-        use std::iter;
+    ) -> Result<(), ProofError> {
+        self.verification_equations(
+            bp_gens,
+            pc_gens,
+            j,
+            offset,
+            bit_commitment,
+            bit_challenge,
+            poly_commitment,
+            poly_challenge,
+        )?
+        .verify()
+    }
 
-        use curve25519_dalek::traits::{IsIdentity, VartimeMultiscalarMul};
+    /// Computes the equations checked by [`ProofShare::verify_share`],
+    /// so that the equations of several shares can be combined into a
+    /// single multiscalar multiplication.
+    ///
+    /// Returns an error if the share fails the checks which do not
+    /// need a multiscalar multiplication.
+    pub(super) fn verification_equations(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        j: usize,
+        offset: usize,
+        bit_commitment: &BitCommitment,
+        bit_challenge: &BitChallenge,
+        poly_commitment: &PolyCommitment,
+        poly_challenge: &PolyChallenge,
+    ) -> Result<ShareEquations, ProofError> {
+        use core::iter;
 
         use inner_product_proof::inner_product;
         use util;
 
         let n = self.l_vec.len();
+        if j >= bp_gens.party_capacity || n > bp_gens.gens_capacity || n != self.r_vec.len() {
+            return Err(ProofError::VerificationError);
+        }
+
         let (y, z) = (&bit_challenge.y, &bit_challenge.z);
         let x = &poly_challenge.x;
 
@@ -76,12 +229,12 @@ impl ProofShare {
         let zz = z * z;
         let minus_z = -z;
         let z_j = util::scalar_exp_vartime(z, j as u64); // z^j
-        let y_jn = util::scalar_exp_vartime(y, (j * n) as u64); // y^(j*n)
+        let y_jn = util::scalar_exp_vartime(y, offset as u64); // y^(j*n) for equal bitsizes
         let y_jn_inv = y_jn.invert(); // y^(-j*n)
         let y_inv = y.invert(); // y^(-1)
 
         if self.t_x != inner_product(&self.l_vec, &self.r_vec) {
-            return Err(());
+            return Err(ProofError::VerificationError);
         }
 
         let g = self.l_vec.iter().map(|l_i| minus_z - l_i);
@@ -94,44 +247,204 @@ impl ProofShare {
                 z + exp_y_inv * y_jn_inv * (-r_i) + exp_y_inv * y_jn_inv * (zz * z_j * exp_2)
             });
 
-        let P_check = RistrettoPoint::vartime_multiscalar_mul(
-            iter::once(Scalar::one())
-                .chain(iter::once(*x))
-                .chain(iter::once(-self.e_blinding))
-                .chain(g)
-                .chain(h),
-            iter::once(&bit_commitment.A_j)
-                .chain(iter::once(&bit_commitment.S_j))
-                .chain(iter::once(&pc_gens.B_blinding))
-                .chain(bp_gens.share(j).G(n))
-                .chain(bp_gens.share(j).H(n)),
-        );
-        if !P_check.is_identity() {
-            return Err(());
-        }
+        let P_scalars = iter::once(Scalar::one())
+            .chain(iter::once(*x))
+            .chain(iter::once(-self.e_blinding))
+            .chain(g)
+            .chain(h)
+            .collect();
+        let P_points = iter::once(bit_commitment.A_j)
+            .chain(iter::once(bit_commitment.S_j))
+            .chain(iter::once(pc_gens.B_blinding))
+            .chain(bp_gens.share(j).G(n).cloned())
+            .chain(bp_gens.share(j).H(n).cloned())
+            .collect();
 
-        let V_j = bit_commitment.V_j.decompress().ok_or(())?;
+        let V_j = bit_commitment
+            .V_j
+            .decompress()
+            .ok_or(ProofError::VerificationError)?;
 
         let sum_of_powers_y = util::sum_of_powers(&y, n);
         let sum_of_powers_2 = util::sum_of_powers(&Scalar::from(2u64), n);
         let delta = (z - zz) * sum_of_powers_y * y_jn - z * zz * sum_of_powers_2 * z_j;
-        let t_check = RistrettoPoint::vartime_multiscalar_mul(
-            iter::once(zz * z_j)
-                .chain(iter::once(*x))
-                .chain(iter::once(x * x))
-                .chain(iter::once(delta - self.t_x))
-                .chain(iter::once(-self.t_x_blinding)),
-            iter::once(&V_j)
-                .chain(iter::once(&poly_commitment.T_1_j))
-                .chain(iter::once(&poly_commitment.T_2_j))
-                .chain(iter::once(&pc_gens.B))
-                .chain(iter::once(&pc_gens.B_blinding)),
-        );
+        let t_scalars = vec![zz * z_j, *x, x * x, delta - self.t_x, -self.t_x_blinding];
+        let t_points = vec![
+            V_j,
+            poly_commitment.T_1_j,
+            poly_commitment.T_2_j,
+            pc_gens.B,
+            pc_gens.B_blinding,
+        ];
+
+        Ok(ShareEquations {
+            P_scalars,
+            P_points,
+            t_scalars,
+            t_points,
+        })
+    }
+}
+
+/// The two equations which hold for a valid [`ProofShare`], each
+/// given as a multiscalar multiplication which is the identity.
+///
+/// The first checks the share's \\(\mathbf{l}(x)\\) and
+/// \\(\mathbf{r}(x)\\) against its bit commitment, and the second
+/// checks \\(t(x)\\) against its value and polynomial commitments.
+pub(super) struct ShareEquations {
+    pub(super) P_scalars: Vec<Scalar>,
+    pub(super) P_points: Vec<RistrettoPoint>,
+    pub(super) t_scalars: Vec<Scalar>,
+    pub(super) t_points: Vec<RistrettoPoint>,
+}
 
+impl ShareEquations {
+    /// Checks both equations separately.
+    pub(super) fn verify(&self) -> Result<(), ProofError> {
+        use curve25519_dalek::traits::{IsIdentity, VartimeMultiscalarMul};
+
+        let P_check = RistrettoPoint::vartime_multiscalar_mul(&self.P_scalars, &self.P_points);
+        if !P_check.is_identity() {
+            return Err(ProofError::VerificationError);
+        }
+
+        let t_check = RistrettoPoint::vartime_multiscalar_mul(&self.t_scalars, &self.t_points);
         if t_check.is_identity() {
             Ok(())
         } else {
-            Err(())
+            Err(ProofError::VerificationError)
         }
     }
 }
+
+#[cfg(feature = "test-internals")]
+impl ProofShare {
+    /// The evaluation \\(t(x)\\) of the party's polynomial.
+    pub fn t_x(&self) -> Scalar {
+        self.t_x
+    }
+
+    /// The evaluation \\(\mathbf{l}(x)\\) of the party's vector
+    /// polynomial.
+    pub fn l_vec(&self) -> &[Scalar] {
+        &self.l_vec
+    }
+
+    /// The evaluation \\(\mathbf{r}(x)\\) of the party's vector
+    /// polynomial.
+    pub fn r_vec(&self) -> &[Scalar] {
+        &self.r_vec
+    }
+}
+
+/// Overwrite secrets with null bytes when they go out of scope.
+impl Drop for ProofShare {
+    fn drop(&mut self) {
+        self.clear_secrets();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for ProofShare {
+    fn zeroize(&mut self) {
+        self.clear_secrets();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bincode;
+
+    #[test]
+    fn proof_share_roundtrip() {
+        let share = ProofShare {
+            t_x: Scalar::from(1u64),
+            t_x_blinding: Scalar::from(2u64),
+            e_blinding: Scalar::from(3u64),
+            l_vec: vec![Scalar::from(4u64), Scalar::from(5u64)],
+            r_vec: vec![Scalar::from(6u64), Scalar::from(7u64)],
+        };
+
+        let bytes = bincode::serialize(&share).unwrap();
+        let share2: ProofShare = bincode::deserialize(&bytes).unwrap();
+
+        assert_eq!(share.t_x, share2.t_x);
+        assert_eq!(share.t_x_blinding, share2.t_x_blinding);
+        assert_eq!(share.e_blinding, share2.e_blinding);
+        assert_eq!(share.l_vec, share2.l_vec);
+        assert_eq!(share.r_vec, share2.r_vec);
+        assert_eq!(share, share2);
+    }
+
+    #[test]
+    fn proof_share_rejects_mismatched_lengths() {
+        let share = ProofShare {
+            t_x: Scalar::zero(),
+            t_x_blinding: Scalar::zero(),
+            e_blinding: Scalar::zero(),
+            l_vec: vec![Scalar::zero(); 8],
+            r_vec: vec![Scalar::zero(); 4],
+        };
+
+        let bytes = bincode::serialize(&share).unwrap();
+        assert!(bincode::deserialize::<ProofShare>(&bytes).is_err());
+    }
+
+    #[test]
+    fn proof_share_clear_secrets() {
+        let mut share = ProofShare {
+            t_x: Scalar::from(1u64),
+            t_x_blinding: Scalar::from(2u64),
+            e_blinding: Scalar::from(3u64),
+            l_vec: vec![Scalar::from(4u64), Scalar::from(5u64)],
+            r_vec: vec![Scalar::from(6u64), Scalar::from(7u64)],
+        };
+
+        share.clear_secrets();
+
+        assert_eq!(share.t_x, Scalar::zero());
+        assert_eq!(share.t_x_blinding, Scalar::zero());
+        assert_eq!(share.e_blinding, Scalar::zero());
+        // The vectors keep their length, but their contents are wiped.
+        assert_eq!(share.l_vec, vec![Scalar::zero(); 2]);
+        assert_eq!(share.r_vec, vec![Scalar::zero(); 2]);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn proof_share_zeroize() {
+        let mut share = ProofShare {
+            t_x: Scalar::from(1u64),
+            t_x_blinding: Scalar::from(2u64),
+            e_blinding: Scalar::from(3u64),
+            l_vec: vec![Scalar::from(4u64)],
+            r_vec: vec![Scalar::from(5u64)],
+        };
+
+        share.zeroize();
+
+        assert_eq!(share.t_x_blinding, Scalar::zero());
+        assert_eq!(share.l_vec, vec![Scalar::zero()]);
+        assert_eq!(share.r_vec, vec![Scalar::zero()]);
+    }
+
+    #[test]
+    fn proof_share_debug_redacts_vectors() {
+        let share = ProofShare {
+            t_x: Scalar::from(1u64),
+            t_x_blinding: Scalar::from(2u64),
+            e_blinding: Scalar::from(3u64),
+            l_vec: vec![Scalar::from(4u64), Scalar::from(5u64)],
+            r_vec: vec![Scalar::from(6u64), Scalar::from(7u64)],
+        };
+
+        let debug = format!("{:?}", share);
+        assert!(debug.contains("l_vec: [REDACTED; 2]"));
+        assert!(debug.contains("r_vec: [REDACTED; 2]"));
+        assert!(!debug.contains(&format!("{:?}", Scalar::from(4u64))));
+        assert!(debug.contains(&format!("{:?}", Scalar::from(1u64))));
+    }
+}
@@ -1,21 +1,39 @@
 #![allow(non_snake_case)]
 #![doc(include = "../../docs/range-proof-protocol.md")]
 
-use rand;
-
-use std::iter;
+#[cfg(feature = "hex")]
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::iter;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaChaRng;
+#[cfg(feature = "std")]
+use std::io;
 
 use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
 use curve25519_dalek::scalar::Scalar;
-use curve25519_dalek::traits::{IsIdentity, VartimeMultiscalarMul};
+use curve25519_dalek::traits::{
+    Identity, IsIdentity, MultiscalarMul, VartimeMultiscalarMul, VartimePrecomputedMultiscalarMul,
+};
+use digest::Digest;
 use merlin::Transcript;
+use sha3::Sha3_512;
+use subtle::ConstantTimeEq;
+
+#[cfg(feature = "std")]
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
 use errors::ProofError;
-use generators::{BulletproofGens, PedersenGens};
+#[cfg(feature = "diagnostics")]
+use errors::VerifyFailureReason;
+use generators::{BulletproofGens, PedersenGens, PrecomputedGens};
 use inner_product_proof::InnerProductProof;
-use transcript::TranscriptProtocol;
+use transcript::{labels, TranscriptProtocol};
 use util;
 
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
 use serde::de::Visitor;
 use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
 
@@ -25,6 +43,12 @@ pub mod dealer;
 pub mod messages;
 pub mod party;
 
+mod fixed;
+mod verifiable;
+
+pub use self::fixed::RangeProof64;
+pub use self::verifiable::VerifiableProof;
+
 /// The `RangeProof` struct represents a proof that one or more values
 /// are in a range.
 ///
@@ -36,11 +60,24 @@ pub mod party;
 /// the values, are not included in the proof, and must be known to
 /// the verifier.
 ///
-/// This implementation requires that both the bitsize `n` and the
-/// aggregation size `m` be powers of two, so that `n = 8, 16, 32, 64`
-/// and `m = 1, 2, 4, 8, 16, ...`.  Note that the aggregation size is
-/// not given as an explicit parameter, but is determined by the
-/// number of values or commitments passed to the prover or verifier.
+/// This implementation requires that the bitsize `n` be a power of
+/// two, so that `n = 8, 16, 32, 64, 128`.  The aggregation size `m`
+/// may be any positive number: if it is not a power of two, the prover and
+/// verifier pad the aggregation up to the next power of two with
+/// commitments to zero (with zero blinding factors, so that they are
+/// the identity point), and the [`BulletproofGens`] must have a
+/// `party_capacity` large enough for the padded size.  Note that the
+/// aggregation size is not given as an explicit parameter, but is
+/// determined by the number of values or commitments passed to the
+/// prover or verifier.
+///
+/// Proofs are bound to the Merlin [`Transcript`] they are created
+/// with.  Since `Transcript` is `Clone`, several proofs which share a
+/// common prefix (e.g., a header committed to by the application) can
+/// be created from clones of the transcript taken after the prefix,
+/// optionally followed by a per-proof domain separator, without
+/// recomputing the prefix.  The verifier must clone its transcript at
+/// the same point.
 ///
 /// # Note
 ///
@@ -48,7 +85,7 @@ pub mod party;
 /// protocol locally.  That API is exposed in the [`aggregation`](::range_proof_mpc)
 /// module and can be used to perform online aggregation between
 /// parties without revealing secret values to each other.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct RangeProof {
     /// Commitment to the bits of the value
     A: CompressedRistretto,
@@ -68,10 +105,64 @@ pub struct RangeProof {
     ipp_proof: InnerProductProof,
 }
 
+/// The Fiat-Shamir challenges of a [`RangeProof`], as returned by
+/// [`RangeProof::challenges`].
+#[derive(Copy, Clone, Debug)]
+pub struct RangeProofChallenges {
+    /// Challenge for the bit commitments' vector polynomials
+    pub y: Scalar,
+    /// Challenge for the bit commitments' vector polynomials
+    pub z: Scalar,
+    /// Evaluation point for the polynomial \\(t(x)\\)
+    pub x: Scalar,
+    /// Challenge combining \\(t(x)\\) into the inner-product argument
+    pub w: Scalar,
+}
+
+/// A value which is known to lie in the range \\([0, 2^n)\\) of the
+/// rangeproof that will be created for it.
+///
+/// A proof for a value which does not fit in its range is created
+/// without error, but fails to verify.  Constructing a
+/// `CommittedValue` catches that mistake before proving.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CommittedValue {
+    value: u64,
+    n: usize,
+}
+
+impl CommittedValue {
+    /// Checks that `value` lies in the range \\([0, 2^n)\\).
+    ///
+    /// Returns [`ProofError::InvalidBitsize`] if `n` is not one of
+    /// \\(8, 16, 32, 64, 128\\), and [`ProofError::ValueOutOfBounds`] if
+    /// the value does not fit in `n` bits.
+    pub fn new(value: u64, n: usize) -> Result<CommittedValue, ProofError> {
+        if !util::is_valid_bitsize(n) {
+            return Err(ProofError::InvalidBitsize);
+        }
+        if n < 64 && value >> n != 0 {
+            return Err(ProofError::ValueOutOfBounds);
+        }
+        Ok(CommittedValue { value, n })
+    }
+
+    /// The value itself.
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// The bitsize \\(n\\) of the value's range.
+    pub fn bitsize(&self) -> usize {
+        self.n
+    }
+}
+
 impl RangeProof {
     /// Create a rangeproof for a given pair of value `v` and
     /// blinding scalar `v_blinding`.
-    /// This is a convenience wrapper around [`RangeProof::prove_multiple`].
+    /// This is a convenience wrapper around [`RangeProof::prove_single_with_rng`],
+    /// passing in a threadsafe RNG.
     ///
     /// # Example
     /// ```
@@ -125,6 +216,7 @@ impl RangeProof {
     /// );
     /// # }
     /// ```
+    #[cfg(feature = "std")]
     pub fn prove_single(
         bp_gens: &BulletproofGens,
         pc_gens: &PedersenGens,
@@ -133,12 +225,45 @@ impl RangeProof {
         v_blinding: &Scalar,
         n: usize,
     ) -> Result<(RangeProof, CompressedRistretto), ProofError> {
-        let (p, Vs) =
-            RangeProof::prove_multiple(bp_gens, pc_gens, transcript, &[v], &[*v_blinding], n)?;
+        RangeProof::prove_single_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            v,
+            v_blinding,
+            n,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Create a rangeproof for a given pair of value `v` and
+    /// blinding scalar `v_blinding`, using `rng` to generate the
+    /// proof's blinding factors.
+    /// This is a convenience wrapper around [`RangeProof::prove_multiple_with_rng`].
+    pub fn prove_single_with_rng<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        v: u64,
+        v_blinding: &Scalar,
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+        let (p, Vs) = RangeProof::prove_multiple_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            &[v],
+            &[*v_blinding],
+            n,
+            rng,
+        )?;
         Ok((p, Vs[0]))
     }
 
     /// Create a rangeproof for a set of values.
+    /// This is a convenience wrapper around [`RangeProof::prove_multiple_with_rng`],
+    /// passing in a threadsafe RNG.
     ///
     /// # Example
     /// ```
@@ -192,6 +317,7 @@ impl RangeProof {
     /// );
     /// # }
     /// ```
+    #[cfg(feature = "std")]
     pub fn prove_multiple(
         bp_gens: &BulletproofGens,
         pc_gens: &PedersenGens,
@@ -199,6 +325,171 @@ impl RangeProof {
         values: &[u64],
         blindings: &[Scalar],
         n: usize,
+    ) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+        RangeProof::prove_multiple_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            values,
+            blindings,
+            n,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Create a rangeproof for a set of values, using `rng` to
+    /// generate the proof's blinding factors.
+    ///
+    /// Each of the (locally simulated) parties gets its own
+    /// ChaCha20 RNG (`rand_chacha::ChaChaRng`), seeded from `rng` in
+    /// party order, so a seeded `rng` gives the same proof whether
+    /// or not the `rayon` feature runs the parties in parallel.
+    pub fn prove_multiple_with_rng<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        values: &[u64],
+        blindings: &[Scalar],
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+        RangeProof::prove_multiple_variable_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            values,
+            blindings,
+            &vec![n; values.len()],
+            rng,
+        )
+    }
+
+    /// Create a rangeproof for a set of values, where the `j`-th
+    /// value is proved to lie in a `bitsizes[j]`-bit range.
+    /// This is a convenience wrapper around
+    /// [`RangeProof::prove_multiple_variable_with_rng`], passing in a
+    /// threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn prove_multiple_variable(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        values: &[u64],
+        blindings: &[Scalar],
+        bitsizes: &[usize],
+    ) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+        RangeProof::prove_multiple_variable_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            values,
+            blindings,
+            bitsizes,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Create a rangeproof for a set of values, where the `j`-th
+    /// value is proved to lie in a `bitsizes[j]`-bit range, using
+    /// `rng` to generate the proof's blinding factors.
+    ///
+    /// Each bitsize must be one of `8, 16, 32, 64, 128`.  The parties'
+    /// generators are laid out one after another, so the proof
+    /// uses \\(\sum_j n_j\\) generators rather than \\(n \cdot m\\).
+    /// If this sum is not a power of two, the aggregation is padded
+    /// with commitments to zero of the smallest bitsize until it is.
+    /// When all of the bitsizes are equal, this produces the same
+    /// proof as [`RangeProof::prove_multiple_with_rng`].
+    ///
+    /// Each of the (locally simulated) parties gets its own
+    /// ChaCha20 RNG (`rand_chacha::ChaChaRng`), seeded from `rng` in
+    /// party order, so a seeded `rng` gives the same proof whether
+    /// or not the `rayon` feature runs the parties in parallel.
+    pub fn prove_multiple_variable_with_rng<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        values: &[u64],
+        blindings: &[Scalar],
+        bitsizes: &[usize],
+        rng: &mut T,
+    ) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+        let values: Vec<u128> = values.iter().map(|&v| v.into()).collect();
+        RangeProof::prove_variable_u128_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            &values,
+            blindings,
+            bitsizes,
+            rng,
+        )
+    }
+
+    /// Create a rangeproof for a set of values which may not fit in a
+    /// `u64`, such as sums of many balances, in an `n`-bit range.
+    /// This is a convenience wrapper around
+    /// [`RangeProof::prove_multiple_u128_with_rng`], passing in a
+    /// threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn prove_multiple_u128(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        values: &[u128],
+        blindings: &[Scalar],
+        n: usize,
+    ) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+        RangeProof::prove_multiple_u128_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            values,
+            blindings,
+            n,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Create a rangeproof for a set of values which may not fit in a
+    /// `u64`, in an `n`-bit range, using `rng` to generate the proof's
+    /// blinding factors.
+    ///
+    /// Only the \\(n = 128\\) range can hold values of \\(2^{64}\\) or
+    /// more, and it needs twice as many generators per party as a
+    /// 64-bit range.  The proof is verified with
+    /// [`RangeProof::verify_multiple`], like any other.
+    pub fn prove_multiple_u128_with_rng<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        values: &[u128],
+        blindings: &[Scalar],
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
+        RangeProof::prove_variable_u128_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            values,
+            blindings,
+            &vec![n; values.len()],
+            rng,
+        )
+    }
+
+    /// Creates the rangeproof for
+    /// [`RangeProof::prove_multiple_variable_with_rng`], with the
+    /// values widened to `u128`.
+    fn prove_variable_u128_with_rng<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        values: &[u128],
+        blindings: &[Scalar],
+        bitsizes: &[usize],
+        rng: &mut T,
     ) -> Result<(RangeProof, Vec<CompressedRistretto>), ProofError> {
         use self::dealer::*;
         use self::party::*;
@@ -206,21 +497,55 @@ impl RangeProof {
         if values.len() != blindings.len() {
             return Err(ProofError::WrongNumBlindingFactors);
         }
+        if values.len() != bitsizes.len() {
+            return Err(ProofError::WrongNumBitsizes);
+        }
+
+        // The dealer pads the aggregation to a power of two with
+        // commitments to zero.  Using zero blinding factors makes the
+        // padding commitments the identity, so the verifier can
+        // reconstruct them without being told about them.
+        let m = values.len();
+        let dealer = Dealer::new_variable(bp_gens, pc_gens, transcript, bitsizes)?;
 
-        let dealer = Dealer::new(bp_gens, pc_gens, transcript, n, values.len())?;
+        // Each party's generators start after those of the parties
+        // before it.
+        let offsets: Vec<usize> = bitsizes
+            .iter()
+            .scan(0, |offset, &n| {
+                let party_offset = *offset;
+                *offset += n;
+                Some(party_offset)
+            })
+            .collect();
 
         let parties: Vec<_> = values
             .iter()
             .zip(blindings.iter())
-            .map(|(&v, &v_blinding)| Party::new(bp_gens, pc_gens, v, v_blinding, n))
+            .zip(bitsizes.iter())
+            .map(|((&v, &v_blinding), &n)| Party::new_u128(bp_gens, pc_gens, v, v_blinding, n))
             // Collect the iterator of Results into a Result<Vec>, then unwrap it
             .collect::<Result<Vec<_>, _>>()?;
 
+        // Each party's computations are independent of the others,
+        // so they can be run in parallel.  To allow this, each party
+        // gets its own ChaCha20 RNG, seeded in order with 32 bytes
+        // from `rng`.  `StdRng` isn't used, since its algorithm may
+        // change between `rand` releases.
+        let mut party_rngs: Vec<ChaChaRng> = (0..m)
+            .map(|_| ChaChaRng::from_rng(&mut *rng))
+            .collect::<Result<Vec<_>, _>>()
+            .expect("Seeding from a CryptoRng should never fail");
+
+        #[cfg(feature = "rayon")]
+        let parties = parties.into_par_iter().zip(party_rngs.par_iter_mut());
+        #[cfg(not(feature = "rayon"))]
+        let parties = parties.into_iter().zip(party_rngs.iter_mut());
+
         let (parties, bit_commitments): (Vec<_>, Vec<_>) = parties
-            .into_iter()
             .enumerate()
-            .map(|(j, p)| {
-                p.assign_position(j)
+            .map(|(j, (p, party_rng))| {
+                p.assign_variable_position_with_rng(j, offsets[j], party_rng)
                     .expect("We already checked the parameters, so this should never happen")
             })
             .unzip();
@@ -229,391 +554,5052 @@ impl RangeProof {
 
         let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments)?;
 
+        #[cfg(feature = "rayon")]
+        let parties = parties.into_par_iter().zip(party_rngs.par_iter_mut());
+        #[cfg(not(feature = "rayon"))]
+        let parties = parties.into_iter().zip(party_rngs.iter_mut());
+
         let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
-            .into_iter()
-            .map(|p| p.apply_challenge(&bit_challenge))
+            .map(|(p, party_rng)| p.apply_challenge_with_rng(&bit_challenge, party_rng))
             .unzip();
 
         let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments)?;
 
+        #[cfg(feature = "rayon")]
+        let parties = parties.into_par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let parties = parties.into_iter();
+
         let proof_shares: Vec<_> = parties
-            .into_iter()
             .map(|p| p.apply_challenge(&poly_challenge))
             // Collect the iterator of Results into a Result<Vec>, then unwrap it
             .collect::<Result<Vec<_>, _>>()?;
 
-        let proof = dealer.receive_trusted_shares(&proof_shares)?;
+        let proof = dealer.receive_trusted_shares_owned(proof_shares)?;
 
         Ok((proof, value_commitments))
     }
 
-    /// Verifies a rangeproof for a given value commitment \\(V\\).
-    ///
-    /// This is a convenience wrapper around `verify_multiple` for the `m=1` case.
-    pub fn verify_single(
-        &self,
+    /// Create a rangeproof that the value `v` lies in the range
+    /// \\([a, b)\\).
+    /// This is a convenience wrapper around
+    /// [`RangeProof::prove_bounded_with_rng`], passing in a threadsafe
+    /// RNG.
+    #[cfg(feature = "std")]
+    pub fn prove_bounded(
         bp_gens: &BulletproofGens,
         pc_gens: &PedersenGens,
         transcript: &mut Transcript,
-        V: &CompressedRistretto,
+        v: u64,
+        v_blinding: &Scalar,
+        a: u64,
+        b: u64,
         n: usize,
-    ) -> Result<(), ProofError> {
-        self.verify_multiple(bp_gens, pc_gens, transcript, &[*V], n)
+    ) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+        RangeProof::prove_bounded_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            v,
+            v_blinding,
+            a,
+            b,
+            n,
+            &mut rand::thread_rng(),
+        )
     }
 
-    /// Verifies an aggregated rangeproof for the given value commitments.
-    pub fn verify_multiple(
-        &self,
+    /// Create a rangeproof that the value `v` lies in the range
+    /// \\([a, b)\\), using `rng` to generate the proof's blinding
+    /// factors.
+    ///
+    /// This is an aggregated proof for \\(m = 2\\) that both
+    /// \\(v - a\\) and \\(b - 1 - v\\) lie in \\([0, 2^n)\\),
+    /// so the width \\(b - a\\) of the range must be at most
+    /// \\(2^n\\).  Their commitments are shifts of the commitment
+    /// \\(V\\) to `v`, which is returned with the proof.  Like the
+    /// bitsize, the bounds are not part of the proof, and must be
+    /// given to [`RangeProof::verify_bounded_with_rng`].
+    ///
+    /// Returns [`ProofError::ValueOutOfBounds`] unless
+    /// \\(a \leq v < b\\) and \\(b - a \leq 2^n\\).
+    pub fn prove_bounded_with_rng<T: RngCore + CryptoRng>(
         bp_gens: &BulletproofGens,
         pc_gens: &PedersenGens,
         transcript: &mut Transcript,
-        value_commitments: &[CompressedRistretto],
+        v: u64,
+        v_blinding: &Scalar,
+        a: u64,
+        b: u64,
         n: usize,
-    ) -> Result<(), ProofError> {
-        let m = value_commitments.len();
-
-        // First, replay the "interactive" protocol using the proof
-        // data to recompute all challenges.
-        if !(n == 8 || n == 16 || n == 32 || n == 64) {
-            return Err(ProofError::InvalidBitsize);
-        }
-        if bp_gens.gens_capacity < n {
-            return Err(ProofError::InvalidGeneratorsLength);
+        rng: &mut T,
+    ) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+        if !(a <= v && v < b) {
+            return Err(ProofError::ValueOutOfBounds);
         }
-        if bp_gens.party_capacity < m {
-            return Err(ProofError::InvalidGeneratorsLength);
+        if n < 64 && b - a > 1 << n {
+            return Err(ProofError::ValueOutOfBounds);
         }
 
-        transcript.rangeproof_domain_sep(n as u64, m as u64);
+        // The commitment to b - 1 - v has the negated blinding factor,
+        // so that the verifier can compute it as (b - 1) * B - V.
+        let (proof, _) = RangeProof::prove_multiple_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            &[v - a, b - 1 - v],
+            &[*v_blinding, -v_blinding],
+            n,
+            rng,
+        )?;
+        let V = pc_gens.commit_value(v, v_blinding);
 
-        for V in value_commitments.iter() {
-            // Allow the commitments to be zero (0 value, 0 blinding)
-            // See https://github.com/dalek-cryptography/bulletproofs/pull/248#discussion_r255167177
-            transcript.append_point(b"V", V);
-        }
+        Ok((proof, V))
+    }
 
-        transcript.validate_and_append_point(b"A", &self.A)?;
-        transcript.validate_and_append_point(b"S", &self.S)?;
+    /// Create a rangeproof that the value `v` is either zero or one.
+    /// This is a convenience wrapper around
+    /// [`RangeProof::prove_bit_with_rng`], passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn prove_bit(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        v: u64,
+        v_blinding: &Scalar,
+    ) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+        RangeProof::prove_bit_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            v,
+            v_blinding,
+            &mut rand::thread_rng(),
+        )
+    }
 
-        let y = transcript.challenge_scalar(b"y");
-        let z = transcript.challenge_scalar(b"z");
-        let zz = z * z;
-        let minus_z = -z;
+    /// Create a rangeproof that the value `v` is either zero or one,
+    /// using `rng` to generate the proof's blinding factors.
+    ///
+    /// This is a bounded proof for the range \\([0, 2)\\) with the
+    /// smallest bitsize \\(n = 8\\), so the generators must have
+    /// capacity for \\(n = 8\\) and \\(m = 2\\).  It is checked
+    /// with [`RangeProof::verify_bit_with_rng`].
+    ///
+    /// Returns [`ProofError::ValueOutOfBounds`] unless `v` is zero or
+    /// one.
+    pub fn prove_bit_with_rng<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        v: u64,
+        v_blinding: &Scalar,
+        rng: &mut T,
+    ) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+        RangeProof::prove_bounded_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            v,
+            v_blinding,
+            0,
+            2,
+            8,
+            rng,
+        )
+    }
 
-        transcript.validate_and_append_point(b"T_1", &self.T_1)?;
-        transcript.validate_and_append_point(b"T_2", &self.T_2)?;
+    /// Create a rangeproof that the signed value `v` lies in
+    /// \\([-2^{n-1}, 2^{n-1})\\).
+    /// This is a convenience wrapper around
+    /// [`RangeProof::prove_signed_with_rng`], passing in a threadsafe
+    /// RNG.
+    #[cfg(feature = "std")]
+    pub fn prove_signed(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        v: i64,
+        v_blinding: &Scalar,
+        n: usize,
+    ) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+        RangeProof::prove_signed_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            v,
+            v_blinding,
+            n,
+            &mut rand::thread_rng(),
+        )
+    }
 
-        let x = transcript.challenge_scalar(b"x");
+    /// Create a rangeproof that the signed value `v` lies in
+    /// \\([-2^{n-1}, 2^{n-1})\\), using `rng` to generate the
+    /// proof's blinding factors.
+    ///
+    /// This is a proof that \\(v + 2^{n-1}\\) lies in
+    /// \\([0, 2^n)\\).  The returned commitment \\(V\\) is to
+    /// `v` itself, where a negative value is committed to as its
+    /// negation in the scalar field; the verifier shifts it by
+    /// \\(2^{n-1} B\\) in [`RangeProof::verify_signed_with_rng`].
+    ///
+    /// Returns [`ProofError::InvalidBitsize`] unless \\(n\\) is one
+    /// of \\(8, 16, 32, 64\\), and [`ProofError::ValueOutOfBounds`]
+    /// if `v` does not lie in the range.
+    pub fn prove_signed_with_rng<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        v: i64,
+        v_blinding: &Scalar,
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(RangeProof, CompressedRistretto), ProofError> {
+        let offset = signed_offset(n)?;
+        let shifted = (v as u64).wrapping_add(offset);
+        if n < 64 && shifted >> n != 0 {
+            return Err(ProofError::ValueOutOfBounds);
+        }
 
-        transcript.append_scalar(b"t_x", &self.t_x);
-        transcript.append_scalar(b"t_x_blinding", &self.t_x_blinding);
-        transcript.append_scalar(b"e_blinding", &self.e_blinding);
+        let (proof, _) = RangeProof::prove_single_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            shifted,
+            v_blinding,
+            n,
+            rng,
+        )?;
+        let V = pc_gens
+            .commit(Scalar::from(shifted) - Scalar::from(offset), *v_blinding)
+            .compress();
 
-        let w = transcript.challenge_scalar(b"w");
+        Ok((proof, V))
+    }
 
-        let mut rng = transcript.build_rng().finalize(&mut rand::thread_rng());
+    /// Create a rangeproof that the value `a` is at least the value
+    /// `b`, given the openings of their commitments.
+    ///
+    /// This is a convenience wrapper around
+    /// [`RangeProof::prove_difference_with_rng`], passing in a
+    /// threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn prove_difference(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        a: u64,
+        a_blinding: &Scalar,
+        b: u64,
+        b_blinding: &Scalar,
+        n: usize,
+    ) -> Result<RangeProof, ProofError> {
+        RangeProof::prove_difference_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            a,
+            a_blinding,
+            b,
+            b_blinding,
+            n,
+            &mut rand::thread_rng(),
+        )
+    }
 
-        // Challenge value for batching statements to be verified
-        let c = Scalar::random(&mut rng);
+    /// Create a rangeproof that the value `a` is at least the value
+    /// `b`, using `rng` to generate the proof's blinding factors.
+    ///
+    /// This is a proof that \\(a - b\\) lies in \\([0, 2^n)\\), for
+    /// the commitment \\(V_a - V_b\\) to the difference, which is
+    /// opened by the difference of the blinding factors.  The verifier
+    /// reconstructs it from the commitments \\(V_a\\) and \\(V_b\\)
+    /// with [`RangeProof::verify_difference_with_rng`].
+    ///
+    /// Returns [`ProofError::ValueOutOfBounds`] unless
+    /// \\(0 \leq a - b < 2^n\\).
+    pub fn prove_difference_with_rng<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        a: u64,
+        a_blinding: &Scalar,
+        b: u64,
+        b_blinding: &Scalar,
+        n: usize,
+        rng: &mut T,
+    ) -> Result<RangeProof, ProofError> {
+        if a < b {
+            return Err(ProofError::ValueOutOfBounds);
+        }
+        if n < 64 && (a - b) >> n != 0 {
+            return Err(ProofError::ValueOutOfBounds);
+        }
 
-        let (x_sq, x_inv_sq, s) = self.ipp_proof.verification_scalars(n * m, transcript)?;
-        let s_inv = s.iter().rev();
+        let (proof, _) = RangeProof::prove_single_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            a - b,
+            &(a_blinding - b_blinding),
+            n,
+            rng,
+        )?;
 
-        let a = self.ipp_proof.a;
-        let b = self.ipp_proof.b;
+        Ok(proof)
+    }
 
-        // Construct concat_z_and_2, an iterator of the values of
-        // z^0 * \vec(2)^n || z^1 * \vec(2)^n || ... || z^(m-1) * \vec(2)^n
-        let powers_of_2: Vec<Scalar> = util::exp_iter(Scalar::from(2u64)).take(n).collect();
-        let concat_z_and_2: Vec<Scalar> = util::exp_iter(z)
-            .take(m)
-            .flat_map(|exp_z| powers_of_2.iter().map(move |exp_2| exp_2 * exp_z))
-            .collect();
+    /// Create a rangeproof that the sum of the `values` lies in
+    /// \\([0, 2^n)\\), given the openings of their commitments.
+    ///
+    /// This is a convenience wrapper around
+    /// [`RangeProof::prove_sum_with_rng`], passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn prove_sum(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        values: &[u64],
+        blindings: &[Scalar],
+        n: usize,
+    ) -> Result<RangeProof, ProofError> {
+        RangeProof::prove_sum_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            values,
+            blindings,
+            n,
+            &mut rand::thread_rng(),
+        )
+    }
 
-        let g = s.iter().map(|s_i| minus_z - a * s_i);
-        let h = s_inv
-            .zip(util::exp_iter(y.invert()))
-            .zip(concat_z_and_2.iter())
-            .map(|((s_i_inv, exp_y_inv), z_and_2)| z + exp_y_inv * (zz * z_and_2 - b * s_i_inv));
+    /// Create a rangeproof that the sum of the `values` lies in
+    /// \\([0, 2^n)\\), using `rng` to generate the proof's blinding
+    /// factors.
+    ///
+    /// This is a proof for the commitment \\(\sum_j V_j\\) to the
+    /// sum, which is opened by the sum of the blinding factors.  The
+    /// verifier reconstructs it from the commitments \\(V_j\\) with
+    /// [`RangeProof::verify_sum_with_rng`].
+    ///
+    /// Returns [`ProofError::ValueOverflow`] if the sum does not fit
+    /// in the range, as computed by [`RangeProof::checked_value_sum`].
+    pub fn prove_sum_with_rng<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        values: &[u64],
+        blindings: &[Scalar],
+        n: usize,
+        rng: &mut T,
+    ) -> Result<RangeProof, ProofError> {
+        if values.len() != blindings.len() {
+            return Err(ProofError::WrongNumBlindingFactors);
+        }
+        let sum = RangeProof::checked_value_sum(values, n)?;
+        let blinding: Scalar = blindings.iter().sum();
 
-        let value_commitment_scalars = util::exp_iter(z).take(m).map(|z_exp| c * zz * z_exp);
-        let basepoint_scalar = w * (self.t_x - a * b) + c * (delta(n, m, &y, &z) - self.t_x);
+        // The sum of 64-bit values may need the 128-bit range.
+        let (proof, _) = RangeProof::prove_multiple_u128_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            &[sum],
+            &[blinding],
+            n,
+            rng,
+        )?;
 
-        let mega_check = RistrettoPoint::optional_multiscalar_mul(
-            iter::once(Scalar::one())
-                .chain(iter::once(x))
-                .chain(iter::once(c * x))
-                .chain(iter::once(c * x * x))
-                .chain(x_sq.iter().cloned())
-                .chain(x_inv_sq.iter().cloned())
-                .chain(iter::once(-self.e_blinding - c * self.t_x_blinding))
-                .chain(iter::once(basepoint_scalar))
-                .chain(g)
-                .chain(h)
-                .chain(value_commitment_scalars),
-            iter::once(self.A.decompress())
-                .chain(iter::once(self.S.decompress()))
-                .chain(iter::once(self.T_1.decompress()))
-                .chain(iter::once(self.T_2.decompress()))
-                .chain(self.ipp_proof.L_vec.iter().map(|L| L.decompress()))
-                .chain(self.ipp_proof.R_vec.iter().map(|R| R.decompress()))
-                .chain(iter::once(Some(pc_gens.B_blinding)))
-                .chain(iter::once(Some(pc_gens.B)))
-                .chain(bp_gens.G(n, m).map(|&x| Some(x)))
-                .chain(bp_gens.H(n, m).map(|&x| Some(x)))
-                .chain(value_commitments.iter().map(|V| V.decompress())),
-        )
-        .ok_or_else(|| ProofError::VerificationError)?;
+        Ok(proof)
+    }
 
-        if mega_check.is_identity() {
-            Ok(())
-        } else {
-            Err(ProofError::VerificationError)
+    /// Returns the sum of the `values`, or
+    /// [`ProofError::ValueOverflow`] if it is at least \\(2^n\\).
+    ///
+    /// The sum is computed as a `u128`, so that it can exceed
+    /// \\(2^{64}\\) in the 128-bit range.  Summing the values with
+    /// wrapping arithmetic would give a value which doesn't open the
+    /// sum of their commitments, and so a proof which fails to verify.
+    /// Returns [`ProofError::InvalidBitsize`] if `n` is not one of
+    /// \\(8, 16, 32, 64, 128\\).
+    pub fn checked_value_sum(values: &[u64], n: usize) -> Result<u128, ProofError> {
+        if !util::is_valid_bitsize(n) {
+            return Err(ProofError::InvalidBitsize);
+        }
+
+        let sum = values
+            .iter()
+            .try_fold(0u128, |sum, &v| sum.checked_add(v.into()))
+            .ok_or(ProofError::ValueOverflow)?;
+        if n < 128 && sum >> n != 0 {
+            return Err(ProofError::ValueOverflow);
         }
+
+        Ok(sum)
     }
 
-    /// Serializes the proof into a byte array of \\(2 \lg n + 9\\)
-    /// 32-byte elements, where \\(n\\) is the number of secret bits.
+    /// Verifies a rangeproof for a given value commitment \\(V\\).
     ///
-    /// # Layout
+    /// This is a convenience wrapper around `verify_single_with_rng`,
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn verify_single(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        V: &CompressedRistretto,
+        n: usize,
+    ) -> Result<(), ProofError> {
+        self.verify_single_with_rng(bp_gens, pc_gens, transcript, V, n, &mut rand::thread_rng())
+    }
+
+    /// Verifies a rangeproof for a given value commitment \\(V\\).
     ///
-    /// The layout of the range proof encoding is:
+    /// This is a convenience wrapper around `verify_multiple_with_rng` for the `m=1` case.
+    pub fn verify_single_with_rng<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        V: &CompressedRistretto,
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(), ProofError> {
+        self.verify_multiple_with_rng(bp_gens, pc_gens, transcript, &[*V], n, rng)
+    }
+
+    /// Verifies an aggregated rangeproof for the given value commitments.
     ///
-    /// * four compressed Ristretto points \\(A,S,T_1,T_2\\),
-    /// * three scalars \\(t_x, \tilde{t}_x, \tilde{e}\\),
-    /// * \\(n\\) pairs of compressed Ristretto points \\(L_0,R_0\dots,L_{n-1},R_{n-1}\\),
-    /// * two scalars \\(a, b\\).
-    pub fn to_bytes(&self) -> Vec<u8> {
-        // 7 elements: points A, S, T1, T2, scalars tx, tx_bl, e_bl.
-        let mut buf = Vec::with_capacity(7 * 32 + self.ipp_proof.serialized_size());
-        buf.extend_from_slice(self.A.as_bytes());
-        buf.extend_from_slice(self.S.as_bytes());
-        buf.extend_from_slice(self.T_1.as_bytes());
-        buf.extend_from_slice(self.T_2.as_bytes());
-        buf.extend_from_slice(self.t_x.as_bytes());
-        buf.extend_from_slice(self.t_x_blinding.as_bytes());
-        buf.extend_from_slice(self.e_blinding.as_bytes());
-        // XXX this costs an extra alloc
-        buf.extend_from_slice(self.ipp_proof.to_bytes().as_slice());
-        buf
+    /// This is a convenience wrapper around `verify_multiple_with_rng`,
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn verify_multiple(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        n: usize,
+    ) -> Result<(), ProofError> {
+        self.verify_multiple_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            value_commitments,
+            n,
+            &mut rand::thread_rng(),
+        )
     }
 
-    /// Deserializes the proof from a byte slice.
+    /// Verifies an aggregated rangeproof for the given value commitments.
     ///
-    /// Returns an error if the byte slice cannot be parsed into a `RangeProof`.
-    pub fn from_bytes(slice: &[u8]) -> Result<RangeProof, ProofError> {
-        if slice.len() % 32 != 0 {
-            return Err(ProofError::FormatError);
+    /// If the number of commitments is not a power of two, they are
+    /// padded with the identity in the same way as by
+    /// [`RangeProof::prove_multiple`].
+    pub fn verify_multiple_with_rng<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(), ProofError> {
+        self.verify_multiple_variable_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            value_commitments,
+            &vec![n; value_commitments.len()],
+            rng,
+        )
+    }
+
+    /// Verifies an aggregated rangeproof for the given value
+    /// commitments, where the `j`-th value is in a `bitsizes[j]`-bit
+    /// range.
+    ///
+    /// This is a convenience wrapper around
+    /// `verify_multiple_variable_with_rng`, passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn verify_multiple_variable(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        bitsizes: &[usize],
+    ) -> Result<(), ProofError> {
+        self.verify_multiple_variable_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            value_commitments,
+            bitsizes,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Verifies an aggregated rangeproof for the given value
+    /// commitments, where the `j`-th value is in a `bitsizes[j]`-bit
+    /// range.
+    ///
+    /// The bitsizes must be the same as those given to
+    /// [`RangeProof::prove_multiple_variable_with_rng`], and are padded
+    /// in the same way.
+    ///
+    /// The verification equation is evaluated with a variable-time
+    /// multiscalar multiplication, which is appropriate when the proof
+    /// and commitments are public.  See
+    /// [`RangeProof::verify_multiple_consttime_with_rng`] otherwise.
+    ///
+    /// With the `rayon` feature enabled, the multiscalar multiplication
+    /// for a large aggregation is split into chunks which are evaluated
+    /// in parallel.
+    pub fn verify_multiple_variable_with_rng<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        bitsizes: &[usize],
+        rng: &mut T,
+    ) -> Result<(), ProofError> {
+        if value_commitments.len() != bitsizes.len() {
+            return Err(ProofError::WrongNumBitsizes);
         }
-        if slice.len() < 7 * 32 {
-            return Err(ProofError::FormatError);
+        let context = VerifierContext::new_variable(bitsizes)?;
+
+        self.verify_with_context_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            value_commitments,
+            &context,
+            rng,
+        )
+    }
+
+    /// Verifies an aggregated rangeproof for the given value
+    /// commitments, using the constants precomputed in `context` for
+    /// the parties' bitsizes.
+    ///
+    /// This is a convenience wrapper around
+    /// [`RangeProof::verify_with_context_with_rng`], passing in a
+    /// threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn verify_with_context(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        context: &VerifierContext,
+    ) -> Result<(), ProofError> {
+        self.verify_with_context_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            value_commitments,
+            context,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Verifies an aggregated rangeproof for the given value
+    /// commitments, using the constants precomputed in `context` for
+    /// the parties' bitsizes.
+    ///
+    /// This is equivalent to
+    /// [`RangeProof::verify_multiple_variable_with_rng`] with the
+    /// bitsizes the `context` was created for, but saves recomputing
+    /// the constants when verifying many proofs of the same shape.
+    pub fn verify_with_context_with_rng<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        context: &VerifierContext,
+        rng: &mut T,
+    ) -> Result<(), ProofError> {
+        let eq = self.verification_equation_with_context(
+            transcript,
+            value_commitments,
+            context,
+            bp_gens.gens_capacity,
+            bp_gens.party_capacity,
+            rng,
+        )?;
+
+        let mega_check = optional_vartime_multiscalar_mul(
+            iter::once(eq.B_scalar)
+                .chain(iter::once(eq.B_blinding_scalar))
+                .chain(eq.g_scalars)
+                .chain(eq.h_scalars)
+                .chain(eq.dynamic_scalars),
+            iter::once(Some(pc_gens.B))
+                .chain(iter::once(Some(pc_gens.B_blinding)))
+                .chain(bp_gens.G_variable(&eq.bitsizes).map(|&x| Some(x)))
+                .chain(bp_gens.H_variable(&eq.bitsizes).map(|&x| Some(x)))
+                .chain(eq.dynamic_points),
+        )
+        .ok_or_else(|| ProofError::VerificationError)?;
+
+        if mega_check.is_identity() {
+            Ok(())
+        } else {
+            Err(ProofError::VerificationError)
+        }
+    }
+
+    /// Verifies an aggregated rangeproof for the given value
+    /// commitments, and reports which part of the verification failed
+    /// if it doesn't verify.
+    ///
+    /// This is a convenience wrapper around
+    /// `diagnose_multiple_variable_with_rng`, passing in a threadsafe
+    /// RNG.
+    #[cfg(all(feature = "diagnostics", feature = "std"))]
+    pub fn diagnose_multiple_variable(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        bitsizes: &[usize],
+    ) -> Result<Option<VerifyFailureReason>, ProofError> {
+        self.diagnose_multiple_variable_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            value_commitments,
+            bitsizes,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Verifies an aggregated rangeproof for the given value
+    /// commitments, and reports which part of the verification failed
+    /// if it doesn't verify.
+    ///
+    /// Returns `Ok(None)` if the proof verifies, as by
+    /// [`RangeProof::verify_multiple_variable_with_rng`], and
+    /// `Ok(Some(reason))` if it fails with
+    /// [`ProofError::VerificationError`].  The verification equation
+    /// combines the polynomial identity for \\(t(x)\\) with the
+    /// inner-product relation, so for a failing proof the identity is
+    /// checked again on its own to tell the two apart.  Other errors,
+    /// e.g. for mismatched bitsizes, are returned unchanged.
+    #[cfg(feature = "diagnostics")]
+    pub fn diagnose_multiple_variable_with_rng<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        bitsizes: &[usize],
+        rng: &mut T,
+    ) -> Result<Option<VerifyFailureReason>, ProofError> {
+        let mut replay = transcript.clone();
+        match self.verify_multiple_variable_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            value_commitments,
+            bitsizes,
+            rng,
+        ) {
+            Ok(()) => return Ok(None),
+            Err(ProofError::VerificationError) => {}
+            Err(e) => return Err(e),
+        }
+        let challenges = self.challenges(&mut replay, value_commitments, bitsizes)?;
+
+        let reason =
+            match self.expected_t_commitment(pc_gens, &challenges, value_commitments, bitsizes) {
+                Err(_) => VerifyFailureReason::PointDecompression,
+                Ok(T) if T != self.t_commitment(pc_gens) => VerifyFailureReason::PolynomialIdentity,
+                Ok(_) => {
+                    // The identity holds, so the failure is in the
+                    // inner-product argument, unless one of its points is
+                    // invalid.
+                    let valid_points = iter::once(&self.A)
+                        .chain(iter::once(&self.S))
+                        .chain(self.ipp_proof.L_vec.iter())
+                        .chain(self.ipp_proof.R_vec.iter())
+                        .all(|P| P.decompress().is_some());
+                    if valid_points {
+                        VerifyFailureReason::InnerProductRelation
+                    } else {
+                        VerifyFailureReason::PointDecompression
+                    }
+                }
+            };
+        Ok(Some(reason))
+    }
+
+    /// Verifies an aggregated rangeproof for the given value
+    /// commitments in constant time.
+    ///
+    /// This is a convenience wrapper around
+    /// `verify_multiple_consttime_with_rng`, passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn verify_multiple_consttime(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        n: usize,
+    ) -> Result<(), ProofError> {
+        self.verify_multiple_consttime_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            value_commitments,
+            n,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Verifies an aggregated rangeproof for the given value
+    /// commitments in constant time.
+    ///
+    /// This accepts exactly the same proofs as
+    /// [`RangeProof::verify_multiple_with_rng`], but evaluates the
+    /// verification equation with a constant-time multiscalar
+    /// multiplication, and only compares the result with the identity
+    /// at the very end, in constant time.  Use it when the proof or the
+    /// commitments should not be leaked through timing, at the cost of
+    /// slower verification.
+    ///
+    /// The parameters and the encoding of the points are still checked
+    /// up front, so an error is returned early for malformed input.
+    pub fn verify_multiple_consttime_with_rng<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(), ProofError> {
+        let eq = self.verification_equation(
+            transcript,
+            value_commitments,
+            &vec![n; value_commitments.len()],
+            bp_gens.gens_capacity,
+            bp_gens.party_capacity,
+            rng,
+        )?;
+
+        let dynamic_points = eq
+            .dynamic_points
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| ProofError::VerificationError)?;
+
+        let mega_check = RistrettoPoint::multiscalar_mul(
+            iter::once(eq.B_scalar)
+                .chain(iter::once(eq.B_blinding_scalar))
+                .chain(eq.g_scalars)
+                .chain(eq.h_scalars)
+                .chain(eq.dynamic_scalars),
+            iter::once(&pc_gens.B)
+                .chain(iter::once(&pc_gens.B_blinding))
+                .chain(bp_gens.G_variable(&eq.bitsizes))
+                .chain(bp_gens.H_variable(&eq.bitsizes))
+                .chain(dynamic_points.iter()),
+        );
+
+        if mega_check.ct_eq(&RistrettoPoint::identity()).into() {
+            Ok(())
+        } else {
+            Err(ProofError::VerificationError)
+        }
+    }
+
+    /// Verifies a rangeproof that the value committed to by \\(V\\)
+    /// lies in the range \\([a, b)\\).
+    ///
+    /// This is a convenience wrapper around `verify_bounded_with_rng`,
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn verify_bounded(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        V: &CompressedRistretto,
+        a: u64,
+        b: u64,
+        n: usize,
+    ) -> Result<(), ProofError> {
+        self.verify_bounded_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            V,
+            a,
+            b,
+            n,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Verifies a rangeproof that the value committed to by \\(V\\)
+    /// lies in the range \\([a, b)\\), as created by
+    /// [`RangeProof::prove_bounded_with_rng`].
+    ///
+    /// The commitments to \\(v - a\\) and \\(b - 1 - v\\) are
+    /// reconstructed as \\(V - a \cdot B\\) and
+    /// \\((b - 1) \cdot B - V\\).
+    pub fn verify_bounded_with_rng<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        V: &CompressedRistretto,
+        a: u64,
+        b: u64,
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(), ProofError> {
+        if a >= b {
+            return Err(ProofError::VerificationError);
         }
+        let V = V.decompress().ok_or(ProofError::VerificationError)?;
+
+        let V_lo = V - Scalar::from(a) * pc_gens.B;
+        let V_hi = Scalar::from(b - 1) * pc_gens.B - V;
+
+        self.verify_multiple_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            &[V_lo.compress(), V_hi.compress()],
+            n,
+            rng,
+        )
+    }
+
+    /// Verifies a rangeproof that the value committed to by \\(V\\)
+    /// is either zero or one.
+    ///
+    /// This is a convenience wrapper around `verify_bit_with_rng`,
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn verify_bit(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        V: &CompressedRistretto,
+    ) -> Result<(), ProofError> {
+        self.verify_bit_with_rng(bp_gens, pc_gens, transcript, V, &mut rand::thread_rng())
+    }
+
+    /// Verifies a rangeproof that the value committed to by \\(V\\)
+    /// is either zero or one, as created by
+    /// [`RangeProof::prove_bit_with_rng`].
+    pub fn verify_bit_with_rng<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        V: &CompressedRistretto,
+        rng: &mut T,
+    ) -> Result<(), ProofError> {
+        self.verify_bounded_with_rng(bp_gens, pc_gens, transcript, V, 0, 2, 8, rng)
+    }
+
+    /// Verifies a rangeproof that the signed value committed to by
+    /// \\(V\\) lies in \\([-2^{n-1}, 2^{n-1})\\).
+    ///
+    /// This is a convenience wrapper around `verify_signed_with_rng`,
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn verify_signed(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        V: &CompressedRistretto,
+        n: usize,
+    ) -> Result<(), ProofError> {
+        self.verify_signed_with_rng(bp_gens, pc_gens, transcript, V, n, &mut rand::thread_rng())
+    }
+
+    /// Verifies a rangeproof that the signed value committed to by
+    /// \\(V\\) lies in \\([-2^{n-1}, 2^{n-1})\\), as created by
+    /// [`RangeProof::prove_signed_with_rng`].
+    ///
+    /// The commitment to \\(v + 2^{n-1}\\) is reconstructed as
+    /// \\(V + 2^{n-1} B\\).
+    pub fn verify_signed_with_rng<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        V: &CompressedRistretto,
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(), ProofError> {
+        let offset = signed_offset(n)?;
+        let V = V.decompress().ok_or(ProofError::VerificationError)?;
+        let V_shifted = V + Scalar::from(offset) * pc_gens.B;
+
+        self.verify_single_with_rng(bp_gens, pc_gens, transcript, &V_shifted.compress(), n, rng)
+    }
+
+    /// Verifies a rangeproof that the value committed to by \\(V_a\\)
+    /// is at least the value committed to by \\(V_b\\).
+    ///
+    /// This is a convenience wrapper around `verify_difference_with_rng`,
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn verify_difference(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        V_a: &CompressedRistretto,
+        V_b: &CompressedRistretto,
+        n: usize,
+    ) -> Result<(), ProofError> {
+        self.verify_difference_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            V_a,
+            V_b,
+            n,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Verifies a rangeproof that the value committed to by \\(V_a\\)
+    /// is at least the value committed to by \\(V_b\\), as created by
+    /// [`RangeProof::prove_difference_with_rng`].
+    ///
+    /// The commitment to the difference is reconstructed as
+    /// \\(V_a - V_b\\).
+    pub fn verify_difference_with_rng<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        V_a: &CompressedRistretto,
+        V_b: &CompressedRistretto,
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(), ProofError> {
+        let V_a = V_a.decompress().ok_or(ProofError::VerificationError)?;
+        let V_b = V_b.decompress().ok_or(ProofError::VerificationError)?;
+
+        self.verify_single_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            &(V_a - V_b).compress(),
+            n,
+            rng,
+        )
+    }
 
-        use util::read32;
+    /// Verifies a rangeproof that the sum of the values committed to
+    /// by the `value_commitments` lies in \\([0, 2^n)\\).
+    ///
+    /// This is a convenience wrapper around `verify_sum_with_rng`,
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn verify_sum(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        n: usize,
+    ) -> Result<(), ProofError> {
+        self.verify_sum_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            value_commitments,
+            n,
+            &mut rand::thread_rng(),
+        )
+    }
 
-        let A = CompressedRistretto(read32(&slice[0 * 32..]));
-        let S = CompressedRistretto(read32(&slice[1 * 32..]));
-        let T_1 = CompressedRistretto(read32(&slice[2 * 32..]));
-        let T_2 = CompressedRistretto(read32(&slice[3 * 32..]));
+    /// Verifies a rangeproof that the sum of the values committed to
+    /// by the `value_commitments` lies in \\([0, 2^n)\\), as created
+    /// by [`RangeProof::prove_sum_with_rng`].
+    ///
+    /// The commitment to the sum is reconstructed as
+    /// \\(\sum_j V_j\\).
+    pub fn verify_sum_with_rng<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(), ProofError> {
+        let V = value_commitments
+            .iter()
+            .map(|V| V.decompress())
+            .sum::<Option<RistrettoPoint>>()
+            .ok_or(ProofError::VerificationError)?;
+
+        self.verify_single_with_rng(bp_gens, pc_gens, transcript, &V.compress(), n, rng)
+    }
+
+    /// Verifies an aggregated rangeproof for the given value
+    /// commitments, using generators precomputed with
+    /// [`BulletproofGens::precompute`].
+    ///
+    /// This is faster than [`RangeProof::verify_multiple`] when many
+    /// proofs are verified against the same generators.  The bitsize
+    /// `n` must be the one the generators were precomputed for, and
+    /// the (padded) aggregation size must be at most theirs.
+    ///
+    /// This is a convenience wrapper around
+    /// `verify_multiple_precomputed_with_rng`, passing in a
+    /// threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn verify_multiple_precomputed(
+        &self,
+        gens: &PrecomputedGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        n: usize,
+    ) -> Result<(), ProofError> {
+        self.verify_multiple_precomputed_with_rng(
+            gens,
+            transcript,
+            value_commitments,
+            n,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Verifies an aggregated rangeproof for the given value
+    /// commitments, using generators precomputed with
+    /// [`BulletproofGens::precompute`], and `rng` to randomize the
+    /// verification equation.
+    pub fn verify_multiple_precomputed_with_rng<T: RngCore + CryptoRng>(
+        &self,
+        gens: &PrecomputedGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(), ProofError> {
+        if n != gens.n {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+        let eq = self.verification_equation(
+            transcript,
+            value_commitments,
+            &vec![n; value_commitments.len()],
+            gens.n,
+            gens.m,
+            rng,
+        )?;
+
+        // The precomputation covers gens.m parties, so the generators
+        // of any parties beyond this proof's get zero scalars.
+        let pad = n * gens.m - eq.g_scalars.len();
+
+        let mega_check = gens
+            .precomputation
+            .optional_mixed_multiscalar_mul(
+                iter::once(eq.B_scalar)
+                    .chain(iter::once(eq.B_blinding_scalar))
+                    .chain(eq.g_scalars)
+                    .chain(iter::repeat(Scalar::zero()).take(pad))
+                    .chain(eq.h_scalars)
+                    .chain(iter::repeat(Scalar::zero()).take(pad)),
+                eq.dynamic_scalars,
+                eq.dynamic_points,
+            )
+            .ok_or_else(|| ProofError::VerificationError)?;
+
+        if mega_check.is_identity() {
+            Ok(())
+        } else {
+            Err(ProofError::VerificationError)
+        }
+    }
+
+    /// Verifies a batch of aggregated rangeproofs, each with its own
+    /// value commitments and transcript, using a single multiscalar
+    /// multiplication.
+    ///
+    /// Each proof's verification equation is weighted by an
+    /// independent random scalar before the equations are summed, so
+    /// that the combined check passes only if every individual check
+    /// does (except with negligible probability).  All proofs must
+    /// have the same bitsize `n`, but may have different aggregation
+    /// sizes.
+    ///
+    /// If the combined check fails, each proof is verified
+    /// individually, and the indexes of the proofs which failed are
+    /// returned in [`ProofError::BatchVerificationError`].
+    ///
+    /// This is the way to check proofs created in separate sessions
+    /// together.  Such proofs can't be merged into a single aggregated
+    /// proof after the fact: each one's challenges are bound to its own
+    /// transcript, so only the multiparty protocol can produce an
+    /// aggregated proof.
+    ///
+    /// This is a convenience wrapper around `batch_verify_with_rng`,
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn batch_verify(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcripts: &mut [Transcript],
+        proofs: &[(RangeProof, Vec<CompressedRistretto>)],
+        n: usize,
+    ) -> Result<(), ProofError> {
+        RangeProof::batch_verify_with_rng(
+            bp_gens,
+            pc_gens,
+            transcripts,
+            proofs,
+            n,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Verifies a batch of aggregated rangeproofs, as in
+    /// [`RangeProof::batch_verify`], using `rng` to generate the
+    /// weights of the verification equations.
+    pub fn batch_verify_with_rng<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcripts: &mut [Transcript],
+        proofs: &[(RangeProof, Vec<CompressedRistretto>)],
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(), ProofError> {
+        if transcripts.len() != proofs.len() {
+            return Err(ProofError::WrongNumTranscripts);
+        }
+
+        // Keep a copy of each initial transcript state, so that the
+        // proofs can be checked individually if the batch fails.
+        let initial_transcripts: Vec<Transcript> = transcripts.iter().cloned().collect();
+
+        let max_m = proofs
+            .iter()
+            .map(|(_, Vs)| util::next_power_of_two(Vs.len()))
+            .max()
+            .unwrap_or(0);
+
+        let mut B_scalar = Scalar::zero();
+        let mut B_blinding_scalar = Scalar::zero();
+        let mut g_scalars = vec![Scalar::zero(); n * max_m];
+        let mut h_scalars = vec![Scalar::zero(); n * max_m];
+        let mut dynamic_scalars = Vec::new();
+        let mut dynamic_points = Vec::new();
+
+        let mut batch_ok = true;
+        for ((proof, Vs), transcript) in proofs.iter().zip(transcripts.iter_mut()) {
+            let eq = match proof.verification_equation(
+                transcript,
+                Vs,
+                &vec![n; Vs.len()],
+                bp_gens.gens_capacity,
+                bp_gens.party_capacity,
+                rng,
+            ) {
+                Ok(eq) => eq,
+                Err(ProofError::VerificationError) => {
+                    batch_ok = false;
+                    break;
+                }
+                Err(e) => return Err(e),
+            };
+
+            let weight = Scalar::random(rng);
+
+            B_scalar += weight * eq.B_scalar;
+            B_blinding_scalar += weight * eq.B_blinding_scalar;
+            // Each proof's generators are a prefix of the largest
+            // proof's generators, since they are laid out party by party.
+            for (acc, g_i) in g_scalars.iter_mut().zip(eq.g_scalars.iter()) {
+                *acc += weight * g_i;
+            }
+            for (acc, h_i) in h_scalars.iter_mut().zip(eq.h_scalars.iter()) {
+                *acc += weight * h_i;
+            }
+            dynamic_scalars.extend(eq.dynamic_scalars.iter().map(|s| weight * s));
+            dynamic_points.extend(eq.dynamic_points);
+        }
+
+        if batch_ok {
+            let mega_check = RistrettoPoint::optional_multiscalar_mul(
+                iter::once(B_scalar)
+                    .chain(iter::once(B_blinding_scalar))
+                    .chain(g_scalars)
+                    .chain(h_scalars)
+                    .chain(dynamic_scalars),
+                iter::once(Some(pc_gens.B))
+                    .chain(iter::once(Some(pc_gens.B_blinding)))
+                    .chain(bp_gens.G(n, max_m).map(|&x| Some(x)))
+                    .chain(bp_gens.H(n, max_m).map(|&x| Some(x)))
+                    .chain(dynamic_points),
+            );
+
+            if mega_check.map_or(false, |p| p.is_identity()) {
+                return Ok(());
+            }
+        }
+
+        // The batch failed, so find out which proofs are invalid.
+        let bad_proofs: Vec<usize> = proofs
+            .iter()
+            .zip(initial_transcripts.into_iter())
+            .enumerate()
+            .filter_map(|(i, ((proof, Vs), mut transcript))| {
+                match proof.verify_multiple_with_rng(bp_gens, pc_gens, &mut transcript, Vs, n, rng)
+                {
+                    Ok(_) => None,
+                    Err(_) => Some(i),
+                }
+            })
+            .collect();
+
+        if bad_proofs.is_empty() {
+            Ok(())
+        } else {
+            Err(ProofError::BatchVerificationError { bad_proofs })
+        }
+    }
+
+    /// Verifies an aggregated rangeproof for the given value
+    /// commitments, where the `j`-th value lies in a
+    /// `bitsizes[j]`-bit range, and returns the point \\(P\\) opened
+    /// by its inner-product proof.
+    ///
+    /// This is a convenience wrapper around
+    /// `verify_and_recover_with_rng`, passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn verify_and_recover(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        bitsizes: &[usize],
+    ) -> Result<RistrettoPoint, ProofError> {
+        self.verify_and_recover_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            value_commitments,
+            bitsizes,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Verifies an aggregated rangeproof for the given value
+    /// commitments, where the `j`-th value lies in a
+    /// `bitsizes[j]`-bit range, and returns the point \\(P\\) opened
+    /// by its inner-product proof.
+    ///
+    /// This is the point
+    /// \\[
+    /// P = A + x S - \tilde{e} \tilde{B} - z \langle \mathbf{1}, \mathbf{G} \rangle
+    ///     + \langle z \mathbf{y}^{nm} + \mathbf{d}, \mathbf{H}' \rangle
+    /// \\]
+    /// reconstructed by the verifier, where \\(\mathbf{d}\\) is the
+    /// concatenation of the vectors \\(z^{j+2} \mathbf{2}^{n_j}\\)
+    /// and \\(H'_i = y^{-i} H_i\\).  The inner-product proof shows
+    /// that \\(P = \langle \mathbf{l}, \mathbf{G} \rangle +
+    /// \langle \mathbf{r}, \mathbf{H}' \rangle\\) with
+    /// \\(\langle \mathbf{l}, \mathbf{r} \rangle = t(x)\\), so an
+    /// outer protocol can use \\(P\\) to make further checks on the
+    /// vectors.  The point is only returned if the proof verifies.
+    pub fn verify_and_recover_with_rng<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        bitsizes: &[usize],
+        rng: &mut T,
+    ) -> Result<RistrettoPoint, ProofError> {
+        let context = VerifierContext::new_variable(bitsizes)?;
+        let mut replay = transcript.clone();
+        self.verify_with_context_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            value_commitments,
+            &context,
+            rng,
+        )?;
+        let RangeProofChallenges { y, z, x, .. } =
+            self.challenges(&mut replay, value_commitments, bitsizes)?;
+
+        let bitsizes = &context.padded_bitsizes;
+        let zz = z * z;
+        let minus_z = -z;
+        let nm: usize = bitsizes.iter().sum();
+
+        let powers_of_2 = &context.powers_of_2;
+        let concat_z_and_2: Vec<Scalar> = util::exp_iter(z)
+            .zip(bitsizes.iter())
+            .flat_map(|(exp_z, &n)| powers_of_2[..n].iter().map(move |exp_2| exp_2 * exp_z))
+            .collect();
+        let h_scalars = util::exp_iter(y.invert())
+            .zip(concat_z_and_2.iter())
+            .map(|(exp_y_inv, z_and_2)| z + exp_y_inv * zz * z_and_2);
+
+        RistrettoPoint::optional_multiscalar_mul(
+            iter::once(Scalar::one())
+                .chain(iter::once(x))
+                .chain(iter::once(-self.e_blinding))
+                .chain(iter::repeat(minus_z).take(nm))
+                .chain(h_scalars),
+            iter::once(self.A.decompress())
+                .chain(iter::once(self.S.decompress()))
+                .chain(iter::once(Some(pc_gens.B_blinding)))
+                .chain(bp_gens.G_variable(bitsizes).map(|&G| Some(G)))
+                .chain(bp_gens.H_variable(bitsizes).map(|&H| Some(H))),
+        )
+        .ok_or(ProofError::InvalidPoint)
+    }
+
+    /// Replays the proof transcript to recompute the challenges
+    /// \\(y, z, x, w\\), without verifying the proof.
+    ///
+    /// The `transcript` and `bitsizes` must be the same as for
+    /// [`RangeProof::verify_multiple_variable`].  For an honestly
+    /// created proof, these are the challenges derived by the dealer,
+    /// which makes this useful for finding where the transcripts of
+    /// two implementations diverge.
+    pub fn challenges(
+        &self,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        bitsizes: &[usize],
+    ) -> Result<RangeProofChallenges, ProofError> {
+        if value_commitments.len() != bitsizes.len() {
+            return Err(ProofError::WrongNumBitsizes);
+        }
+        let bitsizes = pad_bitsizes(bitsizes)?;
+        let padded_commitments = pad_commitments(value_commitments, bitsizes.len());
+
+        self.padded_challenges(transcript, &padded_commitments, &bitsizes)
+    }
+
+    /// Computes the term \\(\delta(y,z)\\) of the polynomial identity
+    /// checked for \\(t(x)\\), for values in `bitsizes[j]`-bit ranges.
+    ///
+    /// The bitsizes are padded in the same way as by
+    /// [`RangeProof::prove_multiple_variable`], so they should be the
+    /// same as those passed to the verifier.
+    pub fn delta(bitsizes: &[usize], y: &Scalar, z: &Scalar) -> Result<Scalar, ProofError> {
+        Ok(delta(&pad_bitsizes(bitsizes)?, y, z))
+    }
+
+    /// Returns the commitment \\(t(x) B + \tilde{t}(x) \tilde{B}\\)
+    /// to the evaluation of \\(t(x)\\) revealed by the proof.
+    ///
+    /// For a valid proof, this is equal to
+    /// [`RangeProof::expected_t_commitment`].
+    pub fn t_commitment(&self, pc_gens: &PedersenGens) -> RistrettoPoint {
+        pc_gens.commit(self.t_x, self.t_x_blinding)
+    }
+
+    /// Computes the commitment to \\(t(x)\\) implied by the value
+    /// commitments and the proof's commitments \\(T_1, T_2\\),
+    /// \\[
+    /// z^2 \sum_{j=0}^{m-1} z^j V_j + \delta(y,z) B + x T_1 + x^2 T_2,
+    /// \\]
+    /// where the `challenges` are those returned by
+    /// [`RangeProof::challenges`] for the same `value_commitments`
+    /// and `bitsizes`.
+    ///
+    /// Together with [`RangeProof::t_commitment`], this allows the
+    /// polynomial identity for \\(t(x)\\) to be checked on its own,
+    /// separately from the rest of the verification equation.
+    pub fn expected_t_commitment(
+        &self,
+        pc_gens: &PedersenGens,
+        challenges: &RangeProofChallenges,
+        value_commitments: &[CompressedRistretto],
+        bitsizes: &[usize],
+    ) -> Result<RistrettoPoint, ProofError> {
+        if value_commitments.len() != bitsizes.len() {
+            return Err(ProofError::WrongNumBitsizes);
+        }
+        let RangeProofChallenges { y, z, x, .. } = *challenges;
+
+        let T_check = RistrettoPoint::optional_multiscalar_mul(
+            iter::once(RangeProof::delta(bitsizes, &y, &z)?)
+                .chain(iter::once(x))
+                .chain(iter::once(x * x)),
+            iter::once(Some(pc_gens.B))
+                .chain(iter::once(self.T_1.decompress()))
+                .chain(iter::once(self.T_2.decompress())),
+        )
+        .ok_or(ProofError::InvalidPoint)?;
+
+        Ok(RangeProof::commitment_sum(value_commitments, &z)? + T_check)
+    }
+
+    /// Computes the weighted sum \\(\sum_{j=0}^{m-1} z^{j+2} V_j\\) of
+    /// the value commitments, with the same weights as in the
+    /// verification equation.
+    ///
+    /// The padding commitments are the identity, so they don't need
+    /// to be included.  Returns [`ProofError::InvalidPoint`] if any of
+    /// the commitments fails to decompress.
+    pub fn commitment_sum(
+        value_commitments: &[CompressedRistretto],
+        z: &Scalar,
+    ) -> Result<RistrettoPoint, ProofError> {
+        let zz = z * z;
+
+        RistrettoPoint::optional_multiscalar_mul(
+            util::exp_iter(*z)
+                .take(value_commitments.len())
+                .map(|z_exp| zz * z_exp),
+            value_commitments.iter().map(|V| V.decompress()),
+        )
+        .ok_or(ProofError::InvalidPoint)
+    }
+
+    /// Replays the proof transcript for already padded commitments
+    /// and bitsizes.
+    fn padded_challenges(
+        &self,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        bitsizes: &[usize],
+    ) -> Result<RangeProofChallenges, ProofError> {
+        transcript.variable_rangeproof_domain_sep(bitsizes);
+
+        for V in value_commitments.iter() {
+            // Allow the commitments to be zero (0 value, 0 blinding)
+            // See https://github.com/dalek-cryptography/bulletproofs/pull/248#discussion_r255167177
+            transcript.append_point(labels::V, V);
+        }
+
+        transcript.validate_and_append_point(labels::A, &self.A)?;
+        transcript.validate_and_append_point(labels::S, &self.S)?;
+
+        let y = transcript.challenge_scalar(labels::Y);
+        let z = transcript.challenge_scalar(labels::Z);
+
+        transcript.validate_and_append_point(labels::T_1, &self.T_1)?;
+        transcript.validate_and_append_point(labels::T_2, &self.T_2)?;
+
+        let x = transcript.challenge_scalar(labels::X);
+
+        transcript.append_scalar(labels::T_X, &self.t_x);
+        transcript.append_scalar(labels::T_X_BLINDING, &self.t_x_blinding);
+        transcript.append_scalar(labels::E_BLINDING, &self.e_blinding);
+
+        let w = transcript.challenge_scalar(labels::W);
+
+        Ok(RangeProofChallenges { y, z, x, w })
+    }
+
+    /// Replays the proof transcript to recompute all challenges, and
+    /// returns the resulting verification equation, which holds if
+    /// and only if it evaluates to the identity.
+    ///
+    /// The `gens_capacity` and `party_capacity` are those of the
+    /// generators which the equation will be evaluated against.
+    fn verification_equation<T: RngCore + CryptoRng>(
+        &self,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        bitsizes: &[usize],
+        gens_capacity: usize,
+        party_capacity: usize,
+        rng: &mut T,
+    ) -> Result<VerificationEquation, ProofError> {
+        if value_commitments.len() != bitsizes.len() {
+            return Err(ProofError::WrongNumBitsizes);
+        }
+        let context = VerifierContext::new_variable(bitsizes)?;
+
+        self.verification_equation_with_context(
+            transcript,
+            value_commitments,
+            &context,
+            gens_capacity,
+            party_capacity,
+            rng,
+        )
+    }
+
+    /// Returns the verification equation as by
+    /// `verification_equation`, for the bitsizes of the `context`.
+    fn verification_equation_with_context<T: RngCore + CryptoRng>(
+        &self,
+        transcript: &mut Transcript,
+        value_commitments: &[CompressedRistretto],
+        context: &VerifierContext,
+        gens_capacity: usize,
+        party_capacity: usize,
+        rng: &mut T,
+    ) -> Result<VerificationEquation, ProofError> {
+        if value_commitments.len() != context.bitsizes.len() {
+            return Err(ProofError::WrongNumBitsizes);
+        }
+
+        // Pad the commitments with the identity, to match the
+        // commitments to zero added by the prover.
+        let bitsizes = &context.padded_bitsizes;
+        let padded_commitments = pad_commitments(value_commitments, bitsizes.len());
+        let value_commitments = &padded_commitments[..];
+        let m = value_commitments.len();
+        let nm: usize = bitsizes.iter().sum();
+
+        // First, replay the "interactive" protocol using the proof
+        // data to recompute all challenges.
+        if bitsizes.iter().any(|&n| gens_capacity < n) {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+        if party_capacity < m {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+
+        let RangeProofChallenges { y, z, x, w } =
+            self.padded_challenges(transcript, value_commitments, bitsizes)?;
+        let zz = z * z;
+        let minus_z = -z;
+
+        let mut rng = transcript.build_rng().finalize(rng);
+
+        // Challenge value for batching statements to be verified
+        let c = Scalar::random(&mut rng);
+
+        // As when proving, this appends the inner-product proof's
+        // domain separator before replaying its challenges.
+        let (x_sq, x_inv_sq, s) = self.ipp_proof.verification_scalars(nm, transcript)?;
+        let s_inv = s.iter().rev();
+
+        let a = self.ipp_proof.a;
+        let b = self.ipp_proof.b;
+
+        // Construct concat_z_and_2, an iterator of the values of
+        // z^0 * \vec(2)^n_0 || z^1 * \vec(2)^n_1 || ... || z^(m-1) * \vec(2)^n_(m-1),
+        // using the powers of 2 precomputed once for all of the parties
+        let powers_of_2 = &context.powers_of_2;
+        let concat_z_and_2: Vec<Scalar> = util::exp_iter(z)
+            .zip(bitsizes.iter())
+            .flat_map(|(exp_z, &n)| powers_of_2[..n].iter().map(move |exp_2| exp_2 * exp_z))
+            .collect();
+
+        let g_scalars = s.iter().map(|s_i| minus_z - a * s_i).collect();
+        let h_scalars = s_inv
+            .zip(util::exp_iter(y.invert()))
+            .zip(concat_z_and_2.iter())
+            .map(|((s_i_inv, exp_y_inv), z_and_2)| z + exp_y_inv * (zz * z_and_2 - b * s_i_inv))
+            .collect();
+
+        let value_commitment_scalars = util::exp_iter(z).take(m).map(|z_exp| c * zz * z_exp);
+
+        let dynamic_scalars = iter::once(Scalar::one())
+            .chain(iter::once(x))
+            .chain(iter::once(c * x))
+            .chain(iter::once(c * x * x))
+            .chain(x_sq.iter().cloned())
+            .chain(x_inv_sq.iter().cloned())
+            .chain(value_commitment_scalars)
+            .collect();
+        let dynamic_points = iter::once(self.A.decompress())
+            .chain(iter::once(self.S.decompress()))
+            .chain(iter::once(self.T_1.decompress()))
+            .chain(iter::once(self.T_2.decompress()))
+            .chain(self.ipp_proof.L_vec.iter().map(|L| L.decompress()))
+            .chain(self.ipp_proof.R_vec.iter().map(|R| R.decompress()))
+            .chain(value_commitments.iter().map(|V| V.decompress()))
+            .collect();
+
+        Ok(VerificationEquation {
+            B_scalar: w * (self.t_x - a * b) + c * (context.delta(&y, &z) - self.t_x),
+            B_blinding_scalar: -self.e_blinding - c * self.t_x_blinding,
+            g_scalars,
+            h_scalars,
+            dynamic_scalars,
+            dynamic_points,
+            bitsizes: bitsizes.clone(),
+        })
+    }
+
+    /// Returns the size in bytes of the serialization of a proof for
+    /// `m` values of `n` bits each, without creating the proof.
+    ///
+    /// The aggregation size is padded to a power of two in the same
+    /// way as [`RangeProof::prove_multiple`], so this is the exact
+    /// length of the output of [`RangeProof::to_bytes`].  Returns
+    /// [`ProofError::InvalidBitsize`] unless `n` is one of
+    /// \\(8, 16, 32, 64, 128\\), and [`ProofError::InvalidAggregation`]
+    /// if `m` is zero or too large for the padded aggregation to fit in
+    /// a `usize`.
+    pub fn proof_size(n: usize, m: usize) -> Result<usize, ProofError> {
+        if !util::is_valid_bitsize(n) {
+            return Err(ProofError::InvalidBitsize);
+        }
+        if m == 0 {
+            return Err(ProofError::InvalidAggregation);
+        }
+        let nm = m
+            .checked_next_power_of_two()
+            .and_then(|m| m.checked_mul(n))
+            .ok_or(ProofError::InvalidAggregation)?;
+        Ok((2 * nm.trailing_zeros() as usize + 9) * 32)
+    }
+
+    /// Returns the number of padding parties, committing to zero with
+    /// the smallest of the `bitsizes`, which are added to an
+    /// aggregation with these `bitsizes` by
+    /// [`RangeProof::prove_multiple_variable`] and its verifier.
+    ///
+    /// The padding parties come after the real ones, and their
+    /// commitments are not returned by the prover, so the `j`-th
+    /// returned commitment is always that of the `j`-th value.
+    ///
+    /// Returns [`ProofError::InvalidAggregation`] if `bitsizes` is
+    /// empty, and [`ProofError::InvalidBitsize`] if any bitsize is
+    /// not one of \\(8, 16, 32, 64, 128\\).
+    pub fn pad_count(bitsizes: &[usize]) -> Result<usize, ProofError> {
+        if bitsizes.is_empty() {
+            return Err(ProofError::InvalidAggregation);
+        }
+        if bitsizes.iter().any(|&n| !util::is_valid_bitsize(n)) {
+            return Err(ProofError::InvalidBitsize);
+        }
+
+        // Every bitsize is a multiple of the smallest, so the padding
+        // is too.
+        let min_n = *bitsizes.iter().min().unwrap();
+        let total: usize = bitsizes.iter().sum();
+        Ok((util::next_power_of_two(total) - total) / min_n)
+    }
+
+    /// Returns the total number of bits \\(\sum_j n_j\\) proven to be
+    /// in range by this proof, including the bits of any padding
+    /// parties.
+    ///
+    /// The bitsizes and the aggregation size are not part of the
+    /// proof, so they can't be recovered separately: the verifier must
+    /// know them, and can use this as a sanity check that they match
+    /// the proof.
+    pub fn num_bits(&self) -> usize {
+        1 << self.ipp_proof.L_vec.len()
+    }
+
+    /// Checks that the proof is well-formed, without doing any of the
+    /// expensive work of verifying it.
+    ///
+    /// This checks that the inner-product proof has as many \\(L\\)
+    /// as \\(R\\) points, and proves between \\(8\\) and
+    /// \\(2^{31}\\) bits, like the encoding parsed by
+    /// [`RangeProof::from_bytes`].  It also checks that every point
+    /// decompresses, returning [`ProofError::InvalidPoint`] otherwise,
+    /// and that none of \\(A, S, T_1, T_2\\) is the identity, which
+    /// would make the verifier fail.  A proof which passes these
+    /// checks can still fail to verify, but one which fails them is
+    /// certainly invalid, so a verifier can use this to reject
+    /// malformed proofs before paying for
+    /// [`RangeProof::verification_cost`].  The caller should also
+    /// check that [`RangeProof::num_bits`] matches the expected
+    /// bitsizes.
+    pub fn check_wellformed(&self) -> Result<(), ProofError> {
+        let lg_nm = self.ipp_proof.L_vec.len();
+        if lg_nm != self.ipp_proof.R_vec.len() || lg_nm < 3 || lg_nm >= 32 {
+            return Err(ProofError::FormatError);
+        }
+
+        let points = [self.A, self.S, self.T_1, self.T_2];
+        if points.iter().any(|P| P.is_identity()) {
+            return Err(ProofError::VerificationError);
+        }
+
+        let all_points = points
+            .iter()
+            .chain(self.ipp_proof.L_vec.iter())
+            .chain(self.ipp_proof.R_vec.iter());
+        for P in all_points {
+            P.decompress().ok_or(ProofError::InvalidPoint)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of points in the multiscalar multiplication
+    /// which verifies this proof, as an estimate of its cost that can
+    /// be checked before any point is decompressed.
+    ///
+    /// For \\(nm\\) total bits, this is \\(2nm + 2 \lg(nm) + 6\\): the
+    /// \\(\mathbf{G}\\) and \\(\mathbf{H}\\) generators, the
+    /// inner-product proof's \\(L\\) and \\(R\\) points, and the bases
+    /// \\(B, \tilde{B}\\) with \\(A, S, T_1, T_2\\).  The value
+    /// commitments, which aren't part of the proof, add one point each.
+    pub fn verification_cost(&self) -> usize {
+        let lg_nm = self.ipp_proof.L_vec.len();
+        2 * self.num_bits() + 2 * lg_nm + 6
+    }
+
+    /// Serializes the proof into a byte array of \\(2 \lg n + 9\\)
+    /// 32-byte elements, where \\(n\\) is the number of secret bits
+    /// (the bitsize times the aggregation size).
+    ///
+    /// # Layout
+    ///
+    /// The layout of the range proof encoding is:
+    ///
+    /// * four compressed Ristretto points \\(A,S,T_1,T_2\\),
+    /// * three scalars \\(t_x, \tilde{t}_x, \tilde{e}\\),
+    /// * \\(n\\) pairs of compressed Ristretto points \\(L_0,R_0\dots,L_{n-1},R_{n-1}\\),
+    /// * two scalars \\(a, b\\).
+    ///
+    /// The bitsize and the value commitments are not part of the
+    /// encoding: like the transcript, they must be supplied to the
+    /// verifier separately.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // 7 elements: points A, S, T1, T2, scalars tx, tx_bl, e_bl.
+        let mut buf = Vec::with_capacity(7 * 32 + self.ipp_proof.serialized_size());
+        buf.extend_from_slice(self.A.as_bytes());
+        buf.extend_from_slice(self.S.as_bytes());
+        buf.extend_from_slice(self.T_1.as_bytes());
+        buf.extend_from_slice(self.T_2.as_bytes());
+        buf.extend_from_slice(self.t_x.as_bytes());
+        buf.extend_from_slice(self.t_x_blinding.as_bytes());
+        buf.extend_from_slice(self.e_blinding.as_bytes());
+        // XXX this costs an extra alloc
+        buf.extend_from_slice(self.ipp_proof.to_bytes().as_slice());
+        buf
+    }
+
+    /// Deserializes the proof from a byte slice.
+    ///
+    /// Returns an error if the byte slice cannot be parsed into a
+    /// `RangeProof`, including when its length is not exactly
+    /// \\(32 \cdot (2k + 9)\\) bytes for some \\(k < 32\\).
+    /// Every point is decompressed eagerly, so that a point which is
+    /// not a valid compressed Ristretto point is reported as
+    /// `ProofError::InvalidPoint` rather than as a verification failure.
+    ///
+    /// The length is checked before anything is read, and the encoding
+    /// does not declare the bitsize or aggregation size, so arbitrary
+    /// input never panics or causes a large allocation.  This makes
+    /// `from_bytes` suitable as a fuzzing entry point.
+    pub fn from_bytes(slice: &[u8]) -> Result<RangeProof, ProofError> {
+        if slice.len() % 32 != 0 {
+            return Err(ProofError::FormatError);
+        }
+        if slice.len() < 7 * 32 {
+            return Err(ProofError::FormatError);
+        }
+
+        use util::{read32, read_point};
+
+        let A = read_point(&slice[0 * 32..])?;
+        let S = read_point(&slice[1 * 32..])?;
+        let T_1 = read_point(&slice[2 * 32..])?;
+        let T_2 = read_point(&slice[3 * 32..])?;
+
+        let t_x = Scalar::from_canonical_bytes(read32(&slice[4 * 32..]))
+            .ok_or(ProofError::FormatError)?;
+        let t_x_blinding = Scalar::from_canonical_bytes(read32(&slice[5 * 32..]))
+            .ok_or(ProofError::FormatError)?;
+        let e_blinding = Scalar::from_canonical_bytes(read32(&slice[6 * 32..]))
+            .ok_or(ProofError::FormatError)?;
+
+        let ipp_proof = InnerProductProof::from_bytes(&slice[7 * 32..])?;
+
+        Ok(RangeProof {
+            A,
+            S,
+            T_1,
+            T_2,
+            t_x,
+            t_x_blinding,
+            e_blinding,
+            ipp_proof,
+        })
+    }
+
+    /// Serializes the proof as a lowercase hex string of the bytes
+    /// returned by [`RangeProof::to_bytes`].
+    #[cfg(feature = "hex")]
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.to_bytes())
+    }
+
+    /// Deserializes the proof from a hex string, as produced by
+    /// [`RangeProof::to_hex`].
+    ///
+    /// Returns [`ProofError::FormatError`] if the string is not valid
+    /// hex, or any error returned by [`RangeProof::from_bytes`].
+    #[cfg(feature = "hex")]
+    pub fn from_hex(s: &str) -> Result<RangeProof, ProofError> {
+        let bytes = hex::decode(s).map_err(|_| ProofError::FormatError)?;
+        RangeProof::from_bytes(&bytes)
+    }
+
+    /// Writes the proof for `m` values of `n` bits each to `w` as a
+    /// self-delimiting frame, which can be read back with
+    /// [`RangeProof::read_framed`].
+    ///
+    /// The frame is the length of the proof as a 4-byte big-endian
+    /// integer, followed by `n` and `m` in the same format, and then
+    /// the bytes returned by [`RangeProof::to_bytes`].  Returns an
+    /// error of kind [`io::ErrorKind::InvalidInput`] if the proof does
+    /// not have the size [`RangeProof::proof_size`] of a proof for `n`
+    /// and `m`, or if `m` does not fit in the 4-byte header.
+    #[cfg(feature = "std")]
+    pub fn write_framed<W: io::Write>(&self, w: &mut W, n: usize, m: usize) -> io::Result<()> {
+        let bytes = self.to_bytes();
+        if RangeProof::proof_size(n, m) != Ok(bytes.len()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "proof size does not match n and m",
+            ));
+        }
+        let max = u32::max_value() as u64;
+        if bytes.len() as u64 > max || n as u64 > max || m as u64 > max {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame header does not fit in 32 bits",
+            ));
+        }
+
+        w.write_u32::<BigEndian>(bytes.len() as u32)?;
+        w.write_u32::<BigEndian>(n as u32)?;
+        w.write_u32::<BigEndian>(m as u32)?;
+        w.write_all(&bytes)
+    }
+
+    /// Reads a proof written by [`RangeProof::write_framed`] from `r`,
+    /// returning it with its bitsize `n` and aggregation size `m`.
+    ///
+    /// The length prefix is checked against the size of a proof for
+    /// `n` and `m` before anything is allocated, so a corrupt or
+    /// malicious prefix can't cause a large allocation.  Returns an
+    /// error of kind [`io::ErrorKind::InvalidData`] if the header is
+    /// inconsistent or the proof can't be parsed.
+    #[cfg(feature = "std")]
+    pub fn read_framed<R: io::Read>(r: &mut R) -> io::Result<(RangeProof, usize, usize)> {
+        let len = r.read_u32::<BigEndian>()? as usize;
+        let n = r.read_u32::<BigEndian>()? as usize;
+        let m = r.read_u32::<BigEndian>()? as usize;
+        if RangeProof::proof_size(n, m) != Ok(len) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "proof size does not match n and m",
+            ));
+        }
+
+        let mut bytes = vec![0u8; len];
+        r.read_exact(&mut bytes)?;
+        let proof = RangeProof::from_bytes(&bytes)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+        Ok((proof, n, m))
+    }
+
+    /// Returns a 32-byte fingerprint of the proof, which can be used
+    /// to identify it in logs or as a key when caching verification
+    /// results.
+    ///
+    /// The fingerprint is the SHA3-512 hash of the bytes returned by
+    /// [`RangeProof::to_bytes`], truncated to 32 bytes.  Like the
+    /// encoding, it does not cover the value commitments or the
+    /// bitsize, so a cached result should be keyed by those as well.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let digest = Sha3_512::digest(&self.to_bytes());
+        let mut fingerprint = [0u8; 32];
+        fingerprint.copy_from_slice(&digest[..32]);
+        fingerprint
+    }
+}
+
+/// The constants of the rangeproof verification equation which only
+/// depend on the bitsizes of the parties.
+///
+/// A verifier checking many proofs of the same shape can create the
+/// context once and pass it to [`RangeProof::verify_with_context`],
+/// rather than recomputing the padding of the aggregation, the powers
+/// of 2 and their sums for each proof.
+#[derive(Clone, Debug)]
+pub struct VerifierContext {
+    /// The bitsizes of the parties, as given to the verifier
+    bitsizes: Vec<usize>,
+    /// The bitsizes padded in the same way as by the prover
+    padded_bitsizes: Vec<usize>,
+    /// The powers of 2 up to the largest bitsize
+    powers_of_2: Vec<Scalar>,
+    /// The sums \\(\langle \mathbf{1}, \mathbf{2}^{n_j} \rangle\\) for
+    /// each of the padded parties
+    sums_of_powers_of_2: Vec<Scalar>,
+}
+
+impl VerifierContext {
+    /// Creates the context for verifying proofs for `m` values in
+    /// `n`-bit ranges.
+    pub fn new(n: usize, m: usize) -> Result<VerifierContext, ProofError> {
+        VerifierContext::new_variable(&vec![n; m])
+    }
+
+    /// Creates the context for verifying proofs for values where the
+    /// `j`-th value is in a `bitsizes[j]`-bit range.
+    ///
+    /// Returns the same errors as
+    /// [`RangeProof::verify_multiple_variable`] for invalid bitsizes.
+    pub fn new_variable(bitsizes: &[usize]) -> Result<VerifierContext, ProofError> {
+        let padded_bitsizes = pad_bitsizes(bitsizes)?;
+        let max_n = padded_bitsizes.iter().cloned().max().unwrap_or(0);
+        let two = Scalar::from(2u64);
+        let sums_of_powers_of_2 = padded_bitsizes
+            .iter()
+            .map(|&n| util::sum_of_powers(&two, n))
+            .collect();
+
+        Ok(VerifierContext {
+            bitsizes: bitsizes.to_vec(),
+            padded_bitsizes,
+            powers_of_2: util::exp_vec(two, max_n),
+            sums_of_powers_of_2,
+        })
+    }
+
+    /// The bitsizes of the parties, without padding.
+    pub fn bitsizes(&self) -> &[usize] {
+        &self.bitsizes
+    }
+
+    /// Computes \\(\delta(y,z)\\) as by `delta`, using the
+    /// precomputed sums of the powers of 2.
+    fn delta(&self, y: &Scalar, z: &Scalar) -> Scalar {
+        let nm = self.padded_bitsizes.iter().sum();
+        let sum_y = util::sum_of_powers(y, nm);
+        let sum_z_2: Scalar = util::exp_iter(*z)
+            .zip(self.sums_of_powers_of_2.iter())
+            .map(|(exp_z, sum_2)| exp_z * sum_2)
+            .sum();
+
+        (z - z * z) * sum_y - z * z * z * sum_z_2
+    }
+}
+
+/// The verification equation for a single rangeproof.
+///
+/// The scalars for the static generators \\(B\\), \\(\tilde{B}\\),
+/// \\(\mathbf{G}\\) and \\(\mathbf{H}\\) are kept apart from the
+/// proof's own points, so that the equations for several proofs can
+/// be combined into a single multiscalar multiplication.
+struct VerificationEquation {
+    /// Scalar for the Pedersen value base \\(B\\)
+    B_scalar: Scalar,
+    /// Scalar for the Pedersen blinding base \\(\tilde{B}\\)
+    B_blinding_scalar: Scalar,
+    /// Scalars for the \\(\mathbf{G}\\) generators, of length \\(\sum_j n_j\\)
+    g_scalars: Vec<Scalar>,
+    /// Scalars for the \\(\mathbf{H}\\) generators, of length \\(\sum_j n_j\\)
+    h_scalars: Vec<Scalar>,
+    /// Scalars for the points in `dynamic_points`
+    dynamic_scalars: Vec<Scalar>,
+    /// The proof's points and the value commitments, which are
+    /// `None` if they failed to decompress
+    dynamic_points: Vec<Option<RistrettoPoint>>,
+    /// The padded bitsizes of the parties, which determine the
+    /// \\(\mathbf{G}\\) and \\(\mathbf{H}\\) generators to use
+    bitsizes: Vec<usize>,
+}
+
+impl Serialize for RangeProof {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.to_bytes()[..])
+    }
+}
+
+impl<'de> Deserialize<'de> for RangeProof {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct RangeProofVisitor;
+
+        impl<'de> Visitor<'de> for RangeProofVisitor {
+            type Value = RangeProof;
+
+            fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                formatter.write_str("a valid RangeProof")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<RangeProof, E>
+            where
+                E: serde::de::Error,
+            {
+                RangeProof::from_bytes(v).map_err(serde::de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_bytes(RangeProofVisitor)
+    }
+}
+
+/// Compute
+/// \\[
+/// \delta(y,z) = (z - z^{2}) \langle \mathbf{1}, {\mathbf{y}}^{n \cdot m} \rangle - \sum_{j=0}^{m-1} z^{j+3} \cdot \langle \mathbf{1}, {\mathbf{2}}^{n \cdot m} \rangle
+/// \\]
+fn delta(bitsizes: &[usize], y: &Scalar, z: &Scalar) -> Scalar {
+    let sum_y = util::sum_of_powers(y, bitsizes.iter().sum());
+    let sum_z_2: Scalar = util::exp_iter(*z)
+        .zip(bitsizes.iter())
+        .map(|(exp_z, &n)| exp_z * util::sum_of_powers(&Scalar::from(2u64), n))
+        .sum();
+
+    (z - z * z) * sum_y - z * z * z * sum_z_2
+}
+
+/// Returns the offset \\(2^{n-1}\\) which shifts signed `n`-bit
+/// values into \\([0, 2^n)\\), for the bitsizes which fit an `i64`.
+fn signed_offset(n: usize) -> Result<u64, ProofError> {
+    if !util::is_valid_bitsize(n) || n > 64 {
+        return Err(ProofError::InvalidBitsize);
+    }
+    Ok(1 << (n - 1))
+}
+
+/// The number of points above which the verification multiscalar
+/// multiplication is split across threads.  Below this, the work saved
+/// is less than the cost of the split, since each chunk loses some of
+/// the benefit of Pippenger's algorithm.
+#[cfg(feature = "rayon")]
+const PARALLEL_MULTISCALAR_THRESHOLD: usize = 1024;
+
+/// Computes a variable-time multiscalar multiplication of the
+/// `points` by the `scalars`, or `None` if any of the points is `None`.
+#[cfg(not(feature = "rayon"))]
+fn optional_vartime_multiscalar_mul<I, J>(scalars: I, points: J) -> Option<RistrettoPoint>
+where
+    I: IntoIterator<Item = Scalar>,
+    J: IntoIterator<Item = Option<RistrettoPoint>>,
+{
+    RistrettoPoint::optional_multiscalar_mul(scalars, points)
+}
+
+/// Computes a variable-time multiscalar multiplication of the
+/// `points` by the `scalars`, or `None` if any of the points is `None`.
+///
+/// When there are more than [`PARALLEL_MULTISCALAR_THRESHOLD`] points,
+/// they are split into one chunk per thread, and the partial sums of
+/// the chunks are added together.
+#[cfg(feature = "rayon")]
+fn optional_vartime_multiscalar_mul<I, J>(scalars: I, points: J) -> Option<RistrettoPoint>
+where
+    I: IntoIterator<Item = Scalar>,
+    J: IntoIterator<Item = Option<RistrettoPoint>>,
+{
+    let scalars: Vec<Scalar> = scalars.into_iter().collect();
+    let points: Vec<Option<RistrettoPoint>> = points.into_iter().collect();
+
+    if points.len() <= PARALLEL_MULTISCALAR_THRESHOLD {
+        return RistrettoPoint::optional_multiscalar_mul(scalars, points);
+    }
+
+    let num_threads = rayon::current_num_threads();
+    let chunk_size = (points.len() + num_threads - 1) / num_threads;
+
+    scalars
+        .par_chunks(chunk_size)
+        .zip(points.par_chunks(chunk_size))
+        .map(|(s, P)| RistrettoPoint::optional_multiscalar_mul(s, P.iter().cloned()))
+        .sum()
+}
+
+/// Pads the `value_commitments` to `m` commitments with the identity,
+/// to match the commitments to zero added by the prover.
+fn pad_commitments(
+    value_commitments: &[CompressedRistretto],
+    m: usize,
+) -> Vec<CompressedRistretto> {
+    value_commitments
+        .iter()
+        .cloned()
+        .chain(iter::repeat(CompressedRistretto::identity()))
+        .take(m)
+        .collect()
+}
+
+/// Pads the per-party `bitsizes` with parties of the smallest
+/// bitsize, until their sum is a power of two.
+///
+/// When all of the bitsizes are equal, this pads the number of
+/// parties to the next power of two.
+fn pad_bitsizes(bitsizes: &[usize]) -> Result<Vec<usize>, ProofError> {
+    let padding = RangeProof::pad_count(bitsizes)?;
+    let min_n = *bitsizes.iter().min().unwrap();
+
+    Ok(bitsizes
+        .iter()
+        .cloned()
+        .chain(iter::repeat(min_n).take(padding))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use generators::PedersenGens;
+
+    #[test]
+    fn test_delta() {
+        let mut rng = rand::thread_rng();
+        let y = Scalar::random(&mut rng);
+        let z = Scalar::random(&mut rng);
+
+        // Choose n = 256 to ensure we overflow the group order during
+        // the computation, to check that that's done correctly
+        let n = 256;
+
+        // code copied from previous implementation
+        let z2 = z * z;
+        let z3 = z2 * z;
+        let mut power_g = Scalar::zero();
+        let mut exp_y = Scalar::one(); // start at y^0 = 1
+        let mut exp_2 = Scalar::one(); // start at 2^0 = 1
+        for _ in 0..n {
+            power_g += (z - z2) * exp_y - z3 * exp_2;
+
+            exp_y = exp_y * y; // y^i -> y^(i+1)
+            exp_2 = exp_2 + exp_2; // 2^i -> 2^(i+1)
+        }
+
+        assert_eq!(power_g, delta(&[n], &y, &z),);
+    }
+
+    #[test]
+    fn public_delta_matches_aggregated_formula() {
+        let mut rng = rand::thread_rng();
+        let y = Scalar::random(&mut rng);
+        let z = Scalar::random(&mut rng);
+        let (n, m) = (16, 4);
+
+        // (z - z^2) <1, y^(nm)> - sum_j z^(j+3) <1, 2^n>
+        let zz = z * z;
+        let expected = (z - zz) * util::sum_of_powers(&y, n * m)
+            - util::exp_iter(z)
+                .take(m)
+                .map(|z_j| zz * z * z_j * util::sum_of_powers(&Scalar::from(2u64), n))
+                .sum::<Scalar>();
+
+        assert_eq!(RangeProof::delta(&vec![n; m], &y, &z), Ok(expected));
+        // Three parties are padded to four, like by the prover.
+        assert_eq!(
+            RangeProof::delta(&vec![n; 3], &y, &z),
+            RangeProof::delta(&vec![n; 4], &y, &z)
+        );
+        assert_eq!(
+            RangeProof::delta(&[n, 7], &y, &z),
+            Err(ProofError::InvalidBitsize)
+        );
+    }
+
+    #[test]
+    fn proofs_from_forked_transcripts() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 1);
+
+        let mut prefix = Transcript::new(b"ForkedTranscriptTest");
+        prefix.append_message(b"header", b"shared header");
+
+        let fork = |i: u64| {
+            let mut transcript = prefix.clone();
+            transcript.append_u64(b"proof index", i);
+            transcript
+        };
+
+        let proofs: Vec<_> = (0..3)
+            .map(|i| {
+                RangeProof::prove_single(
+                    &bp_gens,
+                    &pc_gens,
+                    &mut fork(i),
+                    i,
+                    &Scalar::from(i + 1),
+                    32,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        for (i, (proof, V)) in proofs.iter().enumerate() {
+            for j in 0..3 {
+                let result = proof.verify_single(&bp_gens, &pc_gens, &mut fork(j), V, 32);
+                assert_eq!(result.is_ok(), i as u64 == j);
+            }
+        }
+    }
+
+    #[test]
+    fn t_commitment_matches_expected() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(64, 4);
+        let mut rng = rand::thread_rng();
+
+        // Three values, so that a padding party is added.
+        let bitsizes = [8, 32, 16];
+        let blindings: Vec<Scalar> = (0..3).map(|_| Scalar::random(&mut rng)).collect();
+        let mut transcript = Transcript::new(b"TCommitmentTest");
+        let (proof, commitments) = RangeProof::prove_multiple_variable(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &[255, 7, 1000],
+            &blindings,
+            &bitsizes,
+        )
+        .unwrap();
+        // The bitsizes are padded with another 8-bit party.
+        assert_eq!(proof.num_bits(), 64);
+
+        let mut transcript = Transcript::new(b"TCommitmentTest");
+        let challenges = proof
+            .challenges(&mut transcript, &commitments, &bitsizes)
+            .unwrap();
+        assert_eq!(
+            proof.t_commitment(&pc_gens),
+            proof
+                .expected_t_commitment(&pc_gens, &challenges, &commitments, &bitsizes)
+                .unwrap()
+        );
+
+        let mut wrong_challenges = challenges;
+        wrong_challenges.x = challenges.y;
+        assert_ne!(
+            proof.t_commitment(&pc_gens),
+            proof
+                .expected_t_commitment(&pc_gens, &wrong_challenges, &commitments, &bitsizes)
+                .unwrap()
+        );
+        assert_eq!(
+            proof.expected_t_commitment(&pc_gens, &challenges, &commitments[..2], &bitsizes),
+            Err(ProofError::WrongNumBitsizes)
+        );
+    }
+
+    #[test]
+    fn commitment_sum_weights_by_powers_of_z() {
+        let pc_gens = PedersenGens::default();
+        let z = Scalar::from(3u64);
+        let V: Vec<_> = (1..4u64)
+            .map(|v| pc_gens.commit(Scalar::from(v), Scalar::from(v + 10)))
+            .collect();
+        let compressed: Vec<_> = V.iter().map(|V_j| V_j.compress()).collect();
+
+        // 9 * V_0 + 27 * V_1 + 81 * V_2
+        let expected =
+            Scalar::from(9u64) * V[0] + Scalar::from(27u64) * V[1] + Scalar::from(81u64) * V[2];
+        assert_eq!(RangeProof::commitment_sum(&compressed, &z), Ok(expected));
+
+        let mut invalid = compressed.clone();
+        invalid[1] = CompressedRistretto([0xff; 32]);
+        assert_eq!(
+            RangeProof::commitment_sum(&invalid, &z),
+            Err(ProofError::InvalidPoint)
+        );
+    }
+
+    /// Given a bitsize `n`, test the following:
+    ///
+    /// 1. Generate `m` random values and create a proof they are all in range;
+    /// 2. Serialize to wire format;
+    /// 3. Deserialize from wire format;
+    /// 4. Verify the proof.
+    fn singleparty_create_and_verify_helper(n: usize, m: usize) {
+        // Split the test into two scopes, so that it's explicit what
+        // data is shared between the prover and the verifier.
+
+        // Use bincode for serialization
+        use bincode;
+
+        // Both prover and verifier have access to the generators and the proof
+        let max_bitsize = 64;
+        let max_parties = 8;
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(max_bitsize, max_parties);
+
+        // Prover's scope
+        let (proof_bytes, value_commitments) = {
+            use rand::Rng;
+            let mut rng = rand::thread_rng();
+
+            // 0. Create witness data
+            let (min, max) = (0u64, ((1u128 << n) - 1) as u64);
+            let values: Vec<u64> = (0..m).map(|_| rng.gen_range(min, max)).collect();
+            let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
+
+            // 1. Create the proof
+            let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+            let (proof, value_commitments) = RangeProof::prove_multiple(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &values,
+                &blindings,
+                n,
+            )
+            .unwrap();
+            assert_eq!(value_commitments.len(), m);
+
+            // 2. Return serialized proof and value commitments
+            (bincode::serialize(&proof).unwrap(), value_commitments)
+        };
+
+        // Verifier's scope
+        {
+            // 3. Deserialize
+            let proof: RangeProof = bincode::deserialize(&proof_bytes).unwrap();
+
+            // 4. Verify with the same customization label as above
+            let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+
+            assert!(proof
+                .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, n)
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn create_and_verify_n_32_m_1() {
+        singleparty_create_and_verify_helper(32, 1);
+    }
+
+    #[test]
+    fn create_and_verify_n_32_m_2() {
+        singleparty_create_and_verify_helper(32, 2);
+    }
+
+    #[test]
+    fn create_and_verify_n_32_m_4() {
+        singleparty_create_and_verify_helper(32, 4);
+    }
+
+    #[test]
+    fn create_and_verify_n_32_m_8() {
+        singleparty_create_and_verify_helper(32, 8);
+    }
+
+    #[test]
+    fn create_and_verify_n_32_m_3() {
+        singleparty_create_and_verify_helper(32, 3);
+    }
+
+    #[test]
+    fn create_and_verify_n_32_m_5() {
+        singleparty_create_and_verify_helper(32, 5);
+    }
+
+    #[test]
+    fn create_and_verify_n_64_m_1() {
+        singleparty_create_and_verify_helper(64, 1);
+    }
+
+    #[test]
+    fn create_and_verify_n_64_m_2() {
+        singleparty_create_and_verify_helper(64, 2);
+    }
+
+    #[test]
+    fn create_and_verify_n_64_m_4() {
+        singleparty_create_and_verify_helper(64, 4);
+    }
+
+    #[test]
+    fn create_and_verify_n_64_m_7() {
+        singleparty_create_and_verify_helper(64, 7);
+    }
+
+    #[test]
+    fn create_and_verify_n_64_m_8() {
+        singleparty_create_and_verify_helper(64, 8);
+    }
+
+    #[test]
+    fn create_and_verify_single() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(64, 1);
+        let blinding = Scalar::random(&mut rand::thread_rng());
+
+        let mut transcript = Transcript::new(b"SingleRangeProofTest");
+        let (proof, V) = RangeProof::prove_single(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            1037578891u64,
+            &blinding,
+            32,
+        )
+        .unwrap();
+        assert_eq!(V, pc_gens.commit(1037578891u64.into(), blinding).compress());
+        assert_eq!(V, pc_gens.commit_value(1037578891, &blinding));
+
+        let mut transcript = Transcript::new(b"SingleRangeProofTest");
+        assert!(proof
+            .verify_single(&bp_gens, &pc_gens, &mut transcript, &V, 32)
+            .is_ok());
+
+        // A different commitment must not verify against the same proof.
+        let other_V = pc_gens.commit(1037578892u64.into(), blinding).compress();
+        let mut transcript = Transcript::new(b"SingleRangeProofTest");
+        assert!(proof
+            .verify_single(&bp_gens, &pc_gens, &mut transcript, &other_V, 32)
+            .is_err());
+    }
+
+    fn variable_create_and_verify_helper(bitsizes: &[usize]) {
+        use bincode;
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(64, 8);
+
+        // Prover's scope
+        let (proof_bytes, value_commitments) = {
+            use rand::Rng;
+            let mut rng = rand::thread_rng();
+
+            let values: Vec<u64> = bitsizes
+                .iter()
+                .map(|&n| rng.gen_range(0, ((1u128 << n) - 1) as u64))
+                .collect();
+            let blindings: Vec<Scalar> =
+                bitsizes.iter().map(|_| Scalar::random(&mut rng)).collect();
+
+            let mut transcript = Transcript::new(b"VariableRangeProofTest");
+            let (proof, value_commitments) = RangeProof::prove_multiple_variable(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &values,
+                &blindings,
+                bitsizes,
+            )
+            .unwrap();
+            assert_eq!(value_commitments.len(), bitsizes.len());
+
+            (bincode::serialize(&proof).unwrap(), value_commitments)
+        };
+
+        // Verifier's scope
+        {
+            let proof: RangeProof = bincode::deserialize(&proof_bytes).unwrap();
+
+            let mut transcript = Transcript::new(b"VariableRangeProofTest");
+            assert!(proof
+                .verify_multiple_variable(
+                    &bp_gens,
+                    &pc_gens,
+                    &mut transcript,
+                    &value_commitments,
+                    bitsizes,
+                )
+                .is_ok());
+
+            // The proof doesn't verify against other bitsizes.
+            let mut wrong_bitsizes = bitsizes.to_vec();
+            wrong_bitsizes.reverse();
+            wrong_bitsizes[0] = if wrong_bitsizes[0] == 8 { 16 } else { 8 };
+            let mut transcript = Transcript::new(b"VariableRangeProofTest");
+            assert!(proof
+                .verify_multiple_variable(
+                    &bp_gens,
+                    &pc_gens,
+                    &mut transcript,
+                    &value_commitments,
+                    &wrong_bitsizes,
+                )
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn create_and_verify_variable_32_64() {
+        variable_create_and_verify_helper(&[32, 64]);
+    }
+
+    #[test]
+    fn create_and_verify_variable_8_64_16() {
+        variable_create_and_verify_helper(&[8, 64, 16]);
+    }
+
+    #[test]
+    fn create_and_verify_variable_64_8_8_16_32() {
+        variable_create_and_verify_helper(&[64, 8, 8, 16, 32]);
+    }
+
+    #[test]
+    fn create_and_verify_u128() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(128, 4);
+        let mut rng = rand::thread_rng();
+
+        // The extremes of the range, and a value just past the range
+        // of a u64, padded to four parties.
+        let values = [u128::max_value(), 1 << 64, 0];
+        let blindings: Vec<Scalar> = (0..3).map(|_| Scalar::random(&mut rng)).collect();
+
+        let mut transcript = Transcript::new(b"U128RangeProofTest");
+        let (proof, value_commitments) = RangeProof::prove_multiple_u128(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &values,
+            &blindings,
+            128,
+        )
+        .unwrap();
+        assert_eq!(proof.ipp_proof.rounds(), 9);
+
+        let mut transcript = Transcript::new(b"U128RangeProofTest");
+        assert!(proof
+            .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, 128)
+            .is_ok());
+
+        // The same commitments with the real values' bits proven in a
+        // narrower range don't verify.
+        let mut transcript = Transcript::new(b"U128RangeProofTest");
+        assert!(proof
+            .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, 64)
+            .is_err());
+    }
+
+    #[test]
+    fn u128_value_out_of_range_fails() {
+        use errors::MPCError;
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(128, 1);
+        let blinding = Scalar::from(3u64);
+
+        // Only the low 64 bits enter the bit commitments, so the
+        // z^2-weighted sum of the bits doesn't match the commitment.
+        let mut transcript = Transcript::new(b"U128RangeProofTest");
+        let result = RangeProof::prove_multiple_u128(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &[1 << 64],
+            &[blinding],
+            64,
+        );
+
+        // With `dealer-checks`, the dealer already rejects the share
+        // at proving time.
+        if cfg!(feature = "dealer-checks") {
+            assert_eq!(
+                result.unwrap_err(),
+                ProofError::ProvingError(MPCError::MalformedProofShares {
+                    bad_shares: vec![0]
+                })
+            );
+            return;
+        }
+
+        let (proof, Vs) = result.unwrap();
+        let mut transcript = Transcript::new(b"U128RangeProofTest");
+        assert!(proof
+            .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &Vs, 64)
+            .is_err());
+    }
+
+    #[test]
+    fn create_and_verify_variable_128_8_64() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(128, 16);
+        let bitsizes = [128, 8, 64];
+        let values = [u64::max_value(), 255, u64::max_value()];
+        let blindings = [Scalar::from(1u64), Scalar::from(2u64), Scalar::from(3u64)];
+
+        // The 200 bits are padded with seven 8-bit parties to 256.
+        let mut transcript = Transcript::new(b"VariableRangeProofTest");
+        let (proof, Vs) = RangeProof::prove_multiple_variable(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &values,
+            &blindings,
+            &bitsizes,
+        )
+        .unwrap();
+        assert_eq!(proof.ipp_proof.rounds(), 8);
+
+        let mut transcript = Transcript::new(b"VariableRangeProofTest");
+        assert!(proof
+            .verify_multiple_variable(&bp_gens, &pc_gens, &mut transcript, &Vs, &bitsizes)
+            .is_ok());
+
+        let mut transcript = Transcript::new(b"VariableRangeProofTest");
+        assert!(proof
+            .verify_multiple_variable(&bp_gens, &pc_gens, &mut transcript, &Vs, &[64, 8, 128])
+            .is_err());
+    }
+
+    #[test]
+    fn pad_count_matches_padded_aggregation() {
+        assert_eq!(RangeProof::pad_count(&[64, 64, 64, 64]), Ok(0));
+        assert_eq!(RangeProof::pad_count(&[32, 32, 32]), Ok(1));
+        assert_eq!(RangeProof::pad_count(&[128, 8, 64]), Ok(7));
+        assert_eq!(RangeProof::pad_count(&[]), Err(ProofError::InvalidAggregation));
+        assert_eq!(RangeProof::pad_count(&[64, 7]), Err(ProofError::InvalidBitsize));
+
+        for bitsizes in &[vec![32, 32, 32], vec![8, 64, 16]] {
+            let padded = pad_bitsizes(bitsizes).unwrap();
+            assert_eq!(
+                padded.len() - bitsizes.len(),
+                RangeProof::pad_count(bitsizes).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn equal_bitsizes_match_uniform_aggregation() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 4);
+        let values = [1u64, 2, 3];
+        let blindings: Vec<Scalar> = (0..3).map(|i| Scalar::from(i as u64 + 10)).collect();
+
+        let mut transcript = Transcript::new(b"VariableRangeProofTest");
+        let (proof, Vs) = RangeProof::prove_multiple_variable(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &values,
+            &blindings,
+            &[32, 32, 32],
+        )
+        .unwrap();
+
+        let mut transcript = Transcript::new(b"VariableRangeProofTest");
+        assert!(proof
+            .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &Vs, 32)
+            .is_ok());
+    }
+
+    #[test]
+    fn variable_rangeproof_rejects_wrong_num_bitsizes() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(64, 4);
+        let blindings = [Scalar::one(), Scalar::one()];
+
+        let mut transcript = Transcript::new(b"VariableRangeProofTest");
+        assert_eq!(
+            RangeProof::prove_multiple_variable(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &[1, 2],
+                &blindings,
+                &[8],
+            )
+            .unwrap_err(),
+            ProofError::WrongNumBitsizes
+        );
+
+        let mut transcript = Transcript::new(b"VariableRangeProofTest");
+        let (proof, Vs) = RangeProof::prove_multiple_variable(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &[1, 2],
+            &blindings,
+            &[8, 16],
+        )
+        .unwrap();
+
+        let mut transcript = Transcript::new(b"VariableRangeProofTest");
+        assert_eq!(
+            proof
+                .verify_multiple_variable(&bp_gens, &pc_gens, &mut transcript, &Vs, &[8, 16, 8])
+                .unwrap_err(),
+            ProofError::WrongNumBitsizes
+        );
+    }
+
+    #[test]
+    fn create_and_verify_bounded() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(16, 2);
+        let blinding = Scalar::from(99u64);
+        let (a, b) = (1000u64, 1000 + (1 << 16));
+
+        for &v in &[a, a + 1, 30000, b - 1] {
+            let mut transcript = Transcript::new(b"BoundedRangeProofTest");
+            let (proof, V) = RangeProof::prove_bounded(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                v,
+                &blinding,
+                a,
+                b,
+                16,
+            )
+            .unwrap();
+            assert!(pc_gens.verify_opening(&V, v, &blinding));
+
+            let mut transcript = Transcript::new(b"BoundedRangeProofTest");
+            assert!(proof
+                .verify_bounded(&bp_gens, &pc_gens, &mut transcript, &V, a, b, 16)
+                .is_ok());
+
+            // The proof doesn't verify for a narrower range.
+            let mut transcript = Transcript::new(b"BoundedRangeProofTest");
+            assert!(proof
+                .verify_bounded(&bp_gens, &pc_gens, &mut transcript, &V, a + 1, b - 1, 16)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn bounded_rejects_values_out_of_bounds() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(8, 2);
+        let blinding = Scalar::one();
+
+        for &(v, a, b) in &[(9, 10, 20), (20, 10, 20), (10, 10, 10), (10, 0, 257)] {
+            let mut transcript = Transcript::new(b"BoundedRangeProofTest");
+            assert_eq!(
+                RangeProof::prove_bounded(
+                    &bp_gens,
+                    &pc_gens,
+                    &mut transcript,
+                    v,
+                    &blinding,
+                    a,
+                    b,
+                    8,
+                )
+                .unwrap_err(),
+                ProofError::ValueOutOfBounds
+            );
+        }
+    }
+
+    #[test]
+    fn create_and_verify_difference() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 1);
+
+        let (a, a_blinding) = (100u64, Scalar::from(5u64));
+        let (b, b_blinding) = (40u64, Scalar::from(9u64));
+        let V_a = pc_gens.commit(Scalar::from(a), a_blinding).compress();
+        let V_b = pc_gens.commit(Scalar::from(b), b_blinding).compress();
+
+        let mut transcript = Transcript::new(b"DifferenceTest");
+        let proof = RangeProof::prove_difference(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            a,
+            &a_blinding,
+            b,
+            &b_blinding,
+            32,
+        )
+        .unwrap();
+
+        let mut transcript = Transcript::new(b"DifferenceTest");
+        assert!(proof
+            .verify_difference(&bp_gens, &pc_gens, &mut transcript, &V_a, &V_b, 32)
+            .is_ok());
+        let mut transcript = Transcript::new(b"DifferenceTest");
+        assert!(proof
+            .verify_difference(&bp_gens, &pc_gens, &mut transcript, &V_b, &V_a, 32)
+            .is_err());
+
+        // The difference must be non-negative and fit in the range.
+        let mut transcript = Transcript::new(b"DifferenceTest");
+        assert_eq!(
+            RangeProof::prove_difference(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                b,
+                &b_blinding,
+                a,
+                &a_blinding,
+                32,
+            )
+            .unwrap_err(),
+            ProofError::ValueOutOfBounds
+        );
+        assert_eq!(
+            RangeProof::prove_difference(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                1 << 32,
+                &a_blinding,
+                0,
+                &b_blinding,
+                32,
+            )
+            .unwrap_err(),
+            ProofError::ValueOutOfBounds
+        );
+    }
+
+    #[test]
+    fn recovered_point_is_opened_by_inner_product_proof() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(16, 4);
+        let bitsizes = [16, 8, 8];
+        let blindings = [Scalar::from(2u64), Scalar::from(3u64), Scalar::from(4u64)];
+
+        let mut transcript = Transcript::new(b"RecoverTest");
+        let (proof, Vs) = RangeProof::prove_multiple_variable(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &[1000, 7, 200],
+            &blindings,
+            &bitsizes,
+        )
+        .unwrap();
+
+        let mut transcript = Transcript::new(b"RecoverTest");
+        let P = proof
+            .verify_and_recover(&bp_gens, &pc_gens, &mut transcript, &Vs, &bitsizes)
+            .unwrap();
+
+        // Check P against the inner-product proof on its own.
+        let mut transcript = Transcript::new(b"RecoverTest");
+        let RangeProofChallenges { y, w, .. } =
+            proof.challenges(&mut transcript, &Vs, &bitsizes).unwrap();
+        let padded_bitsizes = pad_bitsizes(&bitsizes).unwrap();
+        let nm: usize = padded_bitsizes.iter().sum();
+        let G: Vec<RistrettoPoint> = bp_gens.G_variable(&padded_bitsizes).cloned().collect();
+        let H: Vec<RistrettoPoint> = bp_gens.H_variable(&padded_bitsizes).cloned().collect();
+        let Q = w * pc_gens.B;
+        assert!(proof
+            .ipp_proof
+            .verify(
+                nm,
+                &mut transcript,
+                util::exp_iter(y.invert()).take(nm),
+                &(P + proof.t_x * Q),
+                &Q,
+                &G,
+                &H,
+            )
+            .is_ok());
+
+        // Nothing is recovered from a proof which fails to verify.
+        let mut transcript = Transcript::new(b"RecoverTest");
+        assert!(proof
+            .verify_and_recover(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &Vs[..2],
+                &bitsizes[..2]
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn seeded_rng_reproduces_blinding_factors() {
+        use self::messages::{BitChallenge, PolyChallenge};
+        use self::party::*;
+        use rand_chacha::ChaChaRng;
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 1);
+
+        // A party's A and S, and the blinding factors of its share,
+        // only depend on the seed of its RNG.
+        let share = |seed: u8| {
+            let mut rng = ChaChaRng::from_seed([seed; 32]);
+            let (party, bit_commitment) =
+                Party::new(&bp_gens, &pc_gens, 7, Scalar::from(3u64), 32)
+                    .unwrap()
+                    .assign_position_with_rng(0, &mut rng)
+                    .unwrap();
+            let bit_challenge = BitChallenge {
+                y: Scalar::from(5u64),
+                z: Scalar::from(6u64),
+            };
+            let (party, _) = party.apply_challenge_with_rng(&bit_challenge, &mut rng);
+            let poly_challenge = PolyChallenge {
+                x: Scalar::from(8u64),
+            };
+            let share = party.apply_challenge(&poly_challenge).unwrap();
+            (
+                bit_commitment.A_j,
+                bit_commitment.S_j,
+                share.t_x_blinding,
+                share.e_blinding,
+            )
+        };
+        assert_eq!(share(1), share(1));
+        assert!(share(1) != share(2));
+
+        // The same holds for the proofs created by a single prover.
+        let prove = |seed: u8| {
+            let mut transcript = Transcript::new(b"SeededRngTest");
+            RangeProof::prove_single_with_rng(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                7,
+                &Scalar::from(3u64),
+                32,
+                &mut ChaChaRng::from_seed([seed; 32]),
+            )
+            .unwrap()
+            .0
+        };
+        let (proof, again, other) = (prove(1), prove(1), prove(2));
+        assert_eq!((proof.A, proof.S), (again.A, again.S));
+        assert_eq!(
+            (proof.t_x_blinding, proof.e_blinding),
+            (again.t_x_blinding, again.e_blinding)
+        );
+        assert!(proof.A != other.A && proof.S != other.S);
+        assert!(proof.e_blinding != other.e_blinding);
+    }
+
+    #[test]
+    fn create_and_verify_signed() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(64, 1);
+        let blinding = Scalar::from(11u64);
+
+        for &(v, n) in &[(-128i64, 8), (-1, 8), (0, 8), (127, 8), (-5, 32)] {
+            let mut transcript = Transcript::new(b"SignedTest");
+            let (proof, V) =
+                RangeProof::prove_signed(&bp_gens, &pc_gens, &mut transcript, v, &blinding, n)
+                    .unwrap();
+
+            let mut transcript = Transcript::new(b"SignedTest");
+            assert!(proof
+                .verify_signed(&bp_gens, &pc_gens, &mut transcript, &V, n)
+                .is_ok());
+        }
+
+        for &v in &[i64::min_value(), i64::max_value()] {
+            let mut transcript = Transcript::new(b"SignedTest");
+            let (proof, V) =
+                RangeProof::prove_signed(&bp_gens, &pc_gens, &mut transcript, v, &blinding, 64)
+                    .unwrap();
+
+            let mut transcript = Transcript::new(b"SignedTest");
+            assert!(proof
+                .verify_signed(&bp_gens, &pc_gens, &mut transcript, &V, 64)
+                .is_ok());
+        }
+
+        // The commitment is to the signed value, not the shifted one.
+        let mut transcript = Transcript::new(b"SignedTest");
+        let (proof, V) =
+            RangeProof::prove_signed(&bp_gens, &pc_gens, &mut transcript, -3, &blinding, 16)
+                .unwrap();
+        assert_eq!(V, pc_gens.commit(-Scalar::from(3u64), blinding).compress());
+        let mut transcript = Transcript::new(b"SignedTest");
+        let V_three = pc_gens.commit_value(3, &blinding);
+        assert!(proof
+            .verify_signed(&bp_gens, &pc_gens, &mut transcript, &V_three, 16)
+            .is_err());
+
+        for &v in &[128i64, -129] {
+            let mut transcript = Transcript::new(b"SignedTest");
+            assert_eq!(
+                RangeProof::prove_signed(&bp_gens, &pc_gens, &mut transcript, v, &blinding, 8)
+                    .unwrap_err(),
+                ProofError::ValueOutOfBounds
+            );
+        }
+        let mut transcript = Transcript::new(b"SignedTest");
+        assert_eq!(
+            RangeProof::prove_signed(&bp_gens, &pc_gens, &mut transcript, 0, &blinding, 128)
+                .unwrap_err(),
+            ProofError::InvalidBitsize
+        );
+    }
+
+    #[test]
+    fn create_and_verify_bit() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(8, 2);
+        let blinding = Scalar::from(7u64);
+
+        for &v in &[0u64, 1] {
+            let mut transcript = Transcript::new(b"BitTest");
+            let (proof, V) =
+                RangeProof::prove_bit(&bp_gens, &pc_gens, &mut transcript, v, &blinding).unwrap();
+
+            let mut transcript = Transcript::new(b"BitTest");
+            assert!(proof
+                .verify_bit(&bp_gens, &pc_gens, &mut transcript, &V)
+                .is_ok());
+
+            // The proof doesn't verify against a commitment to 2.
+            let V_two = pc_gens.commit(Scalar::from(2u64), blinding).compress();
+            let mut transcript = Transcript::new(b"BitTest");
+            assert!(proof
+                .verify_bit(&bp_gens, &pc_gens, &mut transcript, &V_two)
+                .is_err());
+        }
+
+        let mut transcript = Transcript::new(b"BitTest");
+        assert_eq!(
+            RangeProof::prove_bit(&bp_gens, &pc_gens, &mut transcript, 2, &blinding).unwrap_err(),
+            ProofError::ValueOutOfBounds
+        );
+    }
+
+    #[test]
+    fn create_and_verify_sum() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 1);
+
+        let values = [100u64, 40, 7];
+        let blindings = [Scalar::from(5u64), Scalar::from(9u64), Scalar::from(2u64)];
+        let Vs: Vec<_> = values
+            .iter()
+            .zip(blindings.iter())
+            .map(|(&v, &v_blinding)| pc_gens.commit(Scalar::from(v), v_blinding).compress())
+            .collect();
+
+        let mut transcript = Transcript::new(b"SumTest");
+        let proof =
+            RangeProof::prove_sum(&bp_gens, &pc_gens, &mut transcript, &values, &blindings, 32)
+                .unwrap();
+
+        let mut transcript = Transcript::new(b"SumTest");
+        assert!(proof
+            .verify_sum(&bp_gens, &pc_gens, &mut transcript, &Vs, 32)
+            .is_ok());
+        let mut transcript = Transcript::new(b"SumTest");
+        assert!(proof
+            .verify_sum(&bp_gens, &pc_gens, &mut transcript, &Vs[..2], 32)
+            .is_err());
+    }
+
+    #[test]
+    fn checked_value_sum_detects_overflow() {
+        assert_eq!(RangeProof::checked_value_sum(&[], 8), Ok(0));
+        assert_eq!(RangeProof::checked_value_sum(&[200, 55], 8), Ok(255));
+        assert_eq!(
+            RangeProof::checked_value_sum(&[200, 56], 8),
+            Err(ProofError::ValueOverflow)
+        );
+        assert_eq!(
+            RangeProof::checked_value_sum(&[u64::max_value(), 1], 64),
+            Err(ProofError::ValueOverflow)
+        );
+        assert_eq!(
+            RangeProof::checked_value_sum(&[u64::max_value(), 1], 128),
+            Ok(1 << 64)
+        );
+        assert_eq!(
+            RangeProof::checked_value_sum(&[1], 12),
+            Err(ProofError::InvalidBitsize)
+        );
+
+        // A wrapped sum would produce a proof which fails to verify,
+        // so the prover refuses to create it.
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 1);
+        let mut transcript = Transcript::new(b"SumTest");
+        assert_eq!(
+            RangeProof::prove_sum(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &[1 << 31, 1 << 31],
+                &[Scalar::one(), Scalar::one()],
+                32,
+            )
+            .unwrap_err(),
+            ProofError::ValueOverflow
+        );
+    }
+
+    #[test]
+    fn sum_beyond_u64_is_proved_in_128_bit_range() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(128, 1);
+
+        let values = [u64::max_value(), u64::max_value()];
+        let blindings = [Scalar::from(5u64), Scalar::from(9u64)];
+        let Vs: Vec<_> = values
+            .iter()
+            .zip(blindings.iter())
+            .map(|(&v, v_blinding)| pc_gens.commit_value(v, v_blinding))
+            .collect();
+
+        let mut transcript = Transcript::new(b"SumTest");
+        let proof =
+            RangeProof::prove_sum(&bp_gens, &pc_gens, &mut transcript, &values, &blindings, 128)
+                .unwrap();
+
+        let mut transcript = Transcript::new(b"SumTest");
+        assert!(proof
+            .verify_sum(&bp_gens, &pc_gens, &mut transcript, &Vs, 128)
+            .is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "diagnostics")]
+    fn diagnostics_report_failure_reason() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 1);
+        let blinding = Scalar::from(7u64);
+
+        let mut transcript = Transcript::new(b"DiagnosticsTest");
+        let (proof, V) =
+            RangeProof::prove_single(&bp_gens, &pc_gens, &mut transcript, 42, &blinding, 32)
+                .unwrap();
+
+        let diagnose = |proof: &RangeProof, V: &CompressedRistretto| {
+            let mut transcript = Transcript::new(b"DiagnosticsTest");
+            proof.diagnose_multiple_variable(&bp_gens, &pc_gens, &mut transcript, &[*V], &[32])
+        };
+        let verify = |proof: &RangeProof, V: &CompressedRistretto| {
+            let mut transcript = Transcript::new(b"DiagnosticsTest");
+            proof.verify_single(&bp_gens, &pc_gens, &mut transcript, V, 32)
+        };
+        assert_eq!(diagnose(&proof, &V), Ok(None));
+
+        // A wrong evaluation of t(x) breaks the polynomial identity.
+        let mut bad_proof = proof.clone();
+        bad_proof.t_x += Scalar::one();
+        assert_eq!(
+            diagnose(&bad_proof, &V),
+            Ok(Some(VerifyFailureReason::PolynomialIdentity))
+        );
+        assert_eq!(verify(&bad_proof, &V), Err(ProofError::VerificationError));
+
+        // The blinding factor of A and S only enters the inner-product
+        // relation.
+        let mut bad_proof = proof.clone();
+        bad_proof.e_blinding += Scalar::one();
+        assert_eq!(
+            diagnose(&bad_proof, &V),
+            Ok(Some(VerifyFailureReason::InnerProductRelation))
+        );
+        assert_eq!(verify(&bad_proof, &V), Err(ProofError::VerificationError));
+
+        let bad_V = CompressedRistretto([0xff; 32]);
+        assert_eq!(
+            diagnose(&proof, &bad_V),
+            Ok(Some(VerifyFailureReason::PointDecompression))
+        );
+        assert_eq!(verify(&proof, &bad_V), Err(ProofError::VerificationError));
+
+        // Errors other than a failed verification are passed through.
+        let mut transcript = Transcript::new(b"DiagnosticsTest");
+        assert_eq!(
+            proof.diagnose_multiple_variable(&bp_gens, &pc_gens, &mut transcript, &[V], &[]),
+            Err(ProofError::WrongNumBitsizes)
+        );
+    }
+
+    #[test]
+    fn custom_pedersen_bases() {
+        use sha3::Sha3_512;
+
+        let pc_gens = PedersenGens {
+            B: RistrettoPoint::hash_from_bytes::<Sha3_512>(b"custom value base"),
+            B_blinding: RistrettoPoint::hash_from_bytes::<Sha3_512>(b"custom blinding base"),
+        };
+        let bp_gens = BulletproofGens::new(32, 1);
+        let blinding = Scalar::from(17u64);
+
+        let mut transcript = Transcript::new(b"CustomBasesTest");
+        let (proof, V) =
+            RangeProof::prove_single(&bp_gens, &pc_gens, &mut transcript, 1037, &blinding, 32)
+                .unwrap();
+        assert!(pc_gens.verify_opening(&V, 1037, &blinding));
+
+        let mut transcript = Transcript::new(b"CustomBasesTest");
+        assert!(proof
+            .verify_single(&bp_gens, &pc_gens, &mut transcript, &V, 32)
+            .is_ok());
+
+        // The bases are part of the statement, so the proof doesn't
+        // verify under the default ones.
+        let mut transcript = Transcript::new(b"CustomBasesTest");
+        assert!(proof
+            .verify_single(&bp_gens, &PedersenGens::default(), &mut transcript, &V, 32)
+            .is_err());
+    }
+
+    #[test]
+    fn create_with_rng_is_deterministic() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(64, 4);
+        let values = [1u64, 2, 3];
+        let blindings: Vec<Scalar> = (0..3).map(|i| Scalar::from(i as u64 + 10)).collect();
+
+        let prove = |seed: u8| {
+            let mut transcript = Transcript::new(b"RangeProofRngTest");
+            RangeProof::prove_multiple_with_rng(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &values,
+                &blindings,
+                32,
+                &mut ChaChaRng::from_seed([seed; 32]),
+            )
+            .unwrap()
+        };
+
+        let (proof_a, Vs) = prove(7);
+        let (proof_b, _) = prove(7);
+        let (proof_c, _) = prove(8);
+        assert_eq!(proof_a.to_bytes(), proof_b.to_bytes());
+        assert!(proof_a.to_bytes() != proof_c.to_bytes());
+
+        let mut transcript = Transcript::new(b"RangeProofRngTest");
+        assert!(proof_a
+            .verify_multiple_with_rng(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &Vs,
+                32,
+                &mut ChaChaRng::from_seed([9; 32]),
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn verify_with_precomputed_gens() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(64, 4);
+        let precomputed = bp_gens.precompute(&pc_gens, 32, 4).unwrap();
+        let mut rng = rand::thread_rng();
+
+        for m in 1..5 {
+            let values: Vec<u64> = (0..m).map(|v| v as u64).collect();
+            let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
+
+            let mut transcript = Transcript::new(b"PrecomputedGensTest");
+            let (proof, mut value_commitments) = RangeProof::prove_multiple(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &values,
+                &blindings,
+                32,
+            )
+            .unwrap();
+
+            let mut transcript = Transcript::new(b"PrecomputedGensTest");
+            assert!(proof
+                .verify_multiple_precomputed(&precomputed, &mut transcript, &value_commitments, 32)
+                .is_ok());
+
+            // The precomputation is only valid for its own bitsize.
+            let mut transcript = Transcript::new(b"PrecomputedGensTest");
+            assert_eq!(
+                proof.verify_multiple_precomputed(
+                    &precomputed,
+                    &mut transcript,
+                    &value_commitments,
+                    16
+                ),
+                Err(ProofError::InvalidGeneratorsLength)
+            );
+
+            value_commitments[0] = pc_gens
+                .commit(Scalar::from(100u64), blindings[0])
+                .compress();
+            let mut transcript = Transcript::new(b"PrecomputedGensTest");
+            assert!(proof
+                .verify_multiple_precomputed(&precomputed, &mut transcript, &value_commitments, 32)
+                .is_err());
+        }
+
+        assert!(bp_gens.precompute(&pc_gens, 32, 8).is_err());
+    }
+
+    #[test]
+    fn batch_verify_detects_invalid_proofs() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 4);
+        let mut rng = rand::thread_rng();
+
+        // Proofs with aggregation sizes 1, 4, 2, all over 32-bit ranges.
+        let mut proofs: Vec<(RangeProof, Vec<CompressedRistretto>)> = [1, 4, 2]
+            .iter()
+            .map(|&m| {
+                let values: Vec<u64> = (0..m).map(|v| v as u64).collect();
+                let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
+                let mut transcript = Transcript::new(b"BatchVerificationTest");
+                RangeProof::prove_multiple(
+                    &bp_gens,
+                    &pc_gens,
+                    &mut transcript,
+                    &values,
+                    &blindings,
+                    32,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        let transcripts = || vec![Transcript::new(b"BatchVerificationTest"); 3];
+
+        assert!(
+            RangeProof::batch_verify(&bp_gens, &pc_gens, &mut transcripts(), &proofs, 32).is_ok()
+        );
+
+        // Swap two of the commitments of the second proof.
+        proofs[1].1.swap(0, 1);
+        assert_eq!(
+            RangeProof::batch_verify(&bp_gens, &pc_gens, &mut transcripts(), &proofs, 32),
+            Err(ProofError::BatchVerificationError {
+                bad_proofs: vec![1]
+            })
+        );
+
+        assert_eq!(
+            RangeProof::batch_verify(&bp_gens, &pc_gens, &mut transcripts()[..2], &proofs, 32),
+            Err(ProofError::WrongNumTranscripts)
+        );
+    }
+
+    #[test]
+    fn consttime_verification_matches_vartime() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 4);
+        let mut rng = rand::thread_rng();
+
+        let values = [1u64, 2, 3];
+        let blindings: Vec<Scalar> = (0..3).map(|_| Scalar::random(&mut rng)).collect();
+        let mut transcript = Transcript::new(b"ConstantTimeTest");
+        let (proof, mut commitments) = RangeProof::prove_multiple(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &values,
+            &blindings,
+            32,
+        )
+        .unwrap();
+
+        let mut transcript = Transcript::new(b"ConstantTimeTest");
+        assert!(proof
+            .verify_multiple_consttime(&bp_gens, &pc_gens, &mut transcript, &commitments, 32)
+            .is_ok());
+
+        commitments.swap(0, 1);
+        let mut transcript = Transcript::new(b"ConstantTimeTest");
+        assert_eq!(
+            proof.verify_multiple_consttime(&bp_gens, &pc_gens, &mut transcript, &commitments, 32),
+            Err(ProofError::VerificationError)
+        );
+        let mut transcript = Transcript::new(b"ConstantTimeTest");
+        assert_eq!(
+            proof.verify_multiple(&bp_gens, &pc_gens, &mut transcript, &commitments, 32),
+            Err(ProofError::VerificationError)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn parallel_multiscalar_matches_serial() {
+        use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+
+        let mut rng = rand::thread_rng();
+        let len = 2 * PARALLEL_MULTISCALAR_THRESHOLD + 3;
+        let scalars: Vec<Scalar> = (0..len).map(|_| Scalar::random(&mut rng)).collect();
+        let mut points: Vec<Option<RistrettoPoint>> = (0..len)
+            .map(|_| Some(RISTRETTO_BASEPOINT_POINT * Scalar::random(&mut rng)))
+            .collect();
+
+        assert_eq!(
+            optional_vartime_multiscalar_mul(scalars.clone(), points.clone()),
+            RistrettoPoint::optional_multiscalar_mul(&scalars, points.clone())
+        );
+
+        // A point which failed to decompress fails the whole check,
+        // whichever chunk it is in.
+        points[len - 1] = None;
+        assert_eq!(optional_vartime_multiscalar_mul(scalars, points), None);
+    }
+
+    #[cfg(feature = "hex")]
+    #[test]
+    fn hex_roundtrip() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(8, 1);
+        let mut transcript = Transcript::new(b"HexTest");
+        let (proof, _) = RangeProof::prove_single(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            3,
+            &Scalar::from(4u64),
+            8,
+        )
+        .unwrap();
+
+        let hex = proof.to_hex();
+        assert_eq!(hex.len(), 2 * RangeProof::proof_size(8, 1).unwrap());
+        assert_eq!(
+            RangeProof::from_hex(&hex).unwrap().to_bytes(),
+            proof.to_bytes()
+        );
+        assert_eq!(
+            RangeProof::from_hex(&hex.to_uppercase())
+                .unwrap()
+                .to_bytes(),
+            proof.to_bytes()
+        );
+
+        assert_eq!(
+            RangeProof::from_hex(&hex[1..]).unwrap_err(),
+            ProofError::FormatError
+        );
+        assert_eq!(
+            RangeProof::from_hex("not hex").unwrap_err(),
+            ProofError::FormatError
+        );
+    }
+
+    #[test]
+    fn verifier_context_is_reused_across_proofs() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(16, 4);
+        let context = VerifierContext::new(16, 3).unwrap();
+        assert_eq!(context.bitsizes(), &[16, 16, 16][..]);
+
+        for i in 0..3u64 {
+            let values = [i, i + 1, i + 2];
+            let blindings: Vec<Scalar> = (0..3).map(|j| Scalar::from(i + j + 1)).collect();
+            let mut transcript = Transcript::new(b"VerifierContextTest");
+            let (proof, Vs) = RangeProof::prove_multiple(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &values,
+                &blindings,
+                16,
+            )
+            .unwrap();
+
+            let mut transcript = Transcript::new(b"VerifierContextTest");
+            assert!(proof
+                .verify_with_context(&bp_gens, &pc_gens, &mut transcript, &Vs, &context)
+                .is_ok());
+
+            let mut transcript = Transcript::new(b"VerifierContextTest");
+            assert_eq!(
+                proof.verify_with_context(&bp_gens, &pc_gens, &mut transcript, &Vs[..2], &context),
+                Err(ProofError::WrongNumBitsizes)
+            );
+        }
+
+        // The context computes the same delta as the free function.
+        let (y, z) = (Scalar::from(3u64), Scalar::from(5u64));
+        let context = VerifierContext::new_variable(&[8, 64, 16]).unwrap();
+        assert_eq!(
+            context.delta(&y, &z),
+            RangeProof::delta(&[8, 64, 16], &y, &z).unwrap()
+        );
+        assert!(VerifierContext::new(7, 1).is_err());
+    }
+
+    #[test]
+    fn verification_cost_counts_multiscalar_points() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 4);
+        let values = [1u64, 2, 3];
+        let blindings = vec![Scalar::one(); 3];
+
+        let mut transcript = Transcript::new(b"VerificationCostTest");
+        let (proof, Vs) = RangeProof::prove_multiple(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &values,
+            &blindings,
+            32,
+        )
+        .unwrap();
+
+        // The value commitments are padded to four.
+        let mut rng = rand::thread_rng();
+        let mut transcript = Transcript::new(b"VerificationCostTest");
+        let eq = proof
+            .verification_equation(&mut transcript, &Vs, &[32; 3], 32, 4, &mut rng)
+            .unwrap();
+        assert_eq!(proof.verification_cost(), 2 * 128 + 2 * 7 + 6);
+        assert_eq!(
+            proof.verification_cost() + 4,
+            2 + eq.g_scalars.len() + eq.h_scalars.len() + eq.dynamic_points.len()
+        );
+    }
+
+    #[test]
+    fn proof_size_matches_serialization() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(64, 8);
+
+        for &(n, m) in &[(8, 1), (32, 3), (64, 5), (64, 8)] {
+            let values = vec![0u64; m];
+            let blindings = vec![Scalar::one(); m];
+            let mut transcript = Transcript::new(b"ProofSizeTest");
+            let (proof, _) = RangeProof::prove_multiple(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &values,
+                &blindings,
+                n,
+            )
+            .unwrap();
+
+            assert_eq!(proof.to_bytes().len(), RangeProof::proof_size(n, m).unwrap());
+        }
+
+        assert_eq!(RangeProof::proof_size(0, 1), Err(ProofError::InvalidBitsize));
+        assert_eq!(RangeProof::proof_size(12, 1), Err(ProofError::InvalidBitsize));
+        assert_eq!(RangeProof::proof_size(8, 0), Err(ProofError::InvalidAggregation));
+        assert_eq!(
+            RangeProof::proof_size(64, usize::max_value()),
+            Err(ProofError::InvalidAggregation)
+        );
+    }
+
+    #[test]
+    fn fingerprint_identifies_proof() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 2);
+
+        let mut transcript = Transcript::new(b"FingerprintTest");
+        let (proof, _) = RangeProof::prove_multiple(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &[3, 4],
+            &[Scalar::one(), Scalar::one()],
+            32,
+        )
+        .unwrap();
+
+        let decoded = RangeProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(proof.fingerprint(), decoded.fingerprint());
+
+        let mut bad_proof = proof.clone();
+        bad_proof.A = bad_proof.S;
+        assert_ne!(proof.fingerprint(), bad_proof.fingerprint());
+
+        let mut bad_proof = proof.clone();
+        bad_proof.ipp_proof.a += Scalar::one();
+        assert_ne!(proof.fingerprint(), bad_proof.fingerprint());
+    }
+
+    #[test]
+    fn framed_proofs_roundtrip_over_a_stream() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 4);
+
+        let mut transcript = Transcript::new(b"FramedTest");
+        let (proof_a, _) = RangeProof::prove_single(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            3,
+            &Scalar::one(),
+            16,
+        )
+        .unwrap();
+        let mut transcript = Transcript::new(b"FramedTest");
+        let (proof_b, _) = RangeProof::prove_multiple(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &[3, 4, 5],
+            &[Scalar::one(), Scalar::one(), Scalar::one()],
+            32,
+        )
+        .unwrap();
+
+        let mut stream = Vec::new();
+        proof_a.write_framed(&mut stream, 16, 1).unwrap();
+        proof_b.write_framed(&mut stream, 32, 3).unwrap();
+        assert_eq!(
+            stream.len(),
+            24 + RangeProof::proof_size(16, 1).unwrap() + RangeProof::proof_size(32, 3).unwrap()
+        );
+
+        let mut r = &stream[..];
+        let (decoded_a, n, m) = RangeProof::read_framed(&mut r).unwrap();
+        assert_eq!((decoded_a.to_bytes(), n, m), (proof_a.to_bytes(), 16, 1));
+        let (decoded_b, n, m) = RangeProof::read_framed(&mut r).unwrap();
+        assert_eq!((decoded_b.to_bytes(), n, m), (proof_b.to_bytes(), 32, 3));
+        assert!(r.is_empty());
+
+        // The header must describe the proof.
+        assert_eq!(
+            proof_a.write_framed(&mut Vec::new(), 32, 1).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+
+        // An aggregation too large for the header is rejected rather
+        // than truncated.
+        #[cfg(target_pointer_width = "64")]
+        {
+            let mut huge = proof_a.clone();
+            for _ in 0..32 {
+                huge.ipp_proof.L_vec.push(huge.ipp_proof.L_vec[0]);
+                huge.ipp_proof.R_vec.push(huge.ipp_proof.R_vec[0]);
+            }
+            assert_eq!(
+                huge.write_framed(&mut Vec::new(), 16, 1 << 32).unwrap_err().kind(),
+                io::ErrorKind::InvalidInput
+            );
+        }
+
+        // An absurd length prefix is rejected before allocating.
+        let mut bad_stream = stream.clone();
+        bad_stream[..4].copy_from_slice(&[0xff; 4]);
+        assert_eq!(
+            RangeProof::read_framed(&mut &bad_stream[..]).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        // A truncated stream fails to read.
+        assert_eq!(
+            RangeProof::read_framed(&mut &stream[..100]).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn check_wellformed_rejects_malformed_proofs() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 2);
+
+        let mut transcript = Transcript::new(b"WellformedTest");
+        let (proof, _) = RangeProof::prove_multiple(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &[3, 4],
+            &[Scalar::one(), Scalar::one()],
+            32,
+        )
+        .unwrap();
+        assert_eq!(proof.check_wellformed(), Ok(()));
+
+        let mut bad_proof = proof.clone();
+        bad_proof.ipp_proof.R_vec.pop();
+        assert_eq!(bad_proof.check_wellformed(), Err(ProofError::FormatError));
+
+        let mut bad_proof = proof.clone();
+        bad_proof.ipp_proof.L_vec.truncate(2);
+        bad_proof.ipp_proof.R_vec.truncate(2);
+        assert_eq!(bad_proof.check_wellformed(), Err(ProofError::FormatError));
+
+        let mut bad_proof = proof.clone();
+        bad_proof.T_1 = CompressedRistretto::identity();
+        assert_eq!(
+            bad_proof.check_wellformed(),
+            Err(ProofError::VerificationError)
+        );
+
+        // Find an encoding which is not a valid point.
+        let invalid = (0u8..)
+            .map(|i| CompressedRistretto([i; 32]))
+            .find(|P| P.decompress().is_none())
+            .unwrap();
+        let mut bad_proof = proof.clone();
+        bad_proof.ipp_proof.L_vec[1] = invalid;
+        assert_eq!(bad_proof.check_wellformed(), Err(ProofError::InvalidPoint));
+    }
+
+    #[test]
+    fn proofs_compare_equal_after_roundtrip() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(16, 1);
+
+        let mut transcript = Transcript::new(b"EqualityTest");
+        let (proof, _) = RangeProof::prove_single(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            12,
+            &Scalar::one(),
+            16,
+        )
+        .unwrap();
+
+        assert_eq!(RangeProof::from_bytes(&proof.to_bytes()).unwrap(), proof);
+
+        let mut bad_proof = proof.clone();
+        bad_proof.ipp_proof.b += Scalar::one();
+        assert!(bad_proof != proof);
+    }
+
+    #[test]
+    fn arbitrary_bytes_never_panic() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(8, 1);
+        let V = pc_gens.commit(Scalar::zero(), Scalar::one()).compress();
+
+        // Parsing arbitrary input may fail, and verifying whatever
+        // parses must fail, but neither may panic.
+        for len in 0..32 {
+            let mut random = vec![0u8; len * 32];
+            rng.fill(&mut random[..]);
+
+            for bytes in &[vec![0u8; len * 32], random] {
+                if let Ok(proof) = RangeProof::from_bytes(&bytes) {
+                    let mut transcript = Transcript::new(b"FuzzTest");
+                    assert!(proof
+                        .verify_single(&bp_gens, &pc_gens, &mut transcript, &V, 8)
+                        .is_err());
+                }
+                assert!(RangeProof::from_bytes(&bytes[..bytes.len().saturating_sub(1)]).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn serialized_proof_has_exact_length() {
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(64, 4);
+        let mut rng = rand::thread_rng();
+
+        let blindings: Vec<Scalar> = (0..4).map(|_| Scalar::random(&mut rng)).collect();
+        let mut transcript = Transcript::new(b"SerializationTest");
+        let (proof, _) = RangeProof::prove_multiple(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &[1, 2, 3, 4],
+            &blindings,
+            16,
+        )
+        .unwrap();
+
+        // n * m = 64, so the inner product proof has lg(64) = 6 rounds.
+        let bytes = proof.to_bytes();
+        assert_eq!(bytes.len(), (2 * 6 + 9) * 32);
+        assert_eq!(bytes.len(), RangeProof::proof_size(16, 4).unwrap());
+        assert_eq!(proof.num_bits(), 64);
+        assert!(RangeProof::from_bytes(&bytes).is_ok());
+
+        // Truncated or extended encodings must be rejected, not misparsed.
+        assert_eq!(
+            RangeProof::from_bytes(&bytes[..bytes.len() - 32]).unwrap_err(),
+            ProofError::FormatError
+        );
+        assert_eq!(
+            RangeProof::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(),
+            ProofError::FormatError
+        );
+        let mut extended = bytes.clone();
+        extended.extend_from_slice(&[0u8; 32]);
+        assert_eq!(
+            RangeProof::from_bytes(&extended).unwrap_err(),
+            ProofError::FormatError
+        );
+        assert_eq!(
+            RangeProof::from_bytes(&bytes[..6 * 32]).unwrap_err(),
+            ProofError::FormatError
+        );
+
+        // Points that fail to decompress are caught while parsing,
+        // both in the rangeproof and in its inner-product proof.
+        for point in &[0, 3, 7, 8] {
+            let mut bad = bytes.clone();
+            bad[point * 32..(point + 1) * 32].copy_from_slice(&[0xff; 32]);
+            assert_eq!(
+                RangeProof::from_bytes(&bad).unwrap_err(),
+                ProofError::InvalidPoint
+            );
+        }
+    }
+
+    #[test]
+    fn honest_parties_pass_dealer_validation() {
+        use self::dealer::*;
+        use self::party::*;
+
+        use errors::MPCError;
+
+        let m = 4;
+        let n = 32;
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, m);
+
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+
+        let values: Vec<u64> = (0..m).map(|_| rng.gen::<u32>() as u64).collect();
+        let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
+
+        let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+
+        let (parties, bit_commitments): (Vec<_>, Vec<_>) = values
+            .iter()
+            .zip(blindings.iter())
+            .enumerate()
+            .map(|(j, (&v, &v_blinding))| {
+                Party::new(&bp_gens, &pc_gens, v, v_blinding, n)
+                    .unwrap()
+                    .assign_position(j)
+                    .unwrap()
+            })
+            .unzip();
+        let value_commitments: Vec<_> = bit_commitments.iter().map(|c| c.V_j).collect();
+
+        let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+
+        let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&bit_challenge))
+            .unzip();
+
+        let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+
+        let proof_shares: Vec<_> = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&poly_challenge).unwrap())
+            .collect();
+
+        assert!(dealer.batch_verify_shares(&proof_shares).is_ok());
+        assert_eq!(
+            dealer.batch_verify_shares(&proof_shares[..3]),
+            Err(MPCError::WrongNumProofShares)
+        );
+
+        let proof = dealer.receive_shares(&proof_shares).unwrap();
+
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        assert!(proof
+            .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, n)
+            .is_ok());
+    }
+
+    #[test]
+    fn dealer_blames_party_with_tampered_share() {
+        use self::dealer::*;
+        use self::party::*;
+
+        use errors::MPCError;
+
+        let m = 4;
+        let n = 32;
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, m);
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+
+        let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+
+        let (parties, bit_commitments): (Vec<_>, Vec<_>) = (0..m)
+            .map(|j| {
+                Party::new(&bp_gens, &pc_gens, j as u64, Scalar::from(j as u64 + 1), n)
+                    .unwrap()
+                    .assign_position(j)
+                    .unwrap()
+            })
+            .unzip();
+
+        let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+
+        let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&bit_challenge))
+            .unzip();
+
+        let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+
+        let mut proof_shares: Vec<_> = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&poly_challenge).unwrap())
+            .collect();
+
+        // Party 2 changes its share after computing it honestly.
+        proof_shares[2].t_x += Scalar::one();
+
+        assert_eq!(
+            dealer.receive_shares(&proof_shares).unwrap_err(),
+            MPCError::MalformedProofShares {
+                bad_shares: vec![2]
+            }
+        );
+    }
+
+    #[test]
+    fn all_zero_values_aggregate_and_verify() {
+        use self::dealer::*;
+        use self::party::*;
+
+        let n = 16;
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, 8);
+        let mut rng = rand::thread_rng();
+
+        for &m in &[1, 2, 3, 4, 8] {
+            let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
+
+            // Check the dealer's own verification of the shares, rather
+            // than only the trusted local aggregation.
+            let mut transcript = Transcript::new(b"ZeroValuesTest");
+            let parties_m = util::next_power_of_two(m);
+            let dealer =
+                Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, parties_m).unwrap();
+            let (parties, bit_commitments): (Vec<_>, Vec<_>) = blindings
+                .iter()
+                .cloned()
+                .chain(iter::repeat(Scalar::zero()))
+                .take(parties_m)
+                .enumerate()
+                .map(|(j, v_blinding)| {
+                    Party::new(&bp_gens, &pc_gens, 0, v_blinding, n)
+                        .unwrap()
+                        .assign_position(j)
+                        .unwrap()
+                })
+                .unzip();
+            let value_commitments: Vec<_> = bit_commitments.iter().map(|c| c.V_j).collect();
+
+            let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+            let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+                .into_iter()
+                .map(|p| p.apply_challenge(&bit_challenge))
+                .unzip();
+            let (dealer, poly_challenge) =
+                dealer.receive_poly_commitments(poly_commitments).unwrap();
+            let proof_shares: Vec<_> = parties
+                .into_iter()
+                .map(|p| p.apply_challenge(&poly_challenge).unwrap())
+                .collect();
+            let proof = dealer.receive_shares(&proof_shares).unwrap();
+
+            let mut transcript = Transcript::new(b"ZeroValuesTest");
+            assert!(proof
+                .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, n)
+                .is_ok());
+
+            // The single-party prover pads the aggregation itself.
+            let mut transcript = Transcript::new(b"ZeroValuesTest");
+            let (proof, value_commitments) = RangeProof::prove_multiple(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &vec![0; m],
+                &blindings,
+                n,
+            )
+            .unwrap();
+            let mut transcript = Transcript::new(b"ZeroValuesTest");
+            assert!(proof
+                .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, n)
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn dealer_params_are_validated_up_front() {
+        use self::dealer::*;
+        use errors::MPCError;
+
+        assert_eq!(Dealer::validate_params(64, 1), Ok(()));
+        assert_eq!(Dealer::validate_params(128, 8), Ok(()));
+        assert_eq!(Dealer::validate_params(0, 1), Err(MPCError::ZeroBitsize));
+        assert_eq!(Dealer::validate_params(0, 0), Err(MPCError::ZeroBitsize));
+        assert_eq!(Dealer::validate_params(48, 1), Err(MPCError::InvalidBitsize));
+        assert_eq!(Dealer::validate_params(256, 1), Err(MPCError::InvalidBitsize));
+        assert_eq!(Dealer::validate_params(32, 0), Err(MPCError::EmptyAggregation));
+        assert_eq!(
+            Dealer::validate_params(32, usize::max_value()),
+            Err(MPCError::InvalidAggregation)
+        );
+
+        // An aggregation which isn't a power of two is padded.
+        assert_eq!(Dealer::validate_params(32, 3), Ok(()));
+
+        // The errors convert to those of the single-party API.
+        let to_proof_error = |n, m| Dealer::validate_params(n, m).map_err(ProofError::from);
+        assert_eq!(to_proof_error(0, 1), Err(ProofError::InvalidBitsize));
+        assert_eq!(to_proof_error(48, 1), Err(ProofError::InvalidBitsize));
+        assert_eq!(to_proof_error(32, 0), Err(ProofError::InvalidAggregation));
+
+        // The dealer performs the same checks.
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 4);
+        let mut transcript = Transcript::new(b"ValidateParamsTest");
+        assert_eq!(
+            Dealer::new(&bp_gens, &pc_gens, &mut transcript, 0, 4).err(),
+            Some(MPCError::ZeroBitsize)
+        );
+        assert_eq!(
+            Dealer::new(&bp_gens, &pc_gens, &mut transcript, 32, 0).err(),
+            Some(MPCError::EmptyAggregation)
+        );
+        assert!(Dealer::new(&bp_gens, &pc_gens, &mut transcript, 32, 3).is_ok());
+    }
+
+    #[test]
+    fn dealer_pads_three_parties() {
+        use self::dealer::*;
+        use self::party::*;
+
+        use errors::MPCError;
+
+        let m = 3;
+        let n = 16;
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, 4);
+
+        let run = |tamper: bool| {
+            let mut transcript = Transcript::new(b"DealerPaddingTest");
+            let dealer = Dealer::builder(&bp_gens, &pc_gens)
+                .transcript(&mut transcript)
+                .bits(n)
+                .parties(m)
+                .strict(true)
+                .build()
+                .unwrap();
+
+            let (parties, bit_commitments): (Vec<_>, Vec<_>) = (0..m)
+                .map(|j| {
+                    let v_blinding = Scalar::from(j as u64 + 1);
+                    Party::new(&bp_gens, &pc_gens, 1000 * j as u64, v_blinding, n)
+                        .unwrap()
+                        .assign_position(j)
+                        .unwrap()
+                })
+                .unzip();
+            let value_commitments: Vec<_> = bit_commitments.iter().map(|c| c.V_j).collect();
+
+            let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+            let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+                .into_iter()
+                .map(|p| p.apply_challenge(&bit_challenge))
+                .unzip();
+            let (dealer, poly_challenge) =
+                dealer.receive_poly_commitments(poly_commitments).unwrap();
+            let mut proof_shares: Vec<_> = parties
+                .into_iter()
+                .map(|p| p.apply_challenge(&poly_challenge).unwrap())
+                .collect();
+            if tamper {
+                proof_shares[1].t_x += Scalar::one();
+            }
+
+            let result = dealer.receive_verifiable_shares(&proof_shares);
+            (result, value_commitments)
+        };
+
+        // Only the real parties' commitments are returned, and the
+        // verifier pads them in the same way as the dealer.
+        let (verifiable, value_commitments) = run(false);
+        let verifiable = verifiable.unwrap();
+        assert_eq!(verifiable.value_commitments(), &value_commitments[..]);
+        assert_eq!(verifiable.bitsizes(), &[n; 3]);
+        assert_eq!(verifiable.proof().num_bits(), 4 * n);
+
+        let mut transcript = Transcript::new(b"DealerPaddingTest");
+        assert!(verifiable
+            .proof()
+            .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, n)
+            .is_ok());
+
+        // A malformed share is still blamed on the real party.
+        let (result, _) = run(true);
+        assert_eq!(
+            result.unwrap_err(),
+            MPCError::MalformedProofShares {
+                bad_shares: vec![1]
+            }
+        );
+    }
+
+    #[test]
+    fn undersized_generators_are_rejected() {
+        use self::dealer::*;
+        use errors::MPCError;
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 2);
+
+        // The dealer checks the generators up front, so receiving the
+        // shares can't run past the end of them.
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        assert_eq!(
+            Dealer::new(&bp_gens, &pc_gens, &mut transcript, 64, 2)
+                .err()
+                .unwrap(),
+            MPCError::InvalidGeneratorsLength
+        );
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        assert_eq!(
+            Dealer::new(&bp_gens, &pc_gens, &mut transcript, 32, 4)
+                .err()
+                .unwrap(),
+            MPCError::InvalidGeneratorsLength
+        );
+
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        assert_eq!(
+            RangeProof::prove_multiple(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &[1, 2, 3],
+                &[Scalar::one(); 3],
+                32,
+            )
+            .unwrap_err(),
+            ProofError::InvalidGeneratorsLength
+        );
+
+        // A proof created with larger generators can't be verified
+        // against the undersized ones either.
+        let big_gens = BulletproofGens::new(64, 4);
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        let (proof, Vs) = RangeProof::prove_multiple(
+            &big_gens,
+            &pc_gens,
+            &mut transcript,
+            &[1, 2, 3],
+            &[Scalar::one(); 3],
+            64,
+        )
+        .unwrap();
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        assert_eq!(
+            proof
+                .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &Vs, 64)
+                .unwrap_err(),
+            ProofError::InvalidGeneratorsLength
+        );
+    }
+
+    #[test]
+    fn dealer_builder_checks_parameters() {
+        use self::dealer::*;
+        use errors::MPCError;
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 4);
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+
+        assert_eq!(
+            Dealer::builder(&bp_gens, &pc_gens)
+                .bits(32)
+                .parties(4)
+                .build()
+                .err()
+                .unwrap(),
+            MPCError::MissingTranscript
+        );
+        assert_eq!(
+            Dealer::builder(&bp_gens, &pc_gens)
+                .transcript(&mut transcript)
+                .parties(4)
+                .build()
+                .err()
+                .unwrap(),
+            MPCError::ZeroBitsize
+        );
+        assert_eq!(
+            Dealer::builder(&bp_gens, &pc_gens)
+                .transcript(&mut transcript)
+                .bits(32)
+                .build()
+                .err()
+                .unwrap(),
+            MPCError::EmptyAggregation
+        );
+
+        // Zero bits and zero parties are rejected with distinct errors.
+        assert_eq!(
+            Dealer::new(&bp_gens, &pc_gens, &mut transcript, 0, 4)
+                .err()
+                .unwrap(),
+            MPCError::ZeroBitsize
+        );
+        assert_eq!(
+            Dealer::new(&bp_gens, &pc_gens, &mut transcript, 32, 0)
+                .err()
+                .unwrap(),
+            MPCError::EmptyAggregation
+        );
+        assert_eq!(
+            Dealer::new(&bp_gens, &pc_gens, &mut transcript, 0, 0)
+                .err()
+                .unwrap(),
+            MPCError::ZeroBitsize
+        );
+        assert_eq!(
+            Dealer::new_variable(&bp_gens, &pc_gens, &mut transcript, &[])
+                .err()
+                .unwrap(),
+            MPCError::EmptyAggregation
+        );
+        assert_eq!(
+            Dealer::new_variable(&bp_gens, &pc_gens, &mut transcript, &[32, 0])
+                .err()
+                .unwrap(),
+            MPCError::ZeroBitsize
+        );
+
+        assert!(Dealer::builder(&bp_gens, &pc_gens)
+            .transcript(&mut transcript)
+            .bits(32)
+            .parties(4)
+            .strict(true)
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn streamed_bit_commitments_match_collected() {
+        use self::dealer::*;
+        use self::party::*;
+        use errors::MPCError;
+
+        let m = 4;
+        let n = 16;
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, m);
+
+        let bit_commitments: Vec<_> = (0..m)
+            .map(|j| {
+                Party::new(&bp_gens, &pc_gens, j as u64, Scalar::from(j as u64 + 1), n)
+                    .unwrap()
+                    .assign_position(j)
+                    .unwrap()
+                    .1
+            })
+            .collect();
+
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+        let (_, collected) = dealer
+            .receive_bit_commitments(bit_commitments.clone())
+            .unwrap();
+
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+        let (_, streamed) = dealer
+            .receive_bit_commitments_iter(bit_commitments.iter().cloned())
+            .unwrap();
+
+        assert_eq!(collected.y, streamed.y);
+        assert_eq!(collected.z, streamed.z);
+
+        // Too few and too many commitments are both rejected.
+        for count in &[m - 1, m + 1] {
+            let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+            let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+            assert_eq!(
+                dealer
+                    .receive_bit_commitments_iter(
+                        bit_commitments.iter().cloned().cycle().take(*count)
+                    )
+                    .err()
+                    .unwrap(),
+                MPCError::WrongNumBitCommitments
+            );
+        }
+    }
+
+    #[test]
+    fn strict_dealer_rejects_identity_and_duplicate_commitments() {
+        use self::dealer::*;
+        use self::messages::BitCommitment;
+        use self::party::*;
+        use errors::MPCError;
+
+        let m = 4;
+        let n = 8;
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, m);
+
+        // Party 0 commits to zero with a zero blinding factor, and
+        // party 2 repeats party 1's commitment.
+        let values = [0u64, 1, 1, 2];
+        let blindings = [
+            Scalar::zero(),
+            Scalar::from(3u64),
+            Scalar::from(3u64),
+            Scalar::from(4u64),
+        ];
+
+        let bit_commitments = || -> Vec<BitCommitment> {
+            values
+                .iter()
+                .zip(blindings.iter())
+                .enumerate()
+                .map(|(j, (&v, &v_blinding))| {
+                    Party::new(&bp_gens, &pc_gens, v, v_blinding, n)
+                        .unwrap()
+                        .assign_position(j)
+                        .unwrap()
+                        .1
+                })
+                .collect()
+        };
+
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        let dealer = Dealer::builder(&bp_gens, &pc_gens)
+            .transcript(&mut transcript)
+            .bits(n)
+            .parties(m)
+            .strict(true)
+            .build()
+            .unwrap();
+        match dealer.receive_bit_commitments(bit_commitments()) {
+            Err(MPCError::MalformedBitCommitments { bad_parties }) => {
+                assert_eq!(bad_parties, vec![0, 2]);
+            }
+            Err(_) => panic!("Got wrong error type from malformed bit commitments"),
+            Ok(_) => panic!("The strict dealer should have rejected the bit commitments"),
+        }
+
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+        assert!(dealer.receive_bit_commitments(bit_commitments()).is_ok());
+    }
+
+    #[test]
+    fn replayed_challenges_match_dealer() {
+        use self::dealer::*;
+        use self::party::*;
+
+        let m = 2;
+        let n = 8;
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, m);
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+
+        let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+
+        let (parties, bit_commitments): (Vec<_>, Vec<_>) = (0..m)
+            .map(|j| {
+                Party::new(&bp_gens, &pc_gens, j as u64, Scalar::from(j as u64 + 1), n)
+                    .unwrap()
+                    .assign_position(j)
+                    .unwrap()
+            })
+            .unzip();
+        let value_commitments: Vec<_> = bit_commitments.iter().map(|c| c.V_j).collect();
+
+        let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+
+        let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&bit_challenge))
+            .unzip();
+
+        let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+
+        let proof_shares: Vec<_> = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&poly_challenge).unwrap())
+            .collect();
+
+        let proof = dealer.receive_shares(&proof_shares).unwrap();
+
+        let mut replayed = Transcript::new(b"AggregatedRangeProofTest");
+        let challenges = proof
+            .challenges(&mut replayed, &value_commitments, &[n; 2])
+            .unwrap();
+
+        assert_eq!(challenges.y, bit_challenge.y);
+        assert_eq!(challenges.z, bit_challenge.z);
+        assert_eq!(challenges.x, poly_challenge.x);
+
+        // The dealer doesn't expose w, but it derives w right before
+        // the inner-product proof.  Continuing the replayed transcript
+        // through the inner-product proof only leaves it in the same
+        // state as the dealer's if w was derived from the same state.
+        replayed.innerproduct_domain_sep((n * m) as u64);
+        let ipp = &proof.ipp_proof;
+        for (L, R) in ipp.L_vec.iter().zip(ipp.R_vec.iter()) {
+            replayed.append_point(labels::L, L);
+            replayed.append_point(labels::R, R);
+            replayed.challenge_scalar(labels::U);
+        }
+        assert_eq!(
+            replayed.challenge_scalar(labels::W),
+            transcript.challenge_scalar(labels::W)
+        );
+    }
+
+    #[test]
+    fn dealer_resumes_by_replaying_messages() {
+        use self::dealer::*;
+        use self::messages::BitCommitment;
+        use self::party::*;
+        use bincode;
+
+        let m = 2;
+        let n = 8;
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, m);
+
+        let (parties, bit_commitments): (Vec<_>, Vec<_>) = (0..m)
+            .map(|j| {
+                Party::new(&bp_gens, &pc_gens, j as u64, Scalar::from(j as u64 + 1), n)
+                    .unwrap()
+                    .assign_position(j)
+                    .unwrap()
+            })
+            .unzip();
+
+        // The first request gets the bit challenge and stores the
+        // messages which produced it.
+        let bit_challenge = {
+            let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+            let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+            let (_, bit_challenge) = dealer
+                .receive_bit_commitments(bit_commitments.clone())
+                .unwrap();
+            bit_challenge
+        };
+        let stored = bincode::serialize(&bit_commitments).unwrap();
+
+        let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&bit_challenge))
+            .unzip();
+
+        // A later request replays the stored messages to resume.
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+        let stored: Vec<BitCommitment> = bincode::deserialize(&stored).unwrap();
+        let (dealer, replayed_challenge) = dealer.receive_bit_commitments(stored).unwrap();
+        assert_eq!(replayed_challenge.y, bit_challenge.y);
+        assert_eq!(replayed_challenge.z, bit_challenge.z);
+
+        let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+        let proof_shares: Vec<_> = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&poly_challenge).unwrap())
+            .collect();
+        assert!(dealer.receive_shares(&proof_shares).is_ok());
+    }
+
+    #[test]
+    fn dealer_output_is_verifiable() {
+        use self::dealer::*;
+        use self::party::*;
+
+        let m = 2;
+        let n = 16;
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, m);
+
+        let mut transcript = Transcript::new(b"VerifiableProofTest");
+        let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+        let (parties, bit_commitments): (Vec<_>, Vec<_>) = (0..m)
+            .map(|j| {
+                Party::new(&bp_gens, &pc_gens, 100 + j as u64, Scalar::one(), n)
+                    .unwrap()
+                    .assign_position(j)
+                    .unwrap()
+            })
+            .unzip();
+        let value_commitments: Vec<_> = bit_commitments.iter().map(|c| c.V_j).collect();
+
+        let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+        let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&bit_challenge))
+            .unzip();
+        let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+        let proof_shares: Vec<_> = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&poly_challenge).unwrap())
+            .collect();
+        let verifiable = dealer.receive_verifiable_shares(&proof_shares).unwrap();
+
+        assert_eq!(verifiable.value_commitments(), &value_commitments[..]);
+        assert_eq!(verifiable.bitsizes(), &[n, n]);
+
+        let mut transcript = Transcript::new(b"VerifiableProofTest");
+        assert!(verifiable
+            .verify(&bp_gens, &pc_gens, &mut transcript)
+            .is_ok());
+
+        // Rebundling the proof with other commitments fails to verify.
+        let (proof, mut value_commitments, bitsizes) = verifiable.into_parts();
+        value_commitments.swap(0, 1);
+        let swapped = VerifiableProof::new(proof.clone(), value_commitments, bitsizes).unwrap();
+        let mut transcript = Transcript::new(b"VerifiableProofTest");
+        assert!(swapped.verify(&bp_gens, &pc_gens, &mut transcript).is_err());
+
+        assert_eq!(
+            VerifiableProof::new(proof, swapped.value_commitments().to_vec(), vec![n]),
+            Err(ProofError::WrongNumBitsizes)
+        );
+    }
+
+    #[test]
+    fn transcript_continues_after_receive_shares() {
+        use self::dealer::*;
+        use self::party::*;
+
+        let m = 2;
+        let n = 8;
+
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, m);
+
+        let mut transcript = Transcript::new(b"SignedRangeProofTest");
+        let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+        let (parties, bit_commitments): (Vec<_>, Vec<_>) = (0..m)
+            .map(|j| {
+                Party::new(&bp_gens, &pc_gens, j as u64, Scalar::from(j as u64 + 1), n)
+                    .unwrap()
+                    .assign_position(j)
+                    .unwrap()
+            })
+            .unzip();
+        let value_commitments: Vec<_> = bit_commitments.iter().map(|c| c.V_j).collect();
+
+        let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+        let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&bit_challenge))
+            .unzip();
+        let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+        let proof_shares: Vec<_> = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&poly_challenge).unwrap())
+            .collect();
+        let proof = dealer.receive_shares(&proof_shares).unwrap();
+
+        // The dealer no longer borrows the transcript, so it can be
+        // continued to derive a signature challenge bound to the proof.
+        transcript.append_message(b"msg", b"signed message");
+        let mut prover_challenge = [0u8; 64];
+        transcript.challenge_bytes(b"sig-challenge", &mut prover_challenge);
+
+        let mut transcript = Transcript::new(b"SignedRangeProofTest");
+        assert!(proof
+            .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, n)
+            .is_ok());
+        transcript.append_message(b"msg", b"signed message");
+        let mut verifier_challenge = [0u8; 64];
+        transcript.challenge_bytes(b"sig-challenge", &mut verifier_challenge);
+
+        assert_eq!(&prover_challenge[..], &verifier_challenge[..]);
+    }
+
+    #[test]
+    fn committed_value_checks_range() {
+        assert_eq!(CommittedValue::new(255, 8).unwrap().value(), 255);
+        assert_eq!(CommittedValue::new(255, 8).unwrap().bitsize(), 8);
+        assert!(CommittedValue::new(u64::max_value(), 64).is_ok());
+        assert!(CommittedValue::new(u64::max_value(), 128).is_ok());
+        assert_eq!(
+            CommittedValue::new(256, 8),
+            Err(ProofError::ValueOutOfBounds)
+        );
+        assert_eq!(
+            CommittedValue::new(1 << 32, 32),
+            Err(ProofError::ValueOutOfBounds)
+        );
+        assert_eq!(CommittedValue::new(1, 7), Err(ProofError::InvalidBitsize));
+    }
+
+    #[test]
+    fn dealer_finalizes_to_bytes() {
+        use self::dealer::*;
+        use self::party::*;
+
+        let (n, m) = (16, 2);
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, m);
+        let mut transcript = Transcript::new(b"FinalizeToBytesTest");
+
+        let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+        let (parties, bit_commitments): (Vec<_>, Vec<_>) = (0..m)
+            .map(|j| {
+                Party::new(&bp_gens, &pc_gens, 1000 + j as u64, Scalar::from(7u64), n)
+                    .unwrap()
+                    .assign_position(j)
+                    .unwrap()
+            })
+            .unzip();
+        let value_commitments: Vec<_> = bit_commitments.iter().map(|c| c.V_j).collect();
 
-        let t_x = Scalar::from_canonical_bytes(read32(&slice[4 * 32..]))
-            .ok_or(ProofError::FormatError)?;
-        let t_x_blinding = Scalar::from_canonical_bytes(read32(&slice[5 * 32..]))
-            .ok_or(ProofError::FormatError)?;
-        let e_blinding = Scalar::from_canonical_bytes(read32(&slice[6 * 32..]))
-            .ok_or(ProofError::FormatError)?;
+        let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+        let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&bit_challenge))
+            .unzip();
+        let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+        let proof_shares: Vec<_> = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&poly_challenge).unwrap())
+            .collect();
 
-        let ipp_proof = InnerProductProof::from_bytes(&slice[7 * 32..])?;
+        let bytes = dealer.finalize_to_bytes(&proof_shares).unwrap();
+        assert_eq!(bytes.len(), RangeProof::proof_size(n, m).unwrap());
 
-        Ok(RangeProof {
-            A,
-            S,
-            T_1,
-            T_2,
-            t_x,
-            t_x_blinding,
-            e_blinding,
-            ipp_proof,
-        })
+        let proof = RangeProof::from_bytes(&bytes).unwrap();
+        let mut transcript = Transcript::new(b"FinalizeToBytesTest");
+        assert!(proof
+            .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, n)
+            .is_ok());
     }
-}
 
-impl Serialize for RangeProof {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        serializer.serialize_bytes(&self.to_bytes()[..])
-    }
-}
+    #[test]
+    fn dealer_collects_shares_incrementally() {
+        use self::dealer::*;
+        use self::party::*;
 
-impl<'de> Deserialize<'de> for RangeProof {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        struct RangeProofVisitor;
+        use errors::MPCError;
 
-        impl<'de> Visitor<'de> for RangeProofVisitor {
-            type Value = RangeProof;
+        let (n, m) = (8, 4);
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, m);
 
-            fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
-                formatter.write_str("a valid RangeProof")
+        for &complete in &[false, true] {
+            let mut transcript = Transcript::new(b"CollectSharesTest");
+            let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+            let (parties, bit_commitments): (Vec<_>, Vec<_>) = (0..m)
+                .map(|j| {
+                    Party::new(&bp_gens, &pc_gens, j as u64, Scalar::from(7u64), n)
+                        .unwrap()
+                        .assign_position(j)
+                        .unwrap()
+                })
+                .unzip();
+            let value_commitments: Vec<_> = bit_commitments.iter().map(|c| c.V_j).collect();
+            let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+            let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+                .into_iter()
+                .map(|p| p.apply_challenge(&bit_challenge))
+                .unzip();
+            let (dealer, poly_challenge) =
+                dealer.receive_poly_commitments(poly_commitments).unwrap();
+            let mut proof_shares: Vec<_> = parties
+                .into_iter()
+                .map(|p| p.apply_challenge(&poly_challenge).unwrap())
+                .collect();
+
+            let mut dealer = dealer.collect_shares();
+            let share_0 = proof_shares.remove(0);
+            for (j, share) in proof_shares.into_iter().enumerate().rev() {
+                dealer.add_share(j + 1, share.clone()).unwrap();
+                assert_eq!(
+                    dealer.add_share(j + 1, share),
+                    Err(MPCError::WrongNumProofShares)
+                );
             }
 
-            fn visit_bytes<E>(self, v: &[u8]) -> Result<RangeProof, E>
-            where
-                E: serde::de::Error,
-            {
-                RangeProof::from_bytes(v).map_err(serde::de::Error::custom)
+            let mut bad_share = share_0.clone();
+            bad_share.t_x += Scalar::one();
+            assert_eq!(
+                dealer.add_share(0, bad_share),
+                Err(MPCError::MalformedProofShares {
+                    bad_shares: vec![0]
+                })
+            );
+
+            if !complete {
+                assert_eq!(
+                    dealer.finalize().unwrap_err(),
+                    MPCError::WrongNumProofShares
+                );
+                continue;
             }
-        }
 
-        deserializer.deserialize_bytes(RangeProofVisitor)
+            dealer.add_share(0, share_0).unwrap();
+            let proof = dealer.finalize().unwrap();
+            let mut transcript = Transcript::new(b"CollectSharesTest");
+            assert!(proof
+                .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, n)
+                .is_ok());
+        }
     }
-}
 
-/// Compute
-/// \\[
-/// \delta(y,z) = (z - z^{2}) \langle \mathbf{1}, {\mathbf{y}}^{n \cdot m} \rangle - \sum_{j=0}^{m-1} z^{j+3} \cdot \langle \mathbf{1}, {\mathbf{2}}^{n \cdot m} \rangle
-/// \\]
-fn delta(n: usize, m: usize, y: &Scalar, z: &Scalar) -> Scalar {
-    let sum_y = util::sum_of_powers(y, n * m);
-    let sum_2 = util::sum_of_powers(&Scalar::from(2u64), n);
-    let sum_z = util::sum_of_powers(z, m);
+    #[test]
+    fn dealer_rejects_shares_of_wrong_length() {
+        use self::dealer::*;
+        use self::party::*;
 
-    (z - z * z) * sum_y - z * z * z * sum_2 * sum_z
-}
+        use errors::MPCError;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let (n, m) = (8, 4);
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, m);
+        let mut transcript = Transcript::new(b"ShareLengthTest");
 
-    use generators::PedersenGens;
+        let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
+        let (parties, bit_commitments): (Vec<_>, Vec<_>) = (0..m)
+            .map(|j| {
+                Party::new(&bp_gens, &pc_gens, j as u64, Scalar::from(7u64), n)
+                    .unwrap()
+                    .assign_position(j)
+                    .unwrap()
+            })
+            .unzip();
+        let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+        let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&bit_challenge))
+            .unzip();
+        let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+        let mut proof_shares: Vec<_> = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&poly_challenge).unwrap())
+            .collect();
 
-    #[test]
-    fn test_delta() {
-        let mut rng = rand::thread_rng();
-        let y = Scalar::random(&mut rng);
-        let z = Scalar::random(&mut rng);
+        // Moving an entry from one share to another keeps the total
+        // length, but misaligns every entry in between.
+        let l = proof_shares[1].l_vec.pop().unwrap();
+        proof_shares[2].l_vec.push(l);
+        proof_shares[3].r_vec.pop();
 
-        // Choose n = 256 to ensure we overflow the group order during
-        // the computation, to check that that's done correctly
-        let n = 256;
+        assert_eq!(
+            dealer.receive_trusted_shares(&proof_shares).unwrap_err(),
+            MPCError::MalformedProofShares {
+                bad_shares: vec![1, 2, 3]
+            }
+        );
+    }
 
-        // code copied from previous implementation
-        let z2 = z * z;
-        let z3 = z2 * z;
-        let mut power_g = Scalar::zero();
-        let mut exp_y = Scalar::one(); // start at y^0 = 1
-        let mut exp_2 = Scalar::one(); // start at 2^0 = 1
-        for _ in 0..n {
-            power_g += (z - z2) * exp_y - z3 * exp_2;
+    #[test]
+    fn trusted_shares_by_value_match_borrowed() {
+        use self::dealer::*;
+        use self::messages::ProofShare;
+        use self::party::*;
 
-            exp_y = exp_y * y; // y^i -> y^(i+1)
-            exp_2 = exp_2 + exp_2; // 2^i -> 2^(i+1)
+        use errors::MPCError;
+
+        // Runs the protocol up to the proof shares, with the parties'
+        // blinding factors drawn from a fixed seed.
+        fn shares<'a, 'b>(
+            bp_gens: &'b BulletproofGens,
+            pc_gens: &'b PedersenGens,
+            transcript: &'a mut Transcript,
+            m: usize,
+        ) -> (DealerAwaitingProofShares<'a, 'b>, Vec<ProofShare>) {
+            let n = 8;
+            let mut rng = ChaChaRng::from_seed([1; 32]);
+            let dealer = Dealer::new(bp_gens, pc_gens, transcript, n, m).unwrap();
+            let (parties, bit_commitments): (Vec<_>, Vec<_>) = (0..m)
+                .map(|j| {
+                    Party::new(bp_gens, pc_gens, j as u64, Scalar::from(7u64), n)
+                        .unwrap()
+                        .assign_position_with_rng(j, &mut rng)
+                        .unwrap()
+                })
+                .unzip();
+            let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+            let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+                .into_iter()
+                .map(|p| p.apply_challenge_with_rng(&bit_challenge, &mut rng))
+                .unzip();
+            let (dealer, poly_challenge) =
+                dealer.receive_poly_commitments(poly_commitments).unwrap();
+            let proof_shares = parties
+                .into_iter()
+                .map(|p| p.apply_challenge(&poly_challenge).unwrap())
+                .collect();
+            (dealer, proof_shares)
         }
 
-        assert_eq!(power_g, delta(n, 1, &y, &z),);
-    }
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(8, 4);
 
-    /// Given a bitsize `n`, test the following:
-    ///
-    /// 1. Generate `m` random values and create a proof they are all in range;
-    /// 2. Serialize to wire format;
-    /// 3. Deserialize from wire format;
-    /// 4. Verify the proof.
-    fn singleparty_create_and_verify_helper(n: usize, m: usize) {
-        // Split the test into two scopes, so that it's explicit what
-        // data is shared between the prover and the verifier.
+        // A lone share's vectors are moved, and those of three shares
+        // and a padding share are copied.
+        for &m in &[1, 3] {
+            let mut transcript = Transcript::new(b"OwnedSharesTest");
+            let (dealer, proof_shares) = shares(&bp_gens, &pc_gens, &mut transcript, m);
+            let borrowed = dealer.receive_trusted_shares(&proof_shares).unwrap();
 
-        // Use bincode for serialization
-        use bincode;
+            let mut transcript = Transcript::new(b"OwnedSharesTest");
+            let (dealer, proof_shares) = shares(&bp_gens, &pc_gens, &mut transcript, m);
+            let owned = dealer.receive_trusted_shares_owned(proof_shares).unwrap();
+            assert_eq!(owned.to_bytes(), borrowed.to_bytes());
 
-        // Both prover and verifier have access to the generators and the proof
-        let max_bitsize = 64;
-        let max_parties = 8;
+            let mut transcript = Transcript::new(b"OwnedSharesTest");
+            let (dealer, mut proof_shares) = shares(&bp_gens, &pc_gens, &mut transcript, m);
+            proof_shares.pop();
+            assert_eq!(
+                dealer
+                    .receive_trusted_shares_owned(proof_shares)
+                    .unwrap_err(),
+                MPCError::WrongNumProofShares
+            );
+        }
+    }
+
+    #[test]
+    fn dealer_binds_context() {
+        use self::dealer::*;
+        use self::party::*;
+        use transcript::labels;
+
+        let (n, m) = (8, 1);
         let pc_gens = PedersenGens::default();
-        let bp_gens = BulletproofGens::new(max_bitsize, max_parties);
+        let bp_gens = BulletproofGens::new(n, m);
+        let mut transcript = Transcript::new(b"ContextTest");
 
-        // Prover's scope
-        let (proof_bytes, value_commitments) = {
-            use rand::Rng;
-            let mut rng = rand::thread_rng();
+        let dealer =
+            Dealer::new_with_context(&bp_gens, &pc_gens, &mut transcript, n, m, b"tx 1234")
+                .unwrap();
+        let (party, bit_commitment) = Party::new(&bp_gens, &pc_gens, 42, Scalar::from(7u64), n)
+            .unwrap()
+            .assign_position(0)
+            .unwrap();
+        let V = bit_commitment.V_j;
 
-            // 0. Create witness data
-            let (min, max) = (0u64, ((1u128 << n) - 1) as u64);
-            let values: Vec<u64> = (0..m).map(|_| rng.gen_range(min, max)).collect();
-            let blindings: Vec<Scalar> = (0..m).map(|_| Scalar::random(&mut rng)).collect();
+        let (dealer, bit_challenge) = dealer
+            .receive_bit_commitments(vec![bit_commitment])
+            .unwrap();
+        let (party, poly_commitment) = party.apply_challenge(&bit_challenge);
+        let (dealer, poly_challenge) = dealer
+            .receive_poly_commitments(vec![poly_commitment])
+            .unwrap();
+        let proof_share = party.apply_challenge(&poly_challenge).unwrap();
+        let proof = dealer.receive_shares(&[proof_share]).unwrap();
 
-            // 1. Create the proof
-            let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
-            let (proof, value_commitments) = RangeProof::prove_multiple(
+        let verify = |context: &[u8]| {
+            let mut transcript = Transcript::new(b"ContextTest");
+            transcript.append_message(labels::CONTEXT, context);
+            proof.verify_single(&bp_gens, &pc_gens, &mut transcript, &V, n)
+        };
+        assert!(verify(b"tx 1234").is_ok());
+        assert!(verify(b"tx 1235").is_err());
+
+        let mut transcript = Transcript::new(b"ContextTest");
+        assert!(proof
+            .verify_single(&bp_gens, &pc_gens, &mut transcript, &V, n)
+            .is_err());
+    }
+
+    #[test]
+    fn dealer_binds_optional_context() {
+        use self::dealer::*;
+        use self::party::*;
+        use transcript::labels;
+
+        let (n, m) = (8, 1);
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, m);
+
+        let prove = |context: Option<&[u8]>| {
+            let mut transcript = Transcript::new(b"ContextTest");
+            let dealer = Dealer::new_with_optional_context(
                 &bp_gens,
                 &pc_gens,
                 &mut transcript,
-                &values,
-                &blindings,
                 n,
+                m,
+                context,
             )
             .unwrap();
+            let (party, bit_commitment) =
+                Party::new(&bp_gens, &pc_gens, 42, Scalar::from(7u64), n)
+                    .unwrap()
+                    .assign_position(0)
+                    .unwrap();
+            let V = bit_commitment.V_j;
 
-            // 2. Return serialized proof and value commitments
-            (bincode::serialize(&proof).unwrap(), value_commitments)
+            let (dealer, bit_challenge) = dealer
+                .receive_bit_commitments(vec![bit_commitment])
+                .unwrap();
+            let (party, poly_commitment) = party.apply_challenge(&bit_challenge);
+            let (dealer, poly_challenge) = dealer
+                .receive_poly_commitments(vec![poly_commitment])
+                .unwrap();
+            let proof_share = party.apply_challenge(&poly_challenge).unwrap();
+            (dealer.receive_shares(&[proof_share]).unwrap(), V)
+        };
+        let verify = |proof: &RangeProof, V: &CompressedRistretto, context: Option<&[u8]>| {
+            let mut transcript = Transcript::new(b"ContextTest");
+            ::append_optional_message(&mut transcript, labels::CONTEXT, context);
+            proof.verify_single(&bp_gens, &pc_gens, &mut transcript, V, n)
         };
 
-        // Verifier's scope
-        {
-            // 3. Deserialize
-            let proof: RangeProof = bincode::deserialize(&proof_bytes).unwrap();
-
-            // 4. Verify with the same customization label as above
-            let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        let (proof, V) = prove(Some(b"tx 1234"));
+        assert!(verify(&proof, &V, Some(b"tx 1234")).is_ok());
+        assert!(verify(&proof, &V, Some(b"tx 1235")).is_err());
 
-            assert!(proof
-                .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, n)
-                .is_ok());
-        }
+        // No context and an empty context are distinct.
+        let (proof, V) = prove(None);
+        assert!(verify(&proof, &V, None).is_ok());
+        assert!(verify(&proof, &V, Some(b"")).is_err());
+        let (proof, V) = prove(Some(b""));
+        assert!(verify(&proof, &V, Some(b"")).is_ok());
+        assert!(verify(&proof, &V, None).is_err());
     }
 
     #[test]
-    fn create_and_verify_n_32_m_1() {
-        singleparty_create_and_verify_helper(32, 1);
-    }
+    #[cfg(feature = "dealer-checks")]
+    fn dealer_checks_trusted_shares() {
+        use self::dealer::*;
+        use self::party::*;
 
-    #[test]
-    fn create_and_verify_n_32_m_2() {
-        singleparty_create_and_verify_helper(32, 2);
-    }
+        use errors::MPCError;
 
-    #[test]
-    fn create_and_verify_n_32_m_4() {
-        singleparty_create_and_verify_helper(32, 4);
-    }
+        let (n, m) = (8, 2);
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, m);
+        let mut transcript = Transcript::new(b"DealerChecksTest");
 
-    #[test]
-    fn create_and_verify_n_32_m_8() {
-        singleparty_create_and_verify_helper(32, 8);
-    }
+        let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
 
-    #[test]
-    fn create_and_verify_n_64_m_1() {
-        singleparty_create_and_verify_helper(64, 1);
-    }
+        // Party 1 is dishonest and uses a value out of its range.
+        let (parties, bit_commitments): (Vec<_>, Vec<_>) = [7u64, 1000]
+            .iter()
+            .enumerate()
+            .map(|(j, &v)| {
+                Party::new(&bp_gens, &pc_gens, v, Scalar::from(j as u64 + 1), n)
+                    .unwrap()
+                    .assign_position(j)
+                    .unwrap()
+            })
+            .unzip();
 
-    #[test]
-    fn create_and_verify_n_64_m_2() {
-        singleparty_create_and_verify_helper(64, 2);
+        let (dealer, bit_challenge) = dealer.receive_bit_commitments(bit_commitments).unwrap();
+        let (parties, poly_commitments): (Vec<_>, Vec<_>) = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&bit_challenge))
+            .unzip();
+        let (dealer, poly_challenge) = dealer.receive_poly_commitments(poly_commitments).unwrap();
+        let proof_shares: Vec<_> = parties
+            .into_iter()
+            .map(|p| p.apply_challenge(&poly_challenge).unwrap())
+            .collect();
+
+        assert_eq!(
+            dealer.receive_trusted_shares(&proof_shares).unwrap_err(),
+            MPCError::MalformedProofShares {
+                bad_shares: vec![1]
+            }
+        );
     }
 
     #[test]
-    fn create_and_verify_n_64_m_4() {
-        singleparty_create_and_verify_helper(64, 4);
+    fn single_party_through_dealer_n_8() {
+        use self::dealer::*;
+        use self::party::*;
+
+        let n = 8;
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, 1);
+        let mut transcript = Transcript::new(b"SinglePartyDealerTest");
+
+        let value = CommittedValue::new(255, n).unwrap();
+        let party = Party::from_value(&bp_gens, &pc_gens, value, Scalar::from(3u64)).unwrap();
+        let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, 1).unwrap();
+
+        let (party, bit_commitment) = party.assign_position(0).unwrap();
+        let V = bit_commitment.V_j;
+        let (dealer, bit_challenge) = dealer
+            .receive_bit_commitments(vec![bit_commitment])
+            .unwrap();
+        let (party, poly_commitment) = party.apply_challenge(&bit_challenge);
+        let (dealer, poly_challenge) = dealer
+            .receive_poly_commitments(vec![poly_commitment])
+            .unwrap();
+        let share = party.apply_challenge(&poly_challenge).unwrap();
+        assert_eq!(share.l_vec.len(), n);
+        assert_eq!(share.r_vec.len(), n);
+
+        let proof = dealer.receive_shares(&[share]).unwrap();
+
+        // The proof has the layout of a single rangeproof, and verifies as one.
+        assert_eq!(proof.ipp_proof.L_vec.len(), 3);
+        assert_eq!(proof.to_bytes().len(), RangeProof::proof_size(n, 1).unwrap());
+        let mut transcript = Transcript::new(b"SinglePartyDealerTest");
+        assert!(proof
+            .verify_single(&bp_gens, &pc_gens, &mut transcript, &V, n)
+            .is_ok());
     }
 
     #[test]
-    fn create_and_verify_n_64_m_8() {
-        singleparty_create_and_verify_helper(64, 8);
+    fn party_proves_external_commitment() {
+        use self::dealer::*;
+        use self::party::*;
+
+        use errors::MPCError;
+
+        let n = 16;
+        let pc_gens = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(n, 1);
+        let (v, v_blinding) = (1000u64, Scalar::from(9u64));
+
+        // The commitment is created separately from the proof.
+        let V = pc_gens.commit_value(v, &v_blinding);
+
+        assert_eq!(
+            Party::from_commitment(&bp_gens, &pc_gens, V, v + 1, v_blinding, n).err(),
+            Some(MPCError::MismatchedCommitment)
+        );
+        assert_eq!(
+            Party::from_commitment(&bp_gens, &pc_gens, V, v, Scalar::one(), n).err(),
+            Some(MPCError::MismatchedCommitment)
+        );
+
+        let party = Party::from_commitment(&bp_gens, &pc_gens, V, v, v_blinding, n).unwrap();
+        let mut transcript = Transcript::new(b"ExternalCommitmentTest");
+        let dealer = Dealer::new(&bp_gens, &pc_gens, &mut transcript, n, 1).unwrap();
+
+        let (party, bit_commitment) = party.assign_position(0).unwrap();
+        assert_eq!(bit_commitment.V_j, V);
+        let (dealer, bit_challenge) = dealer
+            .receive_bit_commitments(vec![bit_commitment])
+            .unwrap();
+        let (party, poly_commitment) = party.apply_challenge(&bit_challenge);
+        let (dealer, poly_challenge) = dealer
+            .receive_poly_commitments(vec![poly_commitment])
+            .unwrap();
+        let share = party.apply_challenge(&poly_challenge).unwrap();
+        let proof = dealer.receive_shares(&[share]).unwrap();
+
+        let mut transcript = Transcript::new(b"ExternalCommitmentTest");
+        assert!(proof
+            .verify_single(&bp_gens, &pc_gens, &mut transcript, &V, n)
+            .is_ok());
     }
 
     #[test]
@@ -677,7 +5663,23 @@ mod tests {
         let share2 = party2.apply_challenge(&poly_challenge).unwrap();
         let share3 = party3.apply_challenge(&poly_challenge).unwrap();
 
-        match dealer.receive_shares(&[share0, share1, share2, share3]) {
+        // Each share can also be checked on its own as it arrives.
+        assert!(dealer.verify_share(0, &share0).is_ok());
+        assert!(dealer.verify_share(1, &share1).is_err());
+        assert!(dealer.verify_share(2, &share2).is_ok());
+        assert!(dealer.verify_share(3, &share3).is_err());
+        assert!(dealer.verify_share(2, &share0).is_err());
+        assert!(dealer.verify_share(4, &share0).is_err());
+
+        let shares = [share0, share1, share2, share3];
+        assert_eq!(
+            dealer.batch_verify_shares(&shares),
+            Err(MPCError::MalformedProofShares {
+                bad_shares: vec![1, 3]
+            })
+        );
+
+        match dealer.receive_shares(&shares) {
             Err(MPCError::MalformedProofShares { bad_shares }) => {
                 assert_eq!(bad_shares, vec![1, 3]);
             }
@@ -14,11 +14,13 @@ use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
 use curve25519_dalek::scalar::Scalar;
 use curve25519_dalek::traits::MultiscalarMul;
 
+use alloc::vec::Vec;
 use clear_on_drop::clear::Clear;
+use core::iter;
 use errors::MPCError;
 use generators::{BulletproofGens, PedersenGens};
-use rand;
-use std::iter;
+use rand::{CryptoRng, RngCore};
+use range_proof::CommittedValue;
 use util;
 
 use super::messages::*;
@@ -35,14 +37,26 @@ impl Party {
         v_blinding: Scalar,
         n: usize,
     ) -> Result<PartyAwaitingPosition<'a>, MPCError> {
-        if !(n == 8 || n == 16 || n == 32 || n == 64) {
+        Party::new_u128(bp_gens, pc_gens, v.into(), v_blinding, n)
+    }
+
+    /// Constructs a `PartyAwaitingPosition` for a value which may not
+    /// fit in a `u64`, to be proved in a 128-bit range.
+    pub fn new_u128<'a>(
+        bp_gens: &'a BulletproofGens,
+        pc_gens: &'a PedersenGens,
+        v: u128,
+        v_blinding: Scalar,
+        n: usize,
+    ) -> Result<PartyAwaitingPosition<'a>, MPCError> {
+        if !util::is_valid_bitsize(n) {
             return Err(MPCError::InvalidBitsize);
         }
         if bp_gens.gens_capacity < n {
             return Err(MPCError::InvalidGeneratorsLength);
         }
 
-        let V = pc_gens.commit(v.into(), v_blinding).compress();
+        let V = pc_gens.commit(Scalar::from(v), v_blinding).compress();
 
         Ok(PartyAwaitingPosition {
             bp_gens,
@@ -53,6 +67,40 @@ impl Party {
             V,
         })
     }
+
+    /// Constructs a `PartyAwaitingPosition` for a value which has
+    /// already been checked to fit in its range.
+    pub fn from_value<'a>(
+        bp_gens: &'a BulletproofGens,
+        pc_gens: &'a PedersenGens,
+        value: CommittedValue,
+        v_blinding: Scalar,
+    ) -> Result<PartyAwaitingPosition<'a>, MPCError> {
+        Party::new(bp_gens, pc_gens, value.value(), v_blinding, value.bitsize())
+    }
+
+    /// Constructs a `PartyAwaitingPosition` which proves the range of
+    /// an existing value commitment `V`, created elsewhere, given its
+    /// opening.
+    ///
+    /// The party sends `V` itself to the dealer, so the proof is for
+    /// that exact commitment.  Returns
+    /// [`MPCError::MismatchedCommitment`] unless `v` and `v_blinding`
+    /// open `V` with these generators.
+    pub fn from_commitment<'a>(
+        bp_gens: &'a BulletproofGens,
+        pc_gens: &'a PedersenGens,
+        V: CompressedRistretto,
+        v: u64,
+        v_blinding: Scalar,
+        n: usize,
+    ) -> Result<PartyAwaitingPosition<'a>, MPCError> {
+        let party = Party::new(bp_gens, pc_gens, v, v_blinding, n)?;
+        if party.V != V {
+            return Err(MPCError::MismatchedCommitment);
+        }
+        Ok(party)
+    }
 }
 
 /// A party waiting for the dealer to assign their position in the aggregation.
@@ -60,7 +108,7 @@ pub struct PartyAwaitingPosition<'a> {
     bp_gens: &'a BulletproofGens,
     pc_gens: &'a PedersenGens,
     n: usize,
-    v: u64,
+    v: u128,
     v_blinding: Scalar,
     V: CompressedRistretto,
 }
@@ -68,20 +116,113 @@ pub struct PartyAwaitingPosition<'a> {
 impl<'a> PartyAwaitingPosition<'a> {
     /// Assigns a position in the aggregated proof to this party,
     /// allowing the party to commit to the bits of their value.
+    ///
+    /// This is a convenience wrapper around
+    /// [`PartyAwaitingPosition::assign_position_with_rng`], passing
+    /// in a threadsafe RNG.
+    #[cfg(feature = "std")]
     pub fn assign_position(
         self,
         j: usize,
     ) -> Result<(PartyAwaitingBitChallenge<'a>, BitCommitment), MPCError> {
-        // XXX use transcript RNG
-        let mut rng = rand::thread_rng();
+        self.assign_position_with_rng(j, &mut rand::thread_rng())
+    }
+
+    /// Assigns a position in the aggregated proof to this party,
+    /// allowing the party to commit to the bits of their value,
+    /// using `rng` to generate the blinding factors.
+    ///
+    /// This assumes that every party has the same bitsize; use
+    /// [`PartyAwaitingPosition::assign_variable_position_with_rng`]
+    /// when they do not.
+    pub fn assign_position_with_rng<T: RngCore + CryptoRng>(
+        self,
+        j: usize,
+        rng: &mut T,
+    ) -> Result<(PartyAwaitingBitChallenge<'a>, BitCommitment), MPCError> {
+        let offset = j * self.n;
+        self.assign_variable_position_with_rng(j, offset, rng)
+    }
+
+    /// Assigns a position in an aggregated proof whose parties may
+    /// have different bitsizes to this party.
+    ///
+    /// This is a convenience wrapper around
+    /// [`PartyAwaitingPosition::assign_variable_position_with_rng`],
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn assign_variable_position(
+        self,
+        j: usize,
+        offset: usize,
+    ) -> Result<(PartyAwaitingBitChallenge<'a>, BitCommitment), MPCError> {
+        self.assign_variable_position_with_rng(j, offset, &mut rand::thread_rng())
+    }
+
+    /// Assigns a position in an aggregated proof whose parties may
+    /// have different bitsizes to this party, using `rng` to generate
+    /// the blinding factors.
+    ///
+    /// The `offset` is the sum of the bitsizes of the parties in
+    /// positions before `j`, and must be given to the party by the
+    /// dealer along with its position.
+    pub fn assign_variable_position_with_rng<T: RngCore + CryptoRng>(
+        self,
+        j: usize,
+        offset: usize,
+        rng: &mut T,
+    ) -> Result<(PartyAwaitingBitChallenge<'a>, BitCommitment), MPCError> {
+        if self.bp_gens.party_capacity <= j {
+            return Err(MPCError::InvalidGeneratorsLength);
+        }
 
+        let a_blinding = Scalar::random(rng);
+        let s_blinding = Scalar::random(rng);
+        let s_L: Vec<Scalar> = (0..self.n).map(|_| Scalar::random(rng)).collect();
+        let s_R: Vec<Scalar> = (0..self.n).map(|_| Scalar::random(rng)).collect();
+
+        Ok(self.commit_bits(j, offset, a_blinding, s_blinding, s_L, s_R))
+    }
+
+    /// Assigns a position to a party padding the aggregation, whose
+    /// value is known to be zero, without blinding its commitments.
+    ///
+    /// This is used by the dealer to compute the padding parties'
+    /// messages itself.  The commitments reveal the party's value, so
+    /// this must not be used for parties with a secret value.
+    pub(super) fn assign_position_unblinded(
+        self,
+        j: usize,
+        offset: usize,
+    ) -> Result<(PartyAwaitingBitChallenge<'a>, BitCommitment), MPCError> {
         if self.bp_gens.party_capacity <= j {
             return Err(MPCError::InvalidGeneratorsLength);
         }
 
+        let zeros = vec![Scalar::zero(); self.n];
+        Ok(self.commit_bits(
+            j,
+            offset,
+            Scalar::zero(),
+            Scalar::zero(),
+            zeros.clone(),
+            zeros,
+        ))
+    }
+
+    /// Commits to the bits of the party's value with the given
+    /// blinding factors.
+    fn commit_bits(
+        self,
+        j: usize,
+        offset: usize,
+        a_blinding: Scalar,
+        s_blinding: Scalar,
+        s_L: Vec<Scalar>,
+        s_R: Vec<Scalar>,
+    ) -> (PartyAwaitingBitChallenge<'a>, BitCommitment) {
         let bp_share = self.bp_gens.share(j);
 
-        let a_blinding = Scalar::random(&mut rng);
         // Compute A = <a_L, G> + <a_R, H> + a_blinding * B_blinding
         let mut A = self.pc_gens.B_blinding * a_blinding;
 
@@ -97,10 +238,6 @@ impl<'a> PartyAwaitingPosition<'a> {
             i += 1;
         }
 
-        let s_blinding = Scalar::random(&mut rng);
-        let s_L: Vec<Scalar> = (0..self.n).map(|_| Scalar::random(&mut rng)).collect();
-        let s_R: Vec<Scalar> = (0..self.n).map(|_| Scalar::random(&mut rng)).collect();
-
         // Compute S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
         let S = RistrettoPoint::multiscalar_mul(
             iter::once(&s_blinding).chain(s_L.iter()).chain(s_R.iter()),
@@ -121,12 +258,13 @@ impl<'a> PartyAwaitingPosition<'a> {
             v_blinding: self.v_blinding,
             pc_gens: self.pc_gens,
             j,
+            offset,
             a_blinding,
             s_blinding,
             s_L,
             s_R,
         };
-        Ok((next_state, bit_commitment))
+        (next_state, bit_commitment)
     }
 }
 
@@ -142,9 +280,10 @@ impl<'a> Drop for PartyAwaitingPosition<'a> {
 /// and is waiting for the aggregated value challenge from the dealer.
 pub struct PartyAwaitingBitChallenge<'a> {
     n: usize, // bitsize of the range
-    v: u64,
+    v: u128,
     v_blinding: Scalar,
     j: usize,
+    offset: usize, // sum of the bitsizes of the parties before j
     pc_gens: &'a PedersenGens,
     a_blinding: Scalar,
     s_blinding: Scalar,
@@ -155,14 +294,52 @@ pub struct PartyAwaitingBitChallenge<'a> {
 impl<'a> PartyAwaitingBitChallenge<'a> {
     /// Receive a [`BitChallenge`] from the dealer and use it to
     /// compute commitments to the party's polynomial coefficients.
+    ///
+    /// This is a convenience wrapper around
+    /// [`PartyAwaitingBitChallenge::apply_challenge_with_rng`],
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
     pub fn apply_challenge(
         self,
         vc: &BitChallenge,
     ) -> (PartyAwaitingPolyChallenge, PolyCommitment) {
-        let mut rng = rand::thread_rng();
+        self.apply_challenge_with_rng(vc, &mut rand::thread_rng())
+    }
+
+    /// Receive a [`BitChallenge`] from the dealer and use it to
+    /// compute commitments to the party's polynomial coefficients,
+    /// using `rng` to generate the blinding factors.
+    pub fn apply_challenge_with_rng<T: RngCore + CryptoRng>(
+        self,
+        vc: &BitChallenge,
+        rng: &mut T,
+    ) -> (PartyAwaitingPolyChallenge, PolyCommitment) {
+        let t_1_blinding = Scalar::random(rng);
+        let t_2_blinding = Scalar::random(rng);
+        self.commit_poly(vc, t_1_blinding, t_2_blinding)
+    }
 
+    /// Receive a [`BitChallenge`] for a party padding the aggregation,
+    /// and commit to its polynomial coefficients without blinding
+    /// them, as for
+    /// [`assign_position_unblinded`](PartyAwaitingPosition::assign_position_unblinded).
+    pub(super) fn apply_challenge_unblinded(
+        self,
+        vc: &BitChallenge,
+    ) -> (PartyAwaitingPolyChallenge, PolyCommitment) {
+        self.commit_poly(vc, Scalar::zero(), Scalar::zero())
+    }
+
+    /// Commits to the party's polynomial coefficients with the given
+    /// blinding factors.
+    fn commit_poly(
+        self,
+        vc: &BitChallenge,
+        t_1_blinding: Scalar,
+        t_2_blinding: Scalar,
+    ) -> (PartyAwaitingPolyChallenge, PolyCommitment) {
         let n = self.n;
-        let offset_y = util::scalar_exp_vartime(&vc.y, (self.j * n) as u64);
+        let offset_y = util::scalar_exp_vartime(&vc.y, self.offset as u64);
         let offset_z = util::scalar_exp_vartime(&vc.z, self.j as u64);
 
         // Calculate t by calculating vectors l0, l1, r0, r1 and multiplying
@@ -173,7 +350,7 @@ impl<'a> PartyAwaitingBitChallenge<'a> {
         let mut exp_y = offset_y; // start at y^j
         let mut exp_2 = Scalar::one(); // start at 2^0 = 1
         for i in 0..n {
-            let a_L_i = Scalar::from((self.v >> i) & 1);
+            let a_L_i = Scalar::from(((self.v >> i) & 1) as u64);
             let a_R_i = a_L_i - Scalar::one();
 
             l_poly.0[i] = a_L_i - vc.z;
@@ -188,8 +365,6 @@ impl<'a> PartyAwaitingBitChallenge<'a> {
         let t_poly = l_poly.inner_product(&r_poly);
 
         // Generate x by committing to T_1, T_2 (line 49-54)
-        let t_1_blinding = Scalar::random(&mut rng);
-        let t_2_blinding = Scalar::random(&mut rng);
         let T_1 = self.pc_gens.commit(t_poly.1, t_1_blinding);
         let T_2 = self.pc_gens.commit(t_poly.2, t_2_blinding);
 
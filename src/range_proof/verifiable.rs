@@ -0,0 +1,106 @@
+//! A rangeproof bundled with the statement it proves.
+
+use alloc::vec::Vec;
+
+use curve25519_dalek::ristretto::CompressedRistretto;
+use merlin::Transcript;
+use rand::{CryptoRng, RngCore};
+
+use errors::ProofError;
+use generators::{BulletproofGens, PedersenGens};
+
+use super::RangeProof;
+
+/// A [`RangeProof`] together with the value commitments and bitsizes
+/// it was created for.
+///
+/// A proof on its own does not contain the commitments or the
+/// bitsizes, which the verifier must supply.  Bundling them means that
+/// a proof can be handed to a verifier in one piece, e.g. the output of
+/// [`receive_verifiable_shares`](::range_proof_mpc::dealer::DealerAwaitingProofShares::receive_verifiable_shares),
+/// and checked with [`VerifiableProof::verify`].
+///
+/// The verifier must still ensure that the commitments are the ones it
+/// expects the proof to be about.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifiableProof {
+    pub(super) proof: RangeProof,
+    pub(super) value_commitments: Vec<CompressedRistretto>,
+    pub(super) bitsizes: Vec<usize>,
+}
+
+impl VerifiableProof {
+    /// Bundles a `proof` with the `value_commitments` it was created
+    /// for, where the `j`-th value lies in a `bitsizes[j]`-bit range.
+    ///
+    /// Returns [`ProofError::WrongNumBitsizes`] if there is not exactly
+    /// one bitsize per commitment.
+    pub fn new(
+        proof: RangeProof,
+        value_commitments: Vec<CompressedRistretto>,
+        bitsizes: Vec<usize>,
+    ) -> Result<VerifiableProof, ProofError> {
+        if value_commitments.len() != bitsizes.len() {
+            return Err(ProofError::WrongNumBitsizes);
+        }
+        Ok(VerifiableProof {
+            proof,
+            value_commitments,
+            bitsizes,
+        })
+    }
+
+    /// Returns the rangeproof.
+    pub fn proof(&self) -> &RangeProof {
+        &self.proof
+    }
+
+    /// Returns the commitments to the values in range.
+    pub fn value_commitments(&self) -> &[CompressedRistretto] {
+        &self.value_commitments
+    }
+
+    /// Returns the bitsize of the range of each value.
+    pub fn bitsizes(&self) -> &[usize] {
+        &self.bitsizes
+    }
+
+    /// Splits the bundle into the proof, the value commitments and the
+    /// bitsizes.
+    pub fn into_parts(self) -> (RangeProof, Vec<CompressedRistretto>, Vec<usize>) {
+        (self.proof, self.value_commitments, self.bitsizes)
+    }
+
+    /// Verifies the proof for its value commitments and bitsizes.
+    ///
+    /// This is a convenience wrapper around `verify_with_rng`,
+    /// passing in a threadsafe RNG.
+    #[cfg(feature = "std")]
+    pub fn verify(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+    ) -> Result<(), ProofError> {
+        self.verify_with_rng(bp_gens, pc_gens, transcript, &mut rand::thread_rng())
+    }
+
+    /// Verifies the proof for its value commitments and bitsizes, as
+    /// by [`RangeProof::verify_multiple_variable_with_rng`].
+    pub fn verify_with_rng<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens,
+        pc_gens: &PedersenGens,
+        transcript: &mut Transcript,
+        rng: &mut T,
+    ) -> Result<(), ProofError> {
+        self.proof.verify_multiple_variable_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            &self.value_commitments,
+            &self.bitsizes,
+            rng,
+        )
+    }
+}
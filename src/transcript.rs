@@ -1,4 +1,8 @@
 //! Defines a `TranscriptProtocol` trait for using a Merlin transcript.
+//!
+//! All proof data is absorbed into the STROBE-based Merlin transcript
+//! with `append_message` or `append_u64`, under the labels given at
+//! each call site, so there is no separate hand-rolled hashing.
 
 use curve25519_dalek::ristretto::CompressedRistretto;
 use curve25519_dalek::scalar::Scalar;
@@ -6,10 +10,89 @@ use merlin::Transcript;
 
 use errors::ProofError;
 
+/// The labels under which the rangeproof and inner-product proof
+/// data is absorbed into the transcript, and under which the
+/// challenges are derived.
+///
+/// These are exported so that a verifier in another language can
+/// reproduce the transcript exactly.  A rangeproof absorbs the
+/// domain separator ([`DOMAIN_SEP`] with the value
+/// [`RANGEPROOF_DOMAIN_SEP`], then [`N`] and [`M`] as little-endian
+/// `u64`s), then each [`V`], [`A`] and [`S`]; squeezes [`Y`] and
+/// [`Z`]; absorbs [`T_1`] and [`T_2`]; squeezes [`X`]; absorbs
+/// [`T_X`], [`T_X_BLINDING`] and [`E_BLINDING`]; and squeezes [`W`].
+/// The inner-product proof then absorbs its own domain separator
+/// ([`IPP_DOMAIN_SEP`] and [`N`]), and for each round absorbs [`L`]
+/// and [`R`] and squeezes [`U`].
+///
+/// If the parties have different bitsizes, the rangeproof's domain
+/// separator is instead [`DOMAIN_SEP`] with the value
+/// [`VARIABLE_RANGEPROOF_DOMAIN_SEP`], then [`M`], then [`N`] for the
+/// bitsize of each party in turn.
+pub mod labels {
+    /// Label for domain separators.
+    pub const DOMAIN_SEP: &[u8] = b"dom-sep";
+    /// Domain separator value for a rangeproof.
+    pub const RANGEPROOF_DOMAIN_SEP: &[u8] = b"rangeproof v1";
+    /// Domain separator value for a rangeproof whose parties have
+    /// different bitsizes.
+    pub const VARIABLE_RANGEPROOF_DOMAIN_SEP: &[u8] = b"variable rangeproof v1";
+    /// Domain separator value for an inner-product proof.
+    pub const IPP_DOMAIN_SEP: &[u8] = b"ipp v1";
+    /// Domain separator value for a constraint system proof.
+    pub const R1CS_DOMAIN_SEP: &[u8] = b"r1cs v1";
+    /// Label for the bitsize, or the inner-product proof length.
+    pub const N: &[u8] = b"n";
+    /// Label for the aggregation size.
+    pub const M: &[u8] = b"m";
+    /// Label for application data binding a proof to its context,
+    /// appended before the domain separator.
+    pub const CONTEXT: &[u8] = b"context";
+
+    /// Label for each value commitment \\(V_j\\).
+    pub const V: &[u8] = b"V";
+    /// Label for the commitment \\(A\\) to the bits of the values.
+    pub const A: &[u8] = b"A";
+    /// Label for the commitment \\(S\\) to the blinding factors.
+    pub const S: &[u8] = b"S";
+    /// Label for the challenge \\(y\\).
+    pub const Y: &[u8] = b"y";
+    /// Label for the challenge \\(z\\).
+    pub const Z: &[u8] = b"z";
+    /// Label for the commitment \\(T_1\\).
+    pub const T_1: &[u8] = b"T_1";
+    /// Label for the commitment \\(T_2\\).
+    pub const T_2: &[u8] = b"T_2";
+    /// Label for the challenge \\(x\\).
+    pub const X: &[u8] = b"x";
+    /// Label for the evaluation \\(t(x)\\).
+    pub const T_X: &[u8] = b"t_x";
+    /// Label for the blinding factor of \\(t(x)\\).
+    pub const T_X_BLINDING: &[u8] = b"t_x_blinding";
+    /// Label for the blinding factor of the inner-product arguments.
+    pub const E_BLINDING: &[u8] = b"e_blinding";
+    /// Label for the challenge \\(w\\).
+    pub const W: &[u8] = b"w";
+
+    /// Label for each round's \\(L\\) point in the inner-product proof.
+    pub const L: &[u8] = b"L";
+    /// Label for each round's \\(R\\) point in the inner-product proof.
+    pub const R: &[u8] = b"R";
+    /// Label for each round's challenge \\(u\\) in the inner-product proof.
+    pub const U: &[u8] = b"u";
+}
+
 pub trait TranscriptProtocol {
     /// Append a domain separator for an `n`-bit, `m`-party range proof.
     fn rangeproof_domain_sep(&mut self, n: u64, m: u64);
 
+    /// Append a domain separator for a range proof whose parties have
+    /// the given `bitsizes`.
+    ///
+    /// If all of the bitsizes are equal, this is the same domain
+    /// separator as [`TranscriptProtocol::rangeproof_domain_sep`].
+    fn variable_rangeproof_domain_sep(&mut self, bitsizes: &[usize]);
+
     /// Append a domain separator for a length-`n` inner product proof.
     fn innerproduct_domain_sep(&mut self, n: u64);
 
@@ -30,24 +113,71 @@ pub trait TranscriptProtocol {
         point: &CompressedRistretto,
     ) -> Result<(), ProofError>;
 
-    /// Compute a `label`ed challenge variable.
+    /// Compute a `label`ed challenge variable, by squeezing 64 bytes
+    /// from the transcript with `challenge_bytes` and reducing them
+    /// modulo the group order with `Scalar::from_bytes_mod_order_wide`.
     fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar;
 }
 
+/// Append optional `data` to the `transcript` with the given
+/// `label`, as a presence byte (`0` or `1`) followed by the data, or
+/// by an empty message if there is none.
+///
+/// The transcript has the same shape whether or not the data is
+/// present, and absent data doesn't collide with empty data.  This
+/// is how [`Dealer::new_with_optional_context`] binds its context,
+/// so a verifier calls it with
+/// [`CONTEXT`](::transcript_labels::CONTEXT) and the same context
+/// before verifying.
+///
+/// [`Dealer::new_with_optional_context`]: ::range_proof_mpc::dealer::Dealer::new_with_optional_context
+pub fn append_optional_message(
+    transcript: &mut Transcript,
+    label: &'static [u8],
+    data: Option<&[u8]>,
+) {
+    match data {
+        Some(data) => {
+            transcript.append_message(label, &[1]);
+            transcript.append_message(label, data);
+        }
+        None => {
+            transcript.append_message(label, &[0]);
+            transcript.append_message(label, &[]);
+        }
+    }
+}
+
 impl TranscriptProtocol for Transcript {
     fn rangeproof_domain_sep(&mut self, n: u64, m: u64) {
-        self.append_message(b"dom-sep", b"rangeproof v1");
-        self.append_u64(b"n", n);
-        self.append_u64(b"m", m);
+        self.append_message(labels::DOMAIN_SEP, labels::RANGEPROOF_DOMAIN_SEP);
+        self.append_u64(labels::N, n);
+        self.append_u64(labels::M, m);
+    }
+
+    fn variable_rangeproof_domain_sep(&mut self, bitsizes: &[usize]) {
+        let m = bitsizes.len() as u64;
+        match bitsizes.split_first() {
+            Some((&n, rest)) if rest.iter().all(|&n_j| n_j == n) => {
+                self.rangeproof_domain_sep(n as u64, m)
+            }
+            _ => {
+                self.append_message(labels::DOMAIN_SEP, labels::VARIABLE_RANGEPROOF_DOMAIN_SEP);
+                self.append_u64(labels::M, m);
+                for &n_j in bitsizes {
+                    self.append_u64(labels::N, n_j as u64);
+                }
+            }
+        }
     }
 
     fn innerproduct_domain_sep(&mut self, n: u64) {
-        self.append_message(b"dom-sep", b"ipp v1");
-        self.append_u64(b"n", n);
+        self.append_message(labels::DOMAIN_SEP, labels::IPP_DOMAIN_SEP);
+        self.append_u64(labels::N, n);
     }
 
     fn r1cs_domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"r1cs v1");
+        self.append_message(labels::DOMAIN_SEP, labels::R1CS_DOMAIN_SEP);
     }
 
     fn append_scalar(&mut self, label: &'static [u8], scalar: &Scalar) {
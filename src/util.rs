@@ -1,8 +1,11 @@
 #![deny(missing_docs)]
 #![allow(non_snake_case)]
 
+use alloc::vec::Vec;
 use clear_on_drop::clear::Clear;
+use curve25519_dalek::ristretto::CompressedRistretto;
 use curve25519_dalek::scalar::Scalar;
+use errors::ProofError;
 use inner_product_proof::inner_product;
 
 /// Represents a degree-1 vector polynomial \\(\mathbf{a} + \mathbf{b} \cdot x\\).
@@ -61,10 +64,17 @@ pub fn exp_iter(x: Scalar) -> ScalarExp {
     ScalarExp { x, next_exp_x }
 }
 
+/// Return a vector of the first `n` powers of `x`, for when the same
+/// powers are needed more than once.
+pub fn exp_vec(x: Scalar, n: usize) -> Vec<Scalar> {
+    exp_iter(x).take(n).collect()
+}
+
 pub fn add_vec(a: &[Scalar], b: &[Scalar]) -> Vec<Scalar> {
     let mut out = Vec::new();
     if a.len() != b.len() {
         // throw some error
+        #[cfg(feature = "std")]
         println!("lengths of vectors don't match for vector addition");
     }
     for i in 0..a.len() {
@@ -255,6 +265,18 @@ fn sum_of_powers_slow(x: &Scalar, n: usize) -> Scalar {
     exp_iter(*x).take(n).sum()
 }
 
+/// Returns whether `n` is a bitsize supported by the rangeproofs,
+/// i.e. one of `8, 16, 32, 64, 128`.
+pub fn is_valid_bitsize(n: usize) -> bool {
+    n.is_power_of_two() && n >= 8 && n <= 128
+}
+
+/// Rounds `x` up to the next power of two, which is `x` itself if it
+/// already is one.  Rounds zero up to one.
+pub fn next_power_of_two(x: usize) -> usize {
+    x.next_power_of_two()
+}
+
 /// Given `data` with `len >= 32`, return the first 32 bytes.
 pub fn read32(data: &[u8]) -> [u8; 32] {
     let mut buf32 = [0u8; 32];
@@ -262,6 +284,14 @@ pub fn read32(data: &[u8]) -> [u8; 32] {
     buf32
 }
 
+/// Given `data` with `len >= 32`, return the first 32 bytes as a
+/// compressed Ristretto point, checking that it decompresses.
+pub fn read_point(data: &[u8]) -> Result<CompressedRistretto, ProofError> {
+    let point = CompressedRistretto(read32(data));
+    point.decompress().ok_or(ProofError::InvalidPoint)?;
+    Ok(point)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -276,6 +306,16 @@ mod tests {
         assert_eq!(exp_2[3], Scalar::from(8u64));
     }
 
+    #[test]
+    fn exp_vec_matches_exp_iter() {
+        let x = Scalar::from(7u64);
+        let exp_x = exp_vec(x, 10);
+
+        assert_eq!(exp_x.len(), 10);
+        assert!(exp_x.iter().cloned().eq(exp_iter(x).take(10)));
+        assert!(exp_vec(x, 0).is_empty());
+    }
+
     #[test]
     fn test_inner_product() {
         let a = vec![
@@ -333,6 +373,25 @@ mod tests {
         assert_eq!(sum_of_powers_slow(&x, 64), sum_of_powers(&x, 64));
     }
 
+    #[test]
+    fn test_is_valid_bitsize() {
+        for &n in &[8, 16, 32, 64, 128] {
+            assert!(is_valid_bitsize(n));
+        }
+        for &n in &[0, 1, 4, 7, 24, 63, 256] {
+            assert!(!is_valid_bitsize(n));
+        }
+    }
+
+    #[test]
+    fn test_next_power_of_two() {
+        assert_eq!(next_power_of_two(0), 1);
+        assert_eq!(next_power_of_two(1), 1);
+        assert_eq!(next_power_of_two(3), 4);
+        assert_eq!(next_power_of_two(64), 64);
+        assert_eq!(next_power_of_two(65), 128);
+    }
+
     #[test]
     fn test_sum_of_powers_slow() {
         let x = Scalar::from(10u64);
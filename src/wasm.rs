@@ -0,0 +1,184 @@
+//! The `wasm` module contains JavaScript bindings for creating and
+//! verifying single-value rangeproofs in the browser, built with the
+//! `wasm` feature.
+//!
+//! Values are passed as `BigInt`s, and blinding factors, commitments
+//! and proofs as `Uint8Array`s.  The Bulletproofs generators are
+//! derived from a label with [`BulletproofGens::from_label`], so they
+//! don't have to be shipped to the browser, and the default
+//! [`PedersenGens`] are used for the commitments.
+//!
+//! The proofs are created with the transcript
+//! `Transcript::new(b"BulletproofsWasm")`, followed by
+//! `append_message(b"label", transcript_label)`, so that they can be
+//! verified outside the browser as well.
+
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::scalar::Scalar;
+use merlin::Transcript;
+use wasm_bindgen::prelude::*;
+
+use generators::{BulletproofGens, PedersenGens};
+use range_proof::RangeProof;
+use util;
+use util::read32;
+
+/// A rangeproof together with the commitment to its value.
+#[wasm_bindgen]
+pub struct SingleRangeProof {
+    proof: Vec<u8>,
+    commitment: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl SingleRangeProof {
+    /// The serialized rangeproof.
+    #[wasm_bindgen(getter)]
+    pub fn proof(&self) -> Vec<u8> {
+        self.proof.clone()
+    }
+
+    /// The 32-byte compressed commitment to the value.
+    #[wasm_bindgen(getter)]
+    pub fn commitment(&self) -> Vec<u8> {
+        self.commitment.clone()
+    }
+}
+
+fn transcript(transcript_label: &str) -> Transcript {
+    let mut transcript = Transcript::new(b"BulletproofsWasm");
+    transcript.append_message(b"label", transcript_label.as_bytes());
+    transcript
+}
+
+/// Creates a rangeproof that `value` lies in the range \\([0, 2^n)\\),
+/// committing to it with the 32-byte canonical scalar `blinding`.
+///
+/// Throws if the blinding factor is malformed or `n` is not one of
+/// `8, 16, 32, 64, 128`.
+#[wasm_bindgen(js_name = proveSingle)]
+pub fn prove_single(
+    gens_label: &str,
+    transcript_label: &str,
+    value: u64,
+    blinding: &[u8],
+    n: usize,
+) -> Result<SingleRangeProof, JsValue> {
+    // Check n before it sizes the generators.
+    if !util::is_valid_bitsize(n) {
+        return Err(JsValue::from_str("n must be one of 8, 16, 32, 64, 128"));
+    }
+    if blinding.len() != 32 {
+        return Err(JsValue::from_str("blinding must be 32 bytes"));
+    }
+    let blinding = Scalar::from_canonical_bytes(read32(blinding))
+        .ok_or_else(|| JsValue::from_str("blinding is not a canonical scalar"))?;
+
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::from_label(gens_label.as_bytes(), n, 1);
+
+    let (proof, V) = RangeProof::prove_single(
+        &bp_gens,
+        &pc_gens,
+        &mut transcript(transcript_label),
+        value,
+        &blinding,
+        n,
+    )
+    .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    Ok(SingleRangeProof {
+        proof: proof.to_bytes(),
+        commitment: V.as_bytes().to_vec(),
+    })
+}
+
+/// Verifies a rangeproof created by `proveSingle` with the same
+/// labels and bitsize `n`, for the 32-byte `commitment`.
+///
+/// Returns `false` if the proof is invalid or any input is malformed.
+#[wasm_bindgen(js_name = verifySingle)]
+pub fn verify_single(
+    gens_label: &str,
+    transcript_label: &str,
+    proof: &[u8],
+    commitment: &[u8],
+    n: usize,
+) -> bool {
+    // Check n before it sizes the generators.
+    if !util::is_valid_bitsize(n) || commitment.len() != 32 {
+        return false;
+    }
+    let V = CompressedRistretto(read32(commitment));
+    let proof = match RangeProof::from_bytes(proof) {
+        Ok(proof) => proof,
+        Err(_) => return false,
+    };
+
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::from_label(gens_label.as_bytes(), n, 1);
+
+    proof
+        .verify_single(&bp_gens, &pc_gens, &mut transcript(transcript_label), &V, n)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prove_and_verify_round_trip() {
+        let blinding = Scalar::from(1234u64);
+        let proof = prove_single("test gens", "test proof", 42, blinding.as_bytes(), 32)
+            .ok()
+            .unwrap();
+
+        assert!(verify_single(
+            "test gens",
+            "test proof",
+            &proof.proof,
+            &proof.commitment,
+            32
+        ));
+        assert!(!verify_single(
+            "other gens",
+            "test proof",
+            &proof.proof,
+            &proof.commitment,
+            32
+        ));
+        assert!(!verify_single(
+            "test gens",
+            "other proof",
+            &proof.proof,
+            &proof.commitment,
+            32
+        ));
+        assert!(!verify_single(
+            "test gens",
+            "test proof",
+            &proof.proof[1..],
+            &proof.commitment,
+            32
+        ));
+        assert!(!verify_single(
+            "test gens",
+            "test proof",
+            &proof.proof,
+            &proof.commitment[1..],
+            32
+        ));
+
+        // An untrusted bitsize is rejected before any generators are
+        // created for it.
+        let huge = usize::max_value() / 2;
+        assert!(!verify_single(
+            "test gens",
+            "test proof",
+            &proof.proof,
+            &proof.commitment,
+            huge
+        ));
+    }
+}
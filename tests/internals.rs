@@ -0,0 +1,43 @@
+extern crate rand;
+use rand::Rng;
+
+extern crate curve25519_dalek;
+use curve25519_dalek::scalar::Scalar;
+
+extern crate bulletproofs;
+use bulletproofs::range_proof_mpc::messages::{BitChallenge, PolyChallenge};
+use bulletproofs::range_proof_mpc::party::Party;
+use bulletproofs::{BulletproofGens, PedersenGens};
+
+// Checks that <l(x), r(x)> = t(x) for a party's share, for random
+// values, blinding factors and challenges.
+#[test]
+fn party_polynomials_satisfy_inner_product_relation() {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(64, 4);
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..32 {
+        let n = [8, 16, 32, 64][rng.gen_range(0, 4)];
+        let j = rng.gen_range(0, 4);
+        let v = rng.gen::<u64>() >> (64 - n);
+
+        let party = Party::new(&bp_gens, &pc_gens, v, Scalar::random(&mut rng), n).unwrap();
+        let (party, _) = party.assign_position(j).unwrap();
+        let bit_challenge = BitChallenge::new(Scalar::random(&mut rng), Scalar::random(&mut rng));
+        let (party, _) = party.apply_challenge(&bit_challenge);
+        let share = party
+            .apply_challenge(&PolyChallenge::new(Scalar::random(&mut rng)))
+            .unwrap();
+
+        assert_eq!(share.l_vec().len(), n);
+        assert_eq!(share.r_vec().len(), n);
+        let inner_product: Scalar = share
+            .l_vec()
+            .iter()
+            .zip(share.r_vec())
+            .map(|(l_i, r_i)| l_i * r_i)
+            .sum();
+        assert_eq!(inner_product, share.t_x());
+    }
+}
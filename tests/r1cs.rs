@@ -9,7 +9,8 @@ use bulletproofs::{BulletproofGens, PedersenGens};
 use curve25519_dalek::ristretto::CompressedRistretto;
 use curve25519_dalek::scalar::Scalar;
 use merlin::Transcript;
-use rand::thread_rng;
+use rand::rngs::StdRng;
+use rand::{thread_rng, SeedableRng};
 
 // Shuffle gadget (documented in markdown file)
 
@@ -308,3 +309,62 @@ fn example_gadget_test() {
     // (3 + 4) * (6 + 1) != (40 + 10)
     assert!(example_gadget_roundtrip_helper(3, 4, 6, 1, 40, 10).is_err());
 }
+
+fn example_gadget_proof_with_rng(seed: u64) -> (R1CSProof, Vec<CompressedRistretto>) {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let mut transcript = Transcript::new(b"R1CSExampleGadget");
+    let mut prover = Prover::new(&bp_gens, &pc_gens, &mut transcript);
+
+    let (commitments, vars): (Vec<_>, Vec<_>) = [3u64, 4, 6, 1, 40]
+        .into_iter()
+        .map(|x| prover.commit(Scalar::from(*x), Scalar::random(&mut rng)))
+        .unzip();
+
+    let mut cs = prover.finalize_inputs();
+    example_gadget(
+        &mut cs,
+        vars[0].into(),
+        vars[1].into(),
+        vars[2].into(),
+        vars[3].into(),
+        vars[4].into(),
+        Scalar::from(9u64).into(),
+    );
+
+    (cs.prove_with_rng(&mut rng).unwrap(), commitments)
+}
+
+#[test]
+fn prove_with_rng_is_deterministic() {
+    let (proof1, commitments1) = example_gadget_proof_with_rng(42);
+    let (proof2, commitments2) = example_gadget_proof_with_rng(42);
+    assert_eq!(commitments1, commitments2);
+    assert_eq!(format!("{:?}", proof1), format!("{:?}", proof2));
+
+    let (proof3, _) = example_gadget_proof_with_rng(43);
+    assert_ne!(format!("{:?}", proof1), format!("{:?}", proof3));
+
+    // The seeded proof verifies with a seeded verifier as well.
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(128, 1);
+    let mut transcript = Transcript::new(b"R1CSExampleGadget");
+    let mut verifier = Verifier::new(&bp_gens, &pc_gens, &mut transcript);
+    let vars: Vec<_> = commitments1.iter().map(|V| verifier.commit(*V)).collect();
+
+    let mut cs = verifier.finalize_inputs();
+    example_gadget(
+        &mut cs,
+        vars[0].into(),
+        vars[1].into(),
+        vars[2].into(),
+        vars[3].into(),
+        vars[4].into(),
+        Scalar::from(9u64).into(),
+    );
+    assert!(cs
+        .verify_with_rng(&proof1, &mut StdRng::seed_from_u64(7))
+        .is_ok());
+}
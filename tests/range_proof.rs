@@ -142,3 +142,48 @@ fn generate_test_vectors() {
 
     panic!();
 }
+
+// Proofs created with a deterministic RNG, which lock the wire format:
+// (n, m, seed, proof) for the values 0..m with blindings 1..=m.
+//
+// Each proof is made by `RangeProof::prove_multiple_with_rng` under a
+// `Transcript::new(b"Deterministic Test Vectors")`, with
+// `ChaChaRng::from_seed([seed; 32])` as the RNG.  The prover seeds
+// one ChaCha20 RNG per party from it, in party order, with
+// `ChaChaRng::from_rng`, and draws all of the party's blinding
+// factors from that.  Changing how the party RNGs are derived
+// changes these vectors.
+static DETERMINISTIC_PROOFS: [(usize, usize, u8, &str); 3] = [
+    (8, 1, 1, "2a279774ac2ef2b31247075ea605a01a03e1343db2d93cc0bc58df00f47c8f0010de7378354e3f9710714cbe27b67b9ac032cc8a4e35695e4a7b7b314fbba7551cee288fb1187f8367368db299f4d9194f3b934c339a7ce5f1fc0f5355ba8a3f4e6a3bcc3da65a16df3104745cc9c521bbd120b76b56404097b149da30e3ed365fa848585825880f82af77b010f9e9ca1d7919d5012554d8be53066ef6cb3602ae9a8f026f8f73cb5cc15a4ef7b69b78c171b0d408b5ab1bf1be3ba947f18b0c7fb43a62593db81a315badacc1fe5d2c127a6cbfdd1a940402fbef347c5e5903dc03ab423910acd3119e3f2b1989c012481bb856b335ab3b0815ef0024693866ea6511ffbb63513554f8c8ff8c90adbf351b88453d06483795f73f4d8684a5513cfd69d7cfb5347a469df90aa25c532d935a6e01d5bcb8a9df03ed87cef5f0767c8e8e1888d00ce7e4099cc13786a742434b70c89e3be8d60b36f7b1c065ca43c66ce428bd8ae58d36121cbb2653efa2e20e6f819b4128973378e2c8a953915486cddfedd0ee49a3b57680f5e9f2ef14f5df298ba0f1ae7c88180d08a63545727f7095eca50ff1aa100ab24c7c6cd33766fba8114cae3325086c729d5b793b03dc67b42e421ed7f6e96dcbbcfba911fae49c152919669ba65d10282938f82a0b"),
+    (32, 1, 2, "a2cae11e09629ea354231532e62876d485a202e5a71ac33049db10cd7aeda5545e48e71719738c0df1c563ec669035cca670972b2bb1bd81f86de5f31ce5887f4c4aca34918859d6ecb43af4a5982bc223106ae27540e5d4f56d229d5f167370803cc43e6f81b363e0598d9a33bffa08acdec16216d098b44f8704f8e7172149b4f19377d7a014ef0cb3a5456afdad7ee90e02641cbde22317d5497947a4ad03c6e69b6c979dfe066b714bed2dcbd446fc2ad50865822815f43ceab30b5897099a7660a2da1b793bb116b75132736c02bb9770dc27cadde216835bff0b785e0ff623525ad730cdd2e8dd398d6db7e93007d304fbb84ed5a7ad3c2f3beb8f2a0582b1b203fbb7ab272d82231633caa36d397373fffbc32dab660e6d1c90d3ba5ecc6410cb4c40f4e97b4d9ee4c9810a82ac50ac4914ee7e12600270cb8fdd5311d0342d8d92cd2a057bf07887e911dd5c7ac937e202f5abf1eff44314d3239a6f4a3e6e60ba5c7ec6d92613f1f71693cc57cb24bf11f8a9fbdbbf3168ae77d432e028a5559608deeb6a13ea8cb7276704af23a243723bb153ebbca507714acf1da6771c2e5b6564da8644686e8475b5209f033eda22e2522374e21c9c7223841712fc7551f6af23ae74988c8e7141330d8669250ff87c8eb63f32137d37f2587bbe4c6d7864b87e4c0b8a4677d59f7d63036abdc47a85feaaf1b0cada10c9665d603f195afcde0ace4a2c00ccc5ab2b702d2e53ce15a2dafa69e22a260f2a4e103b4e33cea8c3a991a9f7d07e4b276a3944e239345d41a00311ed58d0192cbb0a4d902d025586ab6a254209f4eb6c7eda041d7cbc3899ff7a28c4d9fc802de801"),
+    (16, 2, 3, "08473427ba20653f4de4391c81b139ae7358164321f6ade3a91cf8a434361361427bad08e089743610db9de93420018ec0e8f4347424af376d83408749bd886234d5da0f5bcac6431625cdab051a48d12f29c2fef53ea2f471301e430879795b4e299c122e86a38b8319e360937b87207634600bbfc960e6fe34ce488282665ee7c52d31da097b748fac190b3b9d957ab75ae0b7ca1c0bdc8f38d20c2fd19c0b2cd2b3b0afabda23a1c861add46ab2855d88e5fe4cb601d68dc62bd6975b6305582bc0395d4c2001da55fe2217622a0219068f856882f89646d49c6e6f55d50762a55ba4d490e5f28fbbc3b22e042824246dad6eca01869403a792e0cc35de7be641c9fa6c2c405a5c1ec88f9327ee77e1d625fa6083575993a20acd62f0d64654f3d4a583cfd0111474fdf4b33ab16069e9e53149c99559e00f9fa5a94f60434844972364b819b6d749919ee79c4a85c56e02cd0ea19c3d38d37b6b89629f43fe4b5adb43dbe0a5fd05cdb0a0568426fba981ca5107bb45e6b38390bb4d8b7f207891fcb455c4e0e2ac828748d23e30cdfb51a7cf5cb57801a011fb51446e701e051104243d175d137cfb13bfe292334e96daaedecb3ef872e0a7bcf98bfd32e6d886585b661cbaac2b5cfe5b677044ae9a8fd0bf8b91ce322417473449d24baad5be0a00644938f3a739b2402bb7b62be39ced90205a32f405615f9c85e3605ec6326859baf602c24f15a603c2b55d0a7018a046261a625c37d2b2a9986d4a0b130745cfc45d1ab640bf0894a1f6988930fc355d08d40ca18f3248e6e4de0cba366bb85bc47a67b6dfc0907df6a9d5bd235854657754ac619127e2160b9600"),
+];
+
+#[test]
+fn deterministic_proofs_match_test_vectors() {
+    let pc_gens = PedersenGens::default();
+    let bp_gens = BulletproofGens::new(64, 2);
+
+    for &(n, m, seed, expected) in DETERMINISTIC_PROOFS.iter() {
+        let values: Vec<u64> = (0..m as u64).collect();
+        let blindings: Vec<Scalar> = (1..=m as u64).map(Scalar::from).collect();
+
+        let mut transcript = Transcript::new(b"Deterministic Test Vectors");
+        let (proof, value_commitments) = RangeProof::prove_multiple_with_rng(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &values,
+            &blindings,
+            n,
+            &mut ChaChaRng::from_seed([seed; 32]),
+        )
+        .unwrap();
+        assert_eq!(hex::encode(proof.to_bytes()), expected);
+
+        let mut transcript = Transcript::new(b"Deterministic Test Vectors");
+        assert!(proof
+            .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, n)
+            .is_ok());
+    }
+}